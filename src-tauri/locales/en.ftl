@@ -0,0 +1,63 @@
+# Sentences of the commentary, the speech and the analysis reports. A language missing a message
+# falls back on the English one.
+
+piece = { $kind ->
+    [chick] chick
+    [elephant] elephant
+    [giraffe] giraffe
+    [hen] hen
+   *[lion] lion
+}
+
+# the piece as the object of a sentence
+piece-object = the { piece }
+
+player = { $player ->
+    [first] First player
+   *[second] Second player
+}
+
+# $piece is the name of the piece and $captured the piece taken with its article
+move = { $drop ->
+        [yes] { $piece } dropped on { $to }
+       *[no] { $piece } moves to { $to }
+    }{ $captured ->
+        [none] {""}
+       *[other] , capturing { $captured }
+    }{ $promotion ->
+        [promotes] {" "}and promotes to hen
+        [declines] {" "}and stays a chick
+       *[none] {""}
+    }{ $check ->
+        [yes] . Check.
+       *[no] .
+    }
+
+draw = { $reason ->
+    [agreement] Draw by agreement.
+    [move-limit] Draw by the move limit.
+    [adjudication] Draw by adjudication.
+   *[repetition] Draw by repetition.
+}
+
+win = { $player } wins { $reason ->
+    [lion-captured] by capturing the lion
+    [lion-reached-camp] by bringing the lion to the last rank
+    [resignation] by resignation
+    [adjudication] by adjudication
+   *[time] on time
+}.
+
+best-move = { $move } is the best move.
+mistake = { $severity ->
+    [inaccuracy] { $move } is inaccurate, { $best } was better.
+    [material] { $move } loses material, { $best } was better.
+   *[decisive] { $move } loses the game, { $best } was necessary.
+}
+
+evaluation = { $verdict ->
+    [balanced] The position is balanced.
+    [better] { $player } is slightly better.
+    [ahead] { $player } is ahead.
+   *[winning] { $player } has a winning position.
+}
@@ -0,0 +1,64 @@
+piece = { $kind ->
+    [chick] poussin
+    [elephant] éléphant
+    [giraffe] girafe
+    [hen] poule
+   *[lion] lion
+}
+
+piece-object = { $kind ->
+    [chick] le poussin
+    [elephant] l'éléphant
+    [giraffe] la girafe
+    [hen] la poule
+   *[lion] le lion
+}
+
+player = { $player ->
+    [first] Le premier joueur
+   *[second] Le second joueur
+}
+
+move = { $drop ->
+        [yes] { $piece } parachuté en { $to }
+       *[no] { $piece } en { $to }
+    }{ $captured ->
+        [none] {""}
+       *[other] , prend { $captured }
+    }{ $promotion ->
+        [promotes] , promu en poule
+        [declines] , reste poussin
+       *[none] {""}
+    }{ $check ->
+        [yes] . Échec.
+       *[no] .
+    }
+
+draw = { $reason ->
+    [agreement] Partie nulle par accord mutuel.
+    [move-limit] Partie nulle, le nombre maximal de coups est atteint.
+    [adjudication] Partie nulle par arbitrage.
+   *[repetition] Partie nulle par répétition.
+}
+
+win = { $player } gagne { $reason ->
+    [lion-captured] en capturant le lion
+    [lion-reached-camp] en amenant son lion sur la dernière rangée
+    [resignation] par abandon
+    [adjudication] par arbitrage
+   *[time] au temps
+}.
+
+best-move = { $move } est le meilleur coup.
+mistake = { $severity ->
+    [inaccuracy] { $move } est imprécis, { $best } était meilleur.
+    [material] { $move } perd du matériel, { $best } était meilleur.
+   *[decisive] { $move } perd la partie, { $best } s'imposait.
+}
+
+evaluation = { $verdict ->
+    [balanced] La position est équilibrée.
+    [better] { $player } est légèrement mieux.
+    [ahead] { $player } a l'avantage.
+   *[winning] { $player } a une position gagnante.
+}
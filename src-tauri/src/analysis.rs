@@ -0,0 +1,130 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
+use crate::session::Session;
+use crate::endgame;
+use crate::shogi::{self, Limits, Move, Pieces, Search, SearchControl};
+use crate::timeman;
+
+// the background search never goes deeper than this
+const MAX_DEPTH: u8 = 14;
+
+#[derive(Clone, Copy, Serialize)]
+pub struct EvalUpdate {
+    ply: usize, // lets the frontend discard updates for an outdated position, the node on the analysis board
+    depth: u8,
+    score: i32,
+}
+
+// background evaluation of the current position, used for the eval bar
+#[derive(Default)]
+pub struct Analysis {
+    running: Mutex<Option<(Arc<SearchControl>, JoinHandle<()>)>>,
+    latest: Arc<Mutex<Option<EvalUpdate>>>, // kept for the stream overlay
+}
+
+impl Analysis {
+    // returns once the background search has released the CPU
+    pub fn stop(&self) {
+        if let Some((control, handle)) = self.running.lock().unwrap().take() {
+            control.stop();
+            let _ = handle.join();
+        }
+    }
+
+    pub fn latest(&self) -> Option<EvalUpdate> {
+        *self.latest.lock().unwrap()
+    }
+
+    fn start(&self, app: AppHandle, session: &Session) {
+        self.start_position(app, *session.pieces(), session.turn(), session.ply());
+    }
+
+    pub fn start_position(&self, app: AppHandle, pieces: Pieces, turn: bool, ply: usize) {
+        self.stop();
+        let control = Arc::new(SearchControl::default());
+        let mut search = Search::new(control.clone());
+        let latest = self.latest.clone();
+        let handle = thread::spawn(move || {
+            for depth in 1..=timeman::depth(MAX_DEPTH) {
+                let Some(score) = search.evaluate(&pieces, turn, depth) else { break };
+                let update = EvalUpdate { ply, depth, score };
+                *latest.lock().unwrap() = Some(update);
+                let _ = app.emit("eval", update);
+                if score.abs() >= 100000 { // forced win found
+                    break;
+                }
+            }
+        });
+        *self.running.lock().unwrap() = Some((control, handle));
+    }
+}
+
+#[tauri::command]
+pub fn start_analysis(app: AppHandle, session: State<Mutex<Session>>, analysis: State<Analysis>) {
+    let session = session.lock().unwrap();
+    if session.result().is_none() {
+        analysis.start(app, &session);
+    }
+}
+
+#[tauri::command]
+pub fn stop_analysis(analysis: State<Analysis>) {
+    analysis.stop();
+}
+
+#[derive(Clone, Copy, Deserialize)]
+pub struct BatchPosition {
+    pub pieces: Pieces,
+    pub turn: bool,
+}
+
+#[derive(Clone, Serialize)]
+pub struct BatchEvaluation {
+    pub score: i32, // from the point of view of the first player
+    pub pv: Vec<Move>, // empty when the game is over in the position
+}
+
+fn evaluate_one(search: &mut Search, position: BatchPosition, depth: u8) -> BatchEvaluation {
+    let BatchPosition { pieces, turn } = position;
+    if endgame::lost(&pieces, turn) {
+        return BatchEvaluation { score: if turn { 100000 } else { -100000 }, pv: vec![] };
+    }
+    match shogi::best_move(search, &pieces, &[], Limits::depth(depth), turn, &mut |_| ()) {
+        Some(result) => BatchEvaluation { score: result.score, pv: result.pv },
+        // a player without a move has lost
+        None => BatchEvaluation { score: if turn { 100000 } else { -100000 }, pv: vec![] },
+    }
+}
+
+// Many positions in one call, for the game analysis and the puzzle generator. The positions are
+// shared among one thread per core, each with its own search, a single one in deterministic mode.
+pub fn evaluate_all(positions: &[BatchPosition], depth: u8) -> Vec<BatchEvaluation> {
+    let cores = if shogi::deterministic() { 1 } else { timeman::threads(thread::available_parallelism().map_or(1, |n| n.get())) };
+    let threads = cores.min(positions.len());
+    let next = AtomicUsize::new(0);
+    let mut evaluations: Vec<(usize, BatchEvaluation)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads).map(|_| scope.spawn(|| {
+            let mut search = Search::default();
+            let mut done = vec![];
+            loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(&position) = positions.get(i) else { break done };
+                done.push((i, evaluate_one(&mut search, position, depth)));
+            }
+        })).collect();
+        workers.into_iter().flat_map(|worker| worker.join().unwrap()).collect()
+    });
+    evaluations.sort_by_key(|&(i, _)| i);
+    evaluations.into_iter().map(|(_, evaluation)| evaluation).collect()
+}
+
+#[tauri::command(async)]
+pub fn evaluate_batch(positions: Vec<BatchPosition>, depth: u8) -> Result<Vec<BatchEvaluation>, String> {
+    for position in &positions {
+        shogi::validate(&position.pieces)?;
+    }
+    Ok(evaluate_all(&positions, depth))
+}
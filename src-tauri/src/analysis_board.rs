@@ -0,0 +1,227 @@
+use std::sync::Mutex;
+use serde::Serialize;
+use tauri::{AppHandle, State};
+use crate::analysis::Analysis;
+use crate::correspondence;
+use crate::endgame;
+use crate::engine::{Engine, Message};
+use crate::notation;
+use crate::session::{GameStateView, Session};
+use crate::shogi::{self, Move, Pieces, Rules};
+use crate::store::Store;
+
+// Free analysis, apart from the game: no clock, the user moves both sides and every line tried is
+// kept as a variation in a tree of positions. The background evaluation follows the position shown.
+
+struct Node {
+    pieces: Pieces,
+    turn: bool,
+    mov: Option<Move>, // None at the root
+    parent: Option<usize>,
+    children: Vec<usize>, // the main line first
+}
+
+pub struct AnalysisBoard {
+    nodes: Vec<Node>,
+    current: usize,
+    rules: Rules,
+}
+
+impl Default for AnalysisBoard {
+    fn default() -> Self {
+        AnalysisBoard::new(shogi::initial_pieces(), false, Rules::default())
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeView {
+    pub index: usize,
+    pub parent: Option<usize>,
+    pub mov: Option<String>,
+    pub children: Vec<usize>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnalysisBoardView {
+    pub nodes: Vec<NodeView>,
+    pub current: usize,
+    pub line: Vec<usize>, // the nodes from the root to the current one
+    pub position: GameStateView,
+}
+
+impl AnalysisBoard {
+    fn new(pieces: Pieces, turn: bool, rules: Rules) -> Self {
+        AnalysisBoard { nodes: vec![Node { pieces, turn, mov: None, parent: None, children: vec![] }], current: 0, rules }
+    }
+
+    fn node(&self) -> &Node {
+        &self.nodes[self.current]
+    }
+
+    // an existing variation is followed, a new move starts a new one
+    fn play(&mut self, mov: Move) -> Result<(), String> {
+        let Node { pieces, turn, .. } = *self.node();
+        if endgame::lost(&pieces, turn) {
+            return Err("the game is over in this position".into());
+        }
+        let mov = shogi::legal_move(&pieces, &self.rules, mov.from, mov.to, Some(mov.promote))
+            .filter(|mov| pieces[mov.from].owner == turn)
+            .ok_or("illegal move")?;
+        if let Some(&child) = self.node().children.iter().find(|&&child| self.nodes[child].mov == Some(mov)) {
+            self.current = child;
+            return Ok(());
+        }
+        let index = self.nodes.len();
+        self.nodes.push(Node { pieces: shogi::play_move(&pieces, mov), turn: !turn, mov: Some(mov), parent: Some(self.current), children: vec![] });
+        self.nodes[self.current].children.push(index);
+        self.current = index;
+        Ok(())
+    }
+
+    fn line(&self) -> Vec<usize> {
+        let mut line = vec![self.current];
+        while let Some(parent) = self.nodes[*line.last().unwrap()].parent {
+            line.push(parent);
+        }
+        line.reverse();
+        line
+    }
+
+    // the node and its subtree, the root stays; the indices of the other nodes change
+    fn delete(&mut self, index: usize) -> Result<(), String> {
+        let parent = self.nodes.get(index).ok_or("no such node")?.parent.ok_or("the root cannot be deleted")?;
+        let mut removed = vec![false; self.nodes.len()];
+        let mut stack = vec![index];
+        while let Some(i) = stack.pop() {
+            removed[i] = true;
+            stack.extend(&self.nodes[i].children);
+        }
+        if removed[self.current] {
+            self.current = parent;
+        }
+        let mut renumbered = vec![0; self.nodes.len()];
+        let mut next = 0;
+        for (i, &gone) in removed.iter().enumerate() {
+            renumbered[i] = next;
+            next += !gone as usize;
+        }
+        let nodes = std::mem::take(&mut self.nodes);
+        self.nodes = nodes.into_iter().zip(&removed).filter(|(_, &gone)| !gone).map(|(mut node, _)| {
+            node.parent = node.parent.map(|parent| renumbered[parent]);
+            node.children = node.children.iter().filter(|&&child| !removed[child]).map(|&child| renumbered[child]).collect();
+            node
+        }).collect();
+        self.current = renumbered[self.current];
+        Ok(())
+    }
+
+    fn view(&self) -> AnalysisBoardView {
+        let nodes = self.nodes.iter().enumerate().map(|(index, node)| NodeView {
+            index,
+            parent: node.parent,
+            mov: node.parent.zip(node.mov).map(|(parent, mov)| notation::move_name(&self.nodes[parent].pieces, mov)),
+            children: node.children.clone(),
+        }).collect();
+        let Node { pieces, turn, .. } = *self.node();
+        let mut position = Session::from_position(pieces, turn);
+        position.set_rules(self.rules);
+        AnalysisBoardView { nodes, current: self.current, line: self.line(), position: position.view() }
+    }
+
+    // the evaluation of the position shown
+    fn analyze(&self, app: AppHandle, analysis: &Analysis) {
+        let Node { pieces, turn, .. } = *self.node();
+        if endgame::lost(&pieces, turn) {
+            analysis.stop();
+        } else {
+            analysis.start_position(app, pieces, turn, self.current);
+        }
+    }
+}
+
+// a new board from "position", by default from the position of the game
+#[tauri::command]
+pub fn open_analysis_board(
+    app: AppHandle,
+    board: State<Mutex<AnalysisBoard>>,
+    session: State<Mutex<Session>>,
+    analysis: State<Analysis>,
+    position: Option<String>,
+) -> Result<AnalysisBoardView, String> {
+    let (pieces, turn, rules) = match position {
+        Some(position) => {
+            let (pieces, turn) = notation::parse_position(&position).ok_or("invalid position")?;
+            (pieces, turn, Rules::default())
+        }
+        None => {
+            let session = session.lock().unwrap();
+            (*session.pieces(), session.turn(), session.rules())
+        }
+    };
+    let mut board = board.lock().unwrap();
+    *board = AnalysisBoard::new(pieces, turn, rules);
+    board.analyze(app, &analysis);
+    Ok(board.view())
+}
+
+#[tauri::command]
+pub fn analysis_board_state(board: State<Mutex<AnalysisBoard>>) -> AnalysisBoardView {
+    board.lock().unwrap().view()
+}
+
+#[tauri::command]
+pub fn analysis_board_play(
+    app: AppHandle,
+    board: State<Mutex<AnalysisBoard>>,
+    analysis: State<Analysis>,
+    from: usize,
+    to: usize,
+    promote: Option<bool>,
+) -> Result<AnalysisBoardView, String> {
+    let mut board = board.lock().unwrap();
+    board.play(Move { from, to, promote: promote.unwrap_or(true) })?;
+    board.analyze(app, &analysis);
+    Ok(board.view())
+}
+
+#[tauri::command]
+pub fn analysis_board_goto(app: AppHandle, board: State<Mutex<AnalysisBoard>>, analysis: State<Analysis>, node: usize) -> Result<AnalysisBoardView, String> {
+    let mut board = board.lock().unwrap();
+    if node >= board.nodes.len() {
+        return Err("no such node".into());
+    }
+    board.current = node;
+    board.analyze(app, &analysis);
+    Ok(board.view())
+}
+
+#[tauri::command]
+pub fn analysis_board_delete(app: AppHandle, board: State<Mutex<AnalysisBoard>>, analysis: State<Analysis>, node: usize) -> Result<AnalysisBoardView, String> {
+    let mut board = board.lock().unwrap();
+    board.delete(node)?;
+    board.analyze(app, &analysis);
+    Ok(board.view())
+}
+
+// a new game against the engine from the position shown, under the rules of the board
+#[tauri::command]
+pub fn play_from_analysis_board(
+    board: State<Mutex<AnalysisBoard>>,
+    session: State<Mutex<Session>>,
+    engine: State<Engine>,
+    analysis: State<Analysis>,
+    store: State<Store>,
+) -> GameStateView {
+    let board = board.lock().unwrap();
+    let Node { pieces, turn, .. } = *board.node();
+    analysis.stop();
+    engine.send(Message::Stop);
+    engine.send(Message::NewGame);
+    let mut session = session.lock().unwrap();
+    *session = Session::from_position(pieces, turn);
+    session.set_rules(board.rules);
+    correspondence::save(&store, &session);
+    session.view()
+}
@@ -0,0 +1,48 @@
+use serde::Serialize;
+use crate::import::{self, ImportFormat, Offense};
+use crate::notation;
+use crate::session::{GameResult, Session};
+use crate::shogi::Rules;
+
+// Checks a whole game record, for imports, tournament results and bug reports.
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Verdict {
+    pub format: ImportFormat,
+    pub offense: Option<Offense>, // the first move that cannot be played, None if the record is sound
+    pub moves: usize, // moves of the record played
+    pub result: Option<GameResult>, // once the last move is played
+    pub position: Option<String>, // the final position, in the notation of position strings
+}
+
+// the record starts from "start", a position string or a share code, or from the initial position
+pub fn arbitrate(record: &str, start: Option<&str>, rules: Rules) -> Result<Verdict, String> {
+    let mut session = match start.map(str::trim) {
+        Some(start) => {
+            let (pieces, turn) = notation::parse_position(start)
+                .or_else(|| notation::parse_share_code(start))
+                .ok_or("the starting position cannot be read")?;
+            Session::from_position(pieces, turn)
+        }
+        None => Session::default(),
+    };
+    session.set_rules(rules);
+    let (format, played) = import::parse_record(session, record.trim());
+    Ok(match played {
+        Ok(session) => Verdict {
+            format,
+            offense: None,
+            moves: session.ply(),
+            result: session.result(),
+            position: Some(notation::position_string(session.pieces(), session.turn())),
+        },
+        // the number is read from the record, where it may be 0
+        Err(offense) => Verdict { format, moves: offense.number.saturating_sub(1), offense: Some(offense), result: None, position: None },
+    })
+}
+
+#[tauri::command]
+pub fn validate_record(record: String, start: Option<String>, rules: Option<Rules>) -> Result<Verdict, String> {
+    arbitrate(&record, start.as_deref(), rules.unwrap_or_default())
+}
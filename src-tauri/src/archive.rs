@@ -0,0 +1,174 @@
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use crate::correspondence;
+use crate::notation;
+use crate::session::{GameResult, Session};
+use crate::shogi::{self, Move, Rules};
+use crate::store::Store;
+
+// The finished games, kept with their metadata so that they can be searched and exported later.
+
+pub const FILE: &str = "games.json";
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GameInfo {
+    pub players: [String; 2], // the first player first
+    pub event: String,
+    pub round: String,
+    pub notes: String,
+    pub starred: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchivedGame {
+    pub id: u32,
+    pub info: GameInfo,
+    pub start: String, // position string of the position the game started from
+    pub moves: Vec<Move>,
+    pub move_list: Vec<String>,
+    #[serde(default)]
+    pub rules: Rules,
+    pub result: Option<GameResult>,
+    pub ended_ms: u64, // UTC timestamp
+}
+
+// every field given must match, "text" is looked for in the players, the event and the notes
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GameQuery {
+    pub text: Option<String>,
+    pub player: Option<String>,
+    pub event: Option<String>,
+    pub starred: Option<bool>,
+}
+
+impl GameQuery {
+    fn matches(&self, game: &ArchivedGame) -> bool {
+        let contains = |field: &str, text: &str| field.to_lowercase().contains(&text.to_lowercase());
+        let info = &game.info;
+        self.text.as_ref().is_none_or(|text| {
+            info.players.iter().any(|player| contains(player, text)) || contains(&info.event, text) || contains(&info.notes, text)
+        })
+            && self.player.as_ref().is_none_or(|player| info.players.iter().any(|name| contains(name, player)))
+            && self.event.as_ref().is_none_or(|event| contains(&info.event, event))
+            && self.starred.is_none_or(|starred| info.starred == starred)
+    }
+}
+
+pub fn load(store: &Store) -> Vec<ArchivedGame> {
+    store.load(FILE).unwrap_or_default()
+}
+
+// The game of the session once it is over, it replaces its previous record when the end was taken
+// back and the game finished again. Returns the record saved.
+pub fn record(store: &Store, session: &mut Session) -> Option<ArchivedGame> {
+    let result = session.result()?;
+    let mut games = load(store);
+    let id = session.archived().unwrap_or_else(|| games.iter().map(|game| game.id + 1).max().unwrap_or(1));
+    let (start, turn) = session.start();
+    let moves = session.moves();
+    let game = ArchivedGame {
+        id,
+        info: session.info().clone(),
+        start: notation::position_string(&start, turn),
+        move_list: notation::move_list(&start, &moves),
+        moves,
+        rules: session.rules(),
+        result: Some(result),
+        ended_ms: correspondence::now_ms(),
+    };
+    games.retain(|game| game.id != id);
+    games.push(game.clone());
+    match store.save(FILE, &games) {
+        Ok(()) => {
+            session.set_archived(id);
+            Some(game)
+        }
+        Err(err) => {
+            eprintln!("cannot save the game: {err}");
+            None
+        }
+    }
+}
+
+// a game from elsewhere, returned under its new id
+pub fn add(store: &Store, mut game: ArchivedGame) -> Result<ArchivedGame, String> {
+    let mut games = load(store);
+    game.id = games.iter().map(|game| game.id + 1).max().unwrap_or(1);
+    games.push(game.clone());
+    store.save(FILE, &games)?;
+    Ok(game)
+}
+
+fn result_tag(result: Option<GameResult>) -> &'static str {
+    match result.map(|result| result.winner) {
+        Some(Some(false)) => "1-0",
+        Some(Some(true)) => "0-1",
+        Some(None) => "1/2-1/2",
+        None => "*",
+    }
+}
+
+// A move list preceded by the metadata as tag pairs, which the import skips
+pub fn export(game: &ArchivedGame) -> String {
+    let info = &game.info;
+    let mut tags = vec![
+        ("Event", info.event.clone()),
+        ("Round", info.round.clone()),
+        ("First", info.players[0].clone()),
+        ("Second", info.players[1].clone()),
+        ("Result", result_tag(game.result).to_string()),
+    ];
+    if game.start != notation::position_string(&shogi::initial_pieces(), false) {
+        tags.push(("Position", game.start.clone()));
+    }
+    if info.starred {
+        tags.push(("Starred", "yes".into()));
+    }
+    if !info.notes.is_empty() {
+        tags.push(("Notes", info.notes.replace('"', "'").replace('\n', " ")));
+    }
+    let mut lines: Vec<String> = tags.into_iter().map(|(name, value)| format!("[{name} \"{value}\"]")).collect();
+    lines.push(String::new());
+    let moves: Vec<String> = game.move_list.chunks(2).enumerate().map(|(i, pair)| format!("{}. {}", i + 1, pair.join(" "))).collect();
+    lines.push(format!("{} {}", moves.join(" "), result_tag(game.result)));
+    lines.join("\n")
+}
+
+// the metadata of the current game, and of its record once it is over
+#[tauri::command]
+pub fn set_game_info(session: State<Mutex<Session>>, store: State<Store>, info: GameInfo) {
+    let mut session = session.lock().unwrap();
+    session.set_info(info);
+    correspondence::save(&store, &session);
+    if session.archived().is_some() {
+        record(&store, &mut session);
+    }
+}
+
+#[tauri::command]
+pub fn update_archived_game(store: State<Store>, id: u32, info: GameInfo) -> Result<ArchivedGame, String> {
+    let mut games = load(&store);
+    let game = games.iter_mut().find(|game| game.id == id).ok_or("no such game")?;
+    game.info = info;
+    let game = game.clone();
+    store.save(FILE, &games)?;
+    Ok(game)
+}
+
+// the games matching the query, the most recent first
+#[tauri::command]
+pub fn search_games(store: State<Store>, query: Option<GameQuery>) -> Vec<ArchivedGame> {
+    let query = query.unwrap_or_default();
+    let mut games: Vec<ArchivedGame> = load(&store).into_iter().filter(|game| query.matches(game)).collect();
+    games.sort_by_key(|game| std::cmp::Reverse(game.ended_ms));
+    games
+}
+
+#[tauri::command]
+pub fn export_game(store: State<Store>, id: u32) -> Result<String, String> {
+    load(&store).iter().find(|game| game.id == id).map(export).ok_or_else(|| "no such game".into())
+}
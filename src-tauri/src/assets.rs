@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, LazyLock, RwLock};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Emitter, State};
+use crate::correspondence;
+use crate::nn::Network;
+use crate::notation;
+use crate::selfplay::Rng;
+use crate::shogi::{Move, Pieces, Rules, SearchResult};
+use crate::store::Store;
+
+// Optional large files downloaded on demand into the data directory rather than shipped with the
+// installer. The frontend gives where to get each one and its SHA-256, from the release notes or
+// a manifest; the file is downloaded next to its place, checked, then moved in at once, so that
+// a failed or interrupted download never replaces a good file. Without network the app goes on
+// with what it has: the installed file if any, the built-in engine otherwise.
+// The opening book gives the engine its first moves, the network weights are where the training
+// starts when the user has no network of their own.
+
+const INDEX_FILE: &str = "assets.json";
+const ASSETS_DIR: &str = "assets";
+const CHUNK: usize = 64 * 1024;
+// progress events at most every this many bytes
+const PROGRESS_STEP: u64 = 1024 * 1024;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AssetKind {
+    NetworkWeights,
+    OpeningBook,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetSource {
+    pub kind: AssetKind,
+    pub url: String,
+    pub sha256: String, // hexadecimal
+    pub size: Option<u64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstalledAsset {
+    pub kind: AssetKind,
+    pub sha256: String,
+    pub size: u64,
+    pub source: String,
+    pub installed_ms: u64, // UTC timestamp
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AssetProgress {
+    kind: AssetKind,
+    received: u64,
+    total: Option<u64>,
+}
+
+fn file_name(kind: AssetKind) -> String {
+    match kind {
+        // apart from the network trained by the user, which it never replaces
+        AssetKind::NetworkWeights => format!("{ASSETS_DIR}/network.json"),
+        AssetKind::OpeningBook => format!("{ASSETS_DIR}/book.json"),
+    }
+}
+
+pub fn installed(store: &Store) -> Vec<InstalledAsset> {
+    store.load(INDEX_FILE).unwrap_or_default()
+}
+
+// the file of an installed asset, None to fall back on the built-in engine
+pub fn path(store: &Store, kind: AssetKind) -> Option<PathBuf> {
+    let path = store.path(&file_name(kind));
+    (installed(store).iter().any(|asset| asset.kind == kind) && path.exists()).then_some(path)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+// downloads "source" to "part", returns the size and the SHA-256 of what was received
+fn download(source: &AssetSource, part: &PathBuf, on_progress: &mut dyn FnMut(u64)) -> Result<(u64, String), String> {
+    let response = ureq::get(&source.url).call().map_err(|err| format!("cannot download {}: {err}", source.url))?;
+    let mut reader = response.into_reader();
+    let mut file = File::create(part).map_err(|err| err.to_string())?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; CHUNK];
+    let (mut received, mut reported) = (0u64, 0u64);
+    loop {
+        let n = reader.read(&mut buffer).map_err(|err| format!("download interrupted: {err}"))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+        file.write_all(&buffer[..n]).map_err(|err| err.to_string())?;
+        received += n as u64;
+        if received - reported >= PROGRESS_STEP {
+            reported = received;
+            on_progress(received);
+        }
+    }
+    on_progress(received);
+    Ok((received, hex(&hasher.finalize())))
+}
+
+// the asset downloaded, checked and moved in, nothing is downloaded if it is already there
+pub fn install(store: &Store, source: &AssetSource, on_progress: &mut dyn FnMut(u64)) -> Result<InstalledAsset, String> {
+    let sha256 = source.sha256.to_lowercase();
+    let mut index = installed(store);
+    if let Some(asset) = index.iter().find(|asset| asset.kind == source.kind && asset.sha256 == sha256) {
+        if store.path(&file_name(source.kind)).exists() {
+            return Ok(asset.clone());
+        }
+    }
+    let target = store.path(&file_name(source.kind));
+    if let Some(dir) = target.parent() {
+        fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    }
+    let part = target.with_extension("part");
+    let downloaded = download(source, &part, on_progress);
+    let checked = downloaded.and_then(|(size, actual)| {
+        if actual != sha256 {
+            return Err(format!("checksum mismatch: expected {sha256}, got {actual}"));
+        }
+        if source.size.is_some_and(|expected| expected != size) {
+            return Err(format!("size mismatch: expected {}, got {size}", source.size.unwrap_or_default()));
+        }
+        // the file must be readable before it replaces the current one
+        let text = fs::read_to_string(&part).map_err(|err| err.to_string())?;
+        let readable = match source.kind {
+            AssetKind::NetworkWeights => serde_json::from_str::<Network>(&text).map(drop),
+            AssetKind::OpeningBook => serde_json::from_str::<Book>(&text).map(drop),
+        };
+        readable.map_err(|err| format!("invalid {:?} file: {err}", source.kind))?;
+        Ok(size)
+    });
+    let size = match checked {
+        Ok(size) => size,
+        Err(err) => {
+            let _ = fs::remove_file(&part);
+            return Err(err);
+        }
+    };
+    fs::rename(&part, &target).map_err(|err| err.to_string())?;
+    let asset = InstalledAsset { kind: source.kind, sha256, size, source: source.url.clone(), installed_ms: correspondence::now_ms() };
+    index.retain(|other| other.kind != source.kind);
+    index.push(asset.clone());
+    store.save(INDEX_FILE, &index)?;
+    load(store);
+    Ok(asset)
+}
+
+// the moves of the book by position string, in the notation of the move list
+type Book = HashMap<String, Vec<String>>;
+
+static BOOK: LazyLock<RwLock<Option<Arc<Book>>>> = LazyLock::new(RwLock::default);
+
+// the installed book in use, at startup and after each change
+pub fn load(store: &Store) {
+    let book = path(store, AssetKind::OpeningBook).and_then(|_| store.load::<Book>(&file_name(AssetKind::OpeningBook)));
+    *BOOK.write().unwrap() = book.map(Arc::new);
+}
+
+// a move of the book in the position, under the standard rules only
+pub fn book_move(pieces: &Pieces, turn: bool, rules: &Rules) -> Option<SearchResult> {
+    if *rules != Rules::default() {
+        return None;
+    }
+    let book = BOOK.read().unwrap().clone()?;
+    let names = book.get(&notation::position_string(pieces, turn))?;
+    let moves: Vec<Move> = names.iter().filter_map(|name| notation::parse_move(pieces, turn, rules, name)).collect();
+    if moves.is_empty() {
+        return None;
+    }
+    // the same one every time in deterministic mode
+    let mov = moves[Rng::from_clock().below(moves.len())];
+    Some(SearchResult { mov, score: 0, depth: 0, pv: vec![mov] })
+}
+
+// the downloaded weights, for a first training
+pub fn network(store: &Store) -> Option<Network> {
+    path(store, AssetKind::NetworkWeights).and_then(|_| store.load(&file_name(AssetKind::NetworkWeights)))
+}
+
+#[tauri::command(async)]
+pub fn install_asset(app: AppHandle, store: State<Store>, source: AssetSource) -> Result<InstalledAsset, String> {
+    install(&store, &source, &mut |received| {
+        let _ = app.emit("asset-progress", AssetProgress { kind: source.kind, received, total: source.size });
+    })
+}
+
+#[tauri::command]
+pub fn installed_assets(store: State<Store>) -> Vec<InstalledAsset> {
+    installed(&store)
+}
+
+// back to the built-in engine for this kind
+#[tauri::command]
+pub fn remove_asset(store: State<Store>, kind: AssetKind) -> Result<(), String> {
+    let mut index = installed(&store);
+    index.retain(|asset| asset.kind != kind);
+    store.remove(&file_name(kind));
+    store.save(INDEX_FILE, &index)?;
+    load(&store);
+    Ok(())
+}
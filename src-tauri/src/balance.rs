@@ -0,0 +1,170 @@
+use std::fs;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+use crate::ladder::{self, LevelSetting};
+use crate::notation;
+use crate::selfplay::{self, Adjudication, Mover, Rng};
+use crate::session::Session;
+use crate::shogi::Rules;
+
+// Many fast games of the engine against itself under a variant, to see whether it is fair before
+// it is offered to the players: how often each side wins, how long the games last and how many
+// end in a draw. A handicap is a start position, the pie rule lets the second player take the
+// first move as their own once it is played.
+
+const DEFAULT_RANDOM_PLIES: usize = 4;
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Variant {
+    pub rules: Rules,
+    pub start: Option<String>, // a position string, the usual start by default
+    pub pie_rule: bool,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EngineConfig {
+    #[serde(flatten)]
+    pub setting: LevelSetting,
+    #[serde(default = "default_random_plies")]
+    pub random_plies: usize, // random moves after the start, so that the games differ
+    #[serde(default)]
+    pub adjudicate: bool, // games decided by the scores end sooner, at the cost of some accuracy
+}
+
+fn default_random_plies() -> usize {
+    DEFAULT_RANDOM_PLIES
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BalanceReport {
+    pub games: usize,
+    pub first_wins: usize, // the first player of the game results, whoever moves first in the start position
+    pub second_wins: usize,
+    pub draws: usize,
+    pub unfinished: usize, // stopped at the ply limit, counted as draws
+    pub first_win_rate: f64,
+    pub second_win_rate: f64,
+    pub decisive_rate: f64,
+    pub average_plies: f64,
+    pub swaps: Option<usize>, // under the pie rule, the games where the first move was taken
+    pub opener_score: Option<f64>, // under the pie rule, the points per game of whoever made the first move
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SimulationProgress {
+    game: usize,
+    games: usize,
+}
+
+fn start(variant: &Variant) -> Result<Session, String> {
+    let mut session = match &variant.start {
+        Some(position) => {
+            let (pieces, turn) = notation::parse_position(position).ok_or("invalid start position")?;
+            Session::from_position(pieces, turn)
+        }
+        None => Session::default(),
+    };
+    session.set_rules(variant.rules);
+    Ok(session)
+}
+
+// One game of "engine" against itself, with whether the first move was taken under the pie rule:
+// the first move is the engine's own, then the second player takes it when its search finds the
+// position better for the first player.
+fn play(variant: &Variant, engine: &EngineConfig, seed: u64) -> Result<(Session, bool), String> {
+    let mut session = start(variant)?;
+    let (mut first, mut second) = (ladder::mover(engine.setting, seed), ladder::mover(engine.setting, seed ^ 1));
+    let adjudication = engine.adjudicate.then(Adjudication::default);
+    let mut rng = Rng::new(seed);
+    let mut swapped = false;
+    let mut random_plies = engine.random_plies;
+    if variant.pie_rule {
+        let opening = first(&session).ok_or("no legal move in the start position")?.mov;
+        session.play(opening)?;
+        let score = second(&session).map_or(0, |result| result.score);
+        swapped = if session.turn() { score > 0 } else { score < 0 };
+        random_plies += 1;
+    }
+    // the player who takes the first move goes on with its pieces
+    let movers: [&mut Mover; 2] = if swapped { [&mut second, &mut first] } else { [&mut first, &mut second] };
+    Ok((selfplay::play_from(session, movers, random_plies, adjudication.as_ref(), &mut rng), swapped))
+}
+
+// "on_game" is called after each game with the number of games played
+pub fn run_simulation(variant: &Variant, games: usize, engine: &EngineConfig, rng: &mut Rng, on_game: &mut dyn FnMut(usize)) -> Result<BalanceReport, String> {
+    let mut report = BalanceReport { games, ..BalanceReport::default() };
+    let (mut plies, mut swaps, mut opener_points) = (0, 0, 0.0);
+    // the first move is made by the player to move in the start position
+    let opener = start(variant)?.turn();
+    for game in 0..games {
+        let (session, swapped) = play(variant, engine, rng.next_u64())?;
+        plies += session.ply();
+        swaps += swapped as usize;
+        match session.result() {
+            Some(result) => match result.winner {
+                Some(winner) => {
+                    if winner { report.second_wins += 1 } else { report.first_wins += 1 }
+                    opener_points += if (winner == opener) != swapped { 1.0 } else { 0.0 };
+                }
+                None => {
+                    report.draws += 1;
+                    opener_points += 0.5;
+                }
+            },
+            None => {
+                report.unfinished += 1;
+                opener_points += 0.5;
+            }
+        }
+        on_game(game + 1);
+    }
+    let games = games.max(1) as f64;
+    report.first_win_rate = report.first_wins as f64 / games;
+    report.second_win_rate = report.second_wins as f64 / games;
+    report.decisive_rate = (report.first_wins + report.second_wins) as f64 / games;
+    report.average_plies = plies as f64 / games;
+    if variant.pie_rule {
+        report.swaps = Some(swaps);
+        report.opener_score = Some(opener_points / games);
+    }
+    Ok(report)
+}
+
+#[tauri::command(async)]
+pub fn simulate(app: AppHandle, variant: Variant, games: usize, engine: EngineConfig) -> Result<BalanceReport, String> {
+    run_simulation(&variant, games, &engine, &mut Rng::from_clock(), &mut |game| {
+        let _ = app.emit("simulation-progress", SimulationProgress { game, games });
+    })
+}
+
+pub fn simulate_cli(args: &[String]) -> Result<(), String> {
+    let usage = || "usage: simulate <games> <depth> [<variant file>]".to_string();
+    let (games, depth, path) = match args {
+        [games, depth] => (games, depth, None),
+        [games, depth, path] => (games, depth, Some(path)),
+        _ => return Err(usage()),
+    };
+    let variant: Variant = match path {
+        Some(path) => {
+            let text = fs::read_to_string(path).map_err(|err| format!("cannot read {path}: {err}"))?;
+            serde_json::from_str(&text).map_err(|err| format!("invalid variant: {err}"))?
+        }
+        None => Variant::default(),
+    };
+    let games: usize = games.parse().map_err(|_| usage())?;
+    let setting = LevelSetting { depth: depth.parse().map_err(|_| usage())?, human: None };
+    let engine = EngineConfig { setting, random_plies: DEFAULT_RANDOM_PLIES, adjudicate: true };
+    let report = run_simulation(&variant, games, &engine, &mut Rng::from_clock(), &mut |game| eprintln!("{game}/{games}"))?;
+    println!("first player: {:.1}% ({} wins)", 100.0 * report.first_win_rate, report.first_wins);
+    println!("second player: {:.1}% ({} wins)", 100.0 * report.second_win_rate, report.second_wins);
+    println!("draws: {} ({} at the ply limit), decisive: {:.1}%", report.draws + report.unfinished, report.unfinished, 100.0 * report.decisive_rate);
+    println!("average length: {:.1} plies", report.average_plies);
+    if let (Some(swaps), Some(score)) = (report.swaps, report.opener_score) {
+        println!("pie rule: first move taken in {swaps} games, {score:.3} points per game for the player who made it");
+    }
+    Ok(())
+}
@@ -0,0 +1,127 @@
+use std::fmt::Write;
+use serde::Deserialize;
+use crate::notation;
+use crate::raster::{self, RenderOptions, PALETTE};
+use crate::session::LastMove;
+use crate::shogi::{self, Pieces, Standard, HAND};
+
+const DEFAULT_CELL: usize = 80;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ImageFormat {
+    #[default]
+    Svg,
+    Png,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BoardImageOptions {
+    #[serde(default)]
+    pub format: ImageFormat,
+    pub cell_size: Option<usize>, // in pixels
+    #[serde(default)]
+    pub flipped: bool,
+    #[serde(default)]
+    pub coordinates: bool,
+    pub last_move: Option<LastMove>,
+    pub path: Option<String>, // the image is also written to this file
+}
+
+fn color(index: usize) -> String {
+    let [r, g, b] = PALETTE[index];
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+// The same picture as the raster rendering, with text instead of pixel glyphs
+pub fn svg(pieces: &Pieces, options: &RenderOptions) -> String {
+    let cell = options.cell;
+    let bar = if options.eval_bar.is_some() { cell / 4 } else { 0 };
+    let (files, ranks) = (Standard::WIDTH, Standard::HEIGHT);
+    let (width, height) = (bar + files * cell, (ranks + 2) * cell);
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">\
+         <rect width=\"{width}\" height=\"{height}\" fill=\"{}\"/>",
+        color(0)
+    );
+    for y in [0, (ranks + 1) * cell] {
+        let _ = write!(svg, "<rect x=\"{bar}\" y=\"{y}\" width=\"{}\" height=\"{cell}\" fill=\"{}\"/>", files * cell, color(5));
+    }
+    let origin = |square: u8| {
+        let shown = notation::oriented(square, options.flipped) as usize;
+        (bar + shown % files * cell, (shown / files + 1) * cell)
+    };
+    for square in 0..Standard::SQUARES as u8 {
+        let (x, y) = origin(square);
+        let fill = if options.highlight.contains(&square) { color(4) } else { "none".into() };
+        let _ = write!(svg, "<rect x=\"{x}\" y=\"{y}\" width=\"{cell}\" height=\"{cell}\" fill=\"{fill}\" stroke=\"{}\"/>", color(1));
+        if options.coordinates {
+            let name = notation::square_name(square);
+            let shown = notation::oriented(square, options.flipped) as usize;
+            let (row, column) = (shown / files, shown % files);
+            let size = cell / 6;
+            if row == ranks - 1 {
+                let _ = write!(svg, "<text x=\"{}\" y=\"{}\" font-size=\"{size}\" font-family=\"sans-serif\" fill=\"{}\" \
+                                     text-anchor=\"end\">{}</text>", x + cell - size / 2, y + cell - size / 2, color(1), &name[..1]);
+            }
+            if column == 0 {
+                let _ = write!(svg, "<text x=\"{}\" y=\"{}\" font-size=\"{size}\" font-family=\"sans-serif\" fill=\"{}\">{}</text>",
+                               x + size / 2, y + size * 3 / 2, color(1), &name[1..]);
+            }
+        }
+    }
+    let mut in_hand = [0; 2];
+    for piece in pieces {
+        let turned = piece.owner != options.flipped;
+        let (x, y) = if piece.position == HAND {
+            let slot = &mut in_hand[piece.owner as usize];
+            *slot += 1;
+            let x = bar + (*slot - 1) * cell / 2 + cell / 4;
+            (x, if turned { cell / 2 } else { (ranks + 1) * cell + cell / 2 })
+        } else {
+            let (x, y) = origin(piece.position);
+            (x + cell / 2, y + cell / 2)
+        };
+        let size = if piece.position == HAND { cell / 3 } else { cell / 2 };
+        let angle = if turned { 180 } else { 0 };
+        let _ = write!(
+            svg,
+            "<text x=\"{x}\" y=\"{y}\" font-size=\"{size}\" font-family=\"sans-serif\" fill=\"{}\" \
+             text-anchor=\"middle\" dominant-baseline=\"central\" transform=\"rotate({angle} {x} {y})\">{}</text>",
+            color(2 + piece.owner as usize),
+            notation::kind_letter(piece.kind)
+        );
+    }
+    if let Some(score) = options.eval_bar {
+        let share = 1.0 / (1.0 + (-score as f64 / 300.0).exp());
+        let bottom_share = if options.flipped { 1.0 - share } else { share };
+        let bottom = (bottom_share * height as f64).round() as usize;
+        let (top_color, bottom_color) = if options.flipped { (color(2), color(3)) } else { (color(3), color(2)) };
+        let _ = write!(svg, "<rect width=\"{bar}\" height=\"{}\" fill=\"{top_color}\"/>", height - bottom);
+        let _ = write!(svg, "<rect y=\"{}\" width=\"{bar}\" height=\"{bottom}\" fill=\"{bottom_color}\"/>", height - bottom);
+    }
+    svg + "</svg>"
+}
+
+// The image of any position for puzzles and forum posts, the content of the SVG or PNG file
+#[tauri::command(async)]
+pub fn export_board_image(pieces: Pieces, options: Option<BoardImageOptions>) -> Result<Vec<u8>, String> {
+    shogi::validate(&pieces)?;
+    let options = options.unwrap_or_default();
+    let render = RenderOptions {
+        cell: options.cell_size.unwrap_or(DEFAULT_CELL).clamp(16, 256),
+        flipped: options.flipped,
+        coordinates: options.coordinates,
+        highlight: options.last_move.map_or(vec![], |m| m.from.into_iter().chain([m.to]).collect()),
+        eval_bar: None,
+    };
+    let bytes = match options.format {
+        ImageFormat::Svg => svg(&pieces, &render).into_bytes(),
+        ImageFormat::Png => raster::png_bytes(&raster::render(&pieces, &render))?,
+    };
+    if let Some(path) = &options.path {
+        std::fs::write(path, &bytes).map_err(|err| err.to_string())?;
+    }
+    Ok(bytes)
+}
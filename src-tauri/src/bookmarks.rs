@@ -0,0 +1,79 @@
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use crate::analysis::Analysis;
+use crate::correspondence;
+use crate::notation;
+use crate::session::{GameStateView, Session};
+use crate::store::Store;
+
+// Positions the user wants to come back to, with a note.
+
+pub const FILE: &str = "bookmarks.json";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Bookmark {
+    pub id: u32,
+    pub position: String, // position string
+    pub note: String,
+    pub created_ms: u64, // UTC timestamp
+}
+
+pub fn load(store: &Store) -> Vec<Bookmark> {
+    store.load(FILE).unwrap_or_default()
+}
+
+pub fn add(store: &Store, position: String, note: String) -> Result<Bookmark, String> {
+    let mut bookmarks = load(store);
+    let id = bookmarks.iter().map(|bookmark| bookmark.id + 1).max().unwrap_or(1);
+    let bookmark = Bookmark { id, position, note, created_ms: correspondence::now_ms() };
+    bookmarks.push(bookmark.clone());
+    store.save(FILE, &bookmarks)?;
+    Ok(bookmark)
+}
+
+// "position" defaults to the current position of the game
+#[tauri::command]
+pub fn add_bookmark(session: State<Mutex<Session>>, store: State<Store>, note: String, position: Option<String>) -> Result<Bookmark, String> {
+    let position = match position {
+        Some(position) => {
+            let (pieces, turn) = notation::parse_position(&position).ok_or("invalid position")?;
+            notation::position_string(&pieces, turn)
+        }
+        None => {
+            let session = session.lock().unwrap();
+            notation::position_string(session.pieces(), session.turn())
+        }
+    };
+    add(&store, position, note)
+}
+
+#[tauri::command]
+pub fn bookmarks(store: State<Store>) -> Vec<Bookmark> {
+    load(&store)
+}
+
+#[tauri::command]
+pub fn delete_bookmark(store: State<Store>, id: u32) -> Result<(), String> {
+    let mut bookmarks = load(&store);
+    bookmarks.retain(|bookmark| bookmark.id != id);
+    store.save(FILE, &bookmarks)
+}
+
+// the game is replaced by a game from the bookmarked position, to analyze it
+#[tauri::command]
+pub fn open_bookmark(
+    session: State<Mutex<Session>>,
+    analysis: State<Analysis>,
+    store: State<Store>,
+    id: u32,
+) -> Result<GameStateView, String> {
+    let bookmark = load(&store).into_iter().find(|bookmark| bookmark.id == id).ok_or("no such bookmark")?;
+    let (pieces, turn) = notation::parse_position(&bookmark.position).ok_or("invalid position in the bookmark")?;
+    analysis.stop();
+    let mut session = session.lock().unwrap();
+    *session = Session::from_position(pieces, turn);
+    correspondence::save(&store, &session);
+    Ok(session.view())
+}
@@ -0,0 +1,158 @@
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State};
+use crate::endgame;
+use crate::notation;
+use crate::shogi::{self, EvalParams, Limits, Search};
+use crate::timeman::TimeBudget;
+
+// the calibration search runs once at startup for about this long
+const CALIBRATION_TIME: Duration = Duration::from_millis(300);
+// depth chosen for a response time when the machine has not been measured yet
+const MAX_TARGET_DEPTH: u8 = 20;
+
+// speed of the machine, measured on the initial position
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Speed {
+    pub nodes_per_sec: u64,
+    pub first_depth_nodes: u64, // nodes searched by the first iteration
+    pub branching: f64, // growth of the number of nodes from one depth to the next
+}
+
+impl Speed {
+    fn measure() -> Self {
+        let budget = TimeBudget { soft: CALIBRATION_TIME, hard: CALIBRATION_TIME * 2 };
+        let mut iterations = vec![];
+        let mut last_nodes = 0;
+        shogi::best_move(&mut Search::default(), &shogi::initial_pieces(), &[], Limits::time(budget), false, &mut |progress| {
+            iterations.push((progress.nodes - last_nodes, progress.elapsed_ms));
+            last_nodes = progress.nodes;
+        });
+        let first_depth_nodes = iterations.first().map_or(1, |&(nodes, _)| nodes.max(1));
+        let (last_depth_nodes, elapsed_ms) = iterations.last().copied().unwrap_or((1, 0));
+        let branching = match iterations.len() {
+            0 | 1 => 2.0,
+            n => (last_depth_nodes as f64 / first_depth_nodes as f64).powf(1.0 / (n - 1) as f64).max(1.1),
+        };
+        Speed {
+            nodes_per_sec: last_nodes * 1000 / elapsed_ms.max(1),
+            first_depth_nodes,
+            branching,
+        }
+    }
+
+    // the deepest search expected to complete within "time", iterations included
+    pub fn depth_within(&self, time: Duration) -> u8 {
+        let affordable = self.nodes_per_sec as f64 * time.as_secs_f64();
+        let mut nodes = self.first_depth_nodes as f64;
+        let mut total = nodes;
+        let mut depth = 1;
+        while depth < MAX_TARGET_DEPTH {
+            nodes *= self.branching;
+            total += nodes;
+            if total > affordable {
+                break;
+            }
+            depth += 1;
+        }
+        depth
+    }
+}
+
+// None until the measure taken at startup is available
+#[derive(Default)]
+pub struct Calibration(Mutex<Option<Speed>>);
+
+impl Calibration {
+    // Search limits to respond within "time". The depth comes from the speed of the machine,
+    // the time budget guarantees the response time if the position is harder than expected.
+    pub fn limits(&self, time: Duration) -> Limits {
+        let depth = self.0.lock().unwrap().map_or(MAX_TARGET_DEPTH, |speed| speed.depth_within(time));
+        Limits { depth, ..Limits::time(TimeBudget::fixed(time)) }
+    }
+}
+
+// the measure is taken in the background so that the window opens without delay
+pub fn start(app: AppHandle) {
+    thread::spawn(move || {
+        let speed = Speed::measure();
+        *app.state::<Calibration>().0.lock().unwrap() = Some(speed);
+    });
+}
+
+#[tauri::command]
+pub fn machine_speed(calibration: State<Calibration>) -> Option<Speed> {
+    *calibration.0.lock().unwrap()
+}
+
+// nodes searched in each position of the benchmark by default
+const BENCH_NODES: u64 = 200_000;
+// the positions of the benchmark are those along a fixed line from the initial one
+const BENCH_PLIES: usize = 12;
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Benchmark {
+    pub positions: usize,
+    pub nodes: u64,
+    pub elapsed_ms: u64,
+    pub nodes_per_sec: u64,
+    // the moves, scores and depths found, the same from one run to the next and from one
+    // machine to another as long as the search itself does not change
+    pub signature: String,
+}
+
+// Searches a fixed number of nodes in each position with a new search and the default weights,
+// so that nothing but the speed of the code differs between two runs.
+pub fn benchmark(nodes: u64) -> Benchmark {
+    let (mut pieces, mut turn) = (shogi::initial_pieces(), false);
+    let mut signature = vec![];
+    let mut total_nodes = 0;
+    let start = Instant::now();
+    for ply in 0..BENCH_PLIES {
+        if endgame::lost(&pieces, turn) {
+            break;
+        }
+        let mut search = Search::with_params(Arc::default(), Arc::new(EvalParams::default()));
+        let mut depth = 0;
+        let Some(result) = shogi::best_move(&mut search, &pieces, &[], Limits::nodes(nodes), turn, &mut |progress| {
+            depth = progress.depth;
+        }) else {
+            break;
+        };
+        total_nodes += search.nodes();
+        signature.push(format!("{}:{}:{}", notation::move_name(&pieces, result.mov), result.score, depth));
+        let moves = shogi::possible_moves(&pieces, turn);
+        pieces = shogi::play_move(&pieces, moves[ply % moves.len()]);
+        turn = !turn;
+    }
+    let elapsed = start.elapsed();
+    Benchmark {
+        positions: signature.len(),
+        nodes: total_nodes,
+        elapsed_ms: elapsed.as_millis() as u64,
+        nodes_per_sec: (total_nodes as f64 / elapsed.as_secs_f64().max(1e-3)) as u64,
+        signature: signature.join(" "),
+    }
+}
+
+#[tauri::command(async)]
+pub fn run_benchmark(nodes: Option<u64>) -> Benchmark {
+    benchmark(nodes.unwrap_or(BENCH_NODES))
+}
+
+// "bench [<nodes per position>]"
+pub fn bench_cli(args: &[String]) -> Result<(), String> {
+    let nodes = match args {
+        [] => BENCH_NODES,
+        [nodes] => nodes.parse().map_err(|_| "usage: bench [<nodes per position>]")?,
+        _ => return Err("usage: bench [<nodes per position>]".into()),
+    };
+    let bench = benchmark(nodes);
+    println!("{} positions, {} nodes in {}ms, {} nodes/s", bench.positions, bench.nodes, bench.elapsed_ms, bench.nodes_per_sec);
+    println!("signature: {}", bench.signature);
+    Ok(())
+}
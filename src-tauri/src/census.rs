@@ -0,0 +1,91 @@
+use std::collections::HashSet;
+use std::fs;
+use serde::Serialize;
+use crate::endgame;
+use crate::notation;
+use crate::shogi::{self, Pieces, Standard, HAND};
+
+// Census of the distinct positions reachable from the initial one, for checking the size of the
+// state space against the tablebase and for the study of the game. Two positions are the same up
+// to the order of twins and up to the left-right mirror, which the rules do not tell apart.
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Census {
+    pub new_per_ply: Vec<usize>, // positions first reached at each ply, the initial one at ply 0
+    pub terminal: usize, // positions where the game is over, which are not expanded
+    pub total: usize,
+}
+
+pub fn mirror(pieces: &Pieces) -> Pieces {
+    let mut mirrored = *pieces;
+    for piece in &mut mirrored {
+        if piece.position != HAND {
+            let (file, row) = (piece.position as usize % Standard::WIDTH, piece.position as usize / Standard::WIDTH);
+            piece.position = (Standard::WIDTH * row + Standard::WIDTH - 1 - file) as u8;
+        }
+    }
+    mirrored
+}
+
+// twins in a fixed order, lions excepted since their index tells their owner
+pub fn sort_twins(pieces: &Pieces) -> Pieces {
+    let mut sorted = *pieces;
+    for i in [0, 2, 3] {
+        let key = |piece: shogi::Piece| (piece.position, piece.owner, piece.kind as u8);
+        if key(sorted[i + 4]) < key(sorted[i]) {
+            sorted.swap(i, i + 4);
+        }
+    }
+    sorted
+}
+
+fn canonical(pieces: &Pieces, turn: bool) -> u64 {
+    let key = shogi::encode_pieces(&sort_twins(pieces), turn);
+    key.min(shogi::encode_pieces(&sort_twins(&mirror(pieces)), turn))
+}
+
+// breadth first up to "plies", the positions are written to "dump" when given, one per line
+pub fn census(plies: usize, dump: Option<&str>) -> Result<Census, String> {
+    let initial = shogi::initial_pieces();
+    let mut seen = HashSet::from([canonical(&initial, false)]);
+    let mut layer = vec![(initial, false)];
+    let mut new_per_ply = vec![1];
+    let mut terminal = 0;
+    let mut lines = vec![];
+    for ply in 0..=plies {
+        let mut next = vec![];
+        for &(pieces, turn) in &layer {
+            if dump.is_some() {
+                lines.push(notation::position_string(&pieces, turn));
+            }
+            if endgame::lost(&pieces, turn) {
+                terminal += 1;
+                continue;
+            }
+            if ply == plies {
+                continue;
+            }
+            for mov in shogi::possible_moves(&pieces, turn) {
+                let child = shogi::play_move(&pieces, mov);
+                if seen.insert(canonical(&child, !turn)) {
+                    next.push((child, !turn));
+                }
+            }
+        }
+        if ply < plies {
+            new_per_ply.push(next.len());
+        }
+        layer = next;
+    }
+    if let Some(path) = dump {
+        lines.push(String::new());
+        fs::write(path, lines.join("\n")).map_err(|err| format!("cannot write {path}: {err}"))?;
+    }
+    Ok(Census { total: seen.len(), new_per_ply, terminal })
+}
+
+#[tauri::command(async)]
+pub fn position_census(plies: usize, dump: Option<String>) -> Result<Census, String> {
+    census(plies, dump.as_deref())
+}
@@ -0,0 +1,130 @@
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+
+// at most this much of the delay between a remote move and its arrival is attributed to the network
+const MAX_LAG_COMPENSATION: Duration = Duration::from_millis(1000);
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeControl {
+    pub initial_ms: u64,
+    pub increment_ms: u64,
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClockView {
+    remaining_ms: [u64; 2],
+    increment_ms: [u64; 2],
+    running: Option<bool>, // the player whose time is running
+}
+
+// Fischer clock: the increment is added to the time of a player after each of their moves.
+// Each player has their own time control so that one side can be given time odds.
+#[derive(Clone, Debug)]
+pub struct Clock {
+    remaining: [Duration; 2],
+    increment: [Duration; 2],
+    turn: bool,
+    running_since: Option<Instant>,
+}
+
+impl Clock {
+    pub fn new(controls: [TimeControl; 2]) -> Self {
+        Clock {
+            remaining: controls.map(|control| Duration::from_millis(control.initial_ms)),
+            increment: controls.map(|control| Duration::from_millis(control.increment_ms)),
+            turn: false,
+            running_since: None,
+        }
+    }
+
+    pub fn start(&mut self, now: Instant) {
+        self.running_since = Some(now);
+    }
+
+    pub fn increment(&self, player: bool) -> Duration {
+        self.increment[player as usize]
+    }
+
+    fn elapsed(&self, now: Instant) -> Duration {
+        self.running_since.map_or(Duration::ZERO, |since| now.saturating_duration_since(since))
+    }
+
+    pub fn remaining(&self, player: bool, now: Instant) -> Duration {
+        let remaining = self.remaining[player as usize];
+        if player == self.turn { remaining.saturating_sub(self.elapsed(now)) } else { remaining }
+    }
+
+    // charges the time spent by the player to move and hands the clock to the opponent.
+    // For a move received over the network, "reported" is the thinking time measured by the sender:
+    // the rest of the delay is network lag, which is refunded up to MAX_LAG_COMPENSATION.
+    fn switch(&mut self, now: Instant, reported: Option<Duration>) {
+        let elapsed = self.elapsed(now);
+        let charged = match reported {
+            Some(reported) => elapsed.saturating_sub(MAX_LAG_COMPENSATION).max(reported.min(elapsed)),
+            None => elapsed,
+        };
+        let remaining = &mut self.remaining[self.turn as usize];
+        *remaining = remaining.saturating_sub(charged);
+        self.turn = !self.turn;
+        if self.running_since.is_some() {
+            self.running_since = Some(now);
+        }
+    }
+
+    // called when the player to move has played
+    pub fn press(&mut self, now: Instant, reported: Option<Duration>) {
+        let mover = self.turn;
+        self.switch(now, reported);
+        self.remaining[mover as usize] += self.increment[mover as usize];
+    }
+
+    // the player whose time has run out, if any
+    pub fn flagged(&self, now: Instant) -> Option<bool> {
+        (self.running_since.is_some() && self.remaining(self.turn, now).is_zero()).then_some(self.turn)
+    }
+
+    // the time spent so far is charged, used when the game ends
+    pub fn stop(&mut self, now: Instant) {
+        let elapsed = self.elapsed(now);
+        let remaining = &mut self.remaining[self.turn as usize];
+        *remaining = remaining.saturating_sub(elapsed);
+        self.running_since = None;
+    }
+
+    // called when a move is taken back, no increment is given
+    pub fn take_back(&mut self, now: Instant) {
+        self.switch(now, None);
+    }
+
+    // the times of both players, without the time running for the player to move
+    pub fn times(&self) -> [Duration; 2] {
+        self.remaining
+    }
+
+    // what is left to each player as time controls, a clock restored from them goes on from there
+    pub fn controls(&self, now: Instant) -> [TimeControl; 2] {
+        [false, true].map(|player| TimeControl {
+            initial_ms: self.remaining(player, now).as_millis() as u64,
+            increment_ms: self.increment(player).as_millis() as u64,
+        })
+    }
+
+    // puts the clock back to "remaining" with "turn" to move, as agreed by both players of a takeback
+    pub fn rewind(&mut self, remaining: [Duration; 2], turn: bool, now: Instant) {
+        self.remaining = remaining;
+        self.turn = turn;
+        if self.running_since.is_some() {
+            self.running_since = Some(now);
+        }
+    }
+
+    pub fn view(&self, now: Instant) -> ClockView {
+        ClockView {
+            remaining_ms: [self.remaining(false, now).as_millis() as u64, self.remaining(true, now).as_millis() as u64],
+            increment_ms: self.increment.map(|increment| increment.as_millis() as u64),
+            running: self.running_since.map(|_| self.turn),
+        }
+    }
+}
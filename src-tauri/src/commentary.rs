@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+use std::fs;
+use std::sync::{Arc, LazyLock, RwLock};
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use unic_langid::LanguageIdentifier;
+use crate::notation;
+use crate::session::{GameResult, Termination};
+use crate::shogi::{self, Kind, Move, Pieces, Rules, Standard, HAND};
+use crate::store::Store;
+use crate::warning::Severity;
+
+// The sentences of the app, from the speech to the reports of the analysis, made from structured
+// facts with the message templates of the language, in Fluent syntax. English and French come
+// with the app; a file "locales/<code>.ftl" in the data directory adds a language or replaces
+// some messages of one. A missing message falls back on English.
+
+const BUILT_IN: [(&str, &str); 2] = [("en", include_str!("../locales/en.ftl")), ("fr", include_str!("../locales/fr.ftl"))];
+const FALLBACK: &str = "en";
+const LOCALES_DIR: &str = "locales";
+
+// a language code, the former names "english" and "french" are still understood
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(from = "String")]
+pub struct Language(pub String);
+
+impl Default for Language {
+    fn default() -> Self {
+        Language(FALLBACK.into())
+    }
+}
+
+impl From<String> for Language {
+    fn from(code: String) -> Self {
+        match code.to_lowercase().as_str() {
+            "english" => Language("en".into()),
+            "french" => Language("fr".into()),
+            code => Language(code.into()),
+        }
+    }
+}
+
+pub struct Catalog {
+    bundles: HashMap<String, FluentBundle<FluentResource>>,
+}
+
+fn resource(code: &str, text: String) -> Result<FluentResource, String> {
+    FluentResource::try_new(text).map_err(|(_, errors)| format!("{code}: {}", errors[0]))
+}
+
+fn bundle(code: &str) -> Result<FluentBundle<FluentResource>, String> {
+    let id: LanguageIdentifier = code.parse().map_err(|_| format!("invalid language code {code}"))?;
+    let mut bundle = FluentBundle::new_concurrent(vec![id]);
+    // the sentences go to speech and plain text, without bidirectional isolation marks
+    bundle.set_use_isolating(false);
+    Ok(bundle)
+}
+
+impl Catalog {
+    fn built_in() -> Self {
+        let mut bundles = HashMap::new();
+        for (code, text) in BUILT_IN {
+            let mut bundle = bundle(code).unwrap();
+            let _ = bundle.add_resource(resource(code, text.into()).unwrap());
+            bundles.insert(code.to_string(), bundle);
+        }
+        Catalog { bundles }
+    }
+
+    // the built-in languages with the files of the data directory over them
+    fn with_files(store: &Store) -> Result<Self, String> {
+        let mut catalog = Catalog::built_in();
+        let Ok(entries) = fs::read_dir(store.path(LOCALES_DIR)) else { return Ok(catalog) };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Some(code) = path.file_stem().and_then(|stem| stem.to_str()).filter(|_| path.extension().is_some_and(|ext| ext == "ftl")) else {
+                continue;
+            };
+            let text = fs::read_to_string(&path).map_err(|err| format!("cannot read {}: {err}", path.display()))?;
+            let resource = resource(code, text)?;
+            let mut bundle = match catalog.bundles.remove(code) {
+                Some(bundle) => bundle,
+                None => bundle(code)?,
+            };
+            bundle.add_resource_overriding(resource);
+            catalog.bundles.insert(code.to_string(), bundle);
+        }
+        Ok(catalog)
+    }
+
+    pub fn languages(&self) -> Vec<String> {
+        let mut codes: Vec<String> = self.bundles.keys().cloned().collect();
+        codes.sort();
+        codes
+    }
+
+    // the message "id" in "language", in English if the language does not have it
+    pub fn format(&self, language: &Language, id: &str, args: &FluentArgs) -> String {
+        let pattern = [language.0.as_str(), FALLBACK].into_iter()
+            .filter_map(|code| self.bundles.get(code))
+            .find_map(|bundle| Some((bundle, bundle.get_message(id)?.value()?)));
+        let Some((bundle, pattern)) = pattern else { return id.to_string() };
+        let mut errors = vec![];
+        bundle.format_pattern(pattern, Some(args), &mut errors).into_owned()
+    }
+}
+
+static CATALOG: LazyLock<RwLock<Arc<Catalog>>> = LazyLock::new(|| RwLock::new(Arc::new(Catalog::built_in())));
+
+pub fn catalog() -> Arc<Catalog> {
+    CATALOG.read().unwrap().clone()
+}
+
+// installs the languages of the data directory, the built-in ones stay if a file is invalid
+pub fn load(store: &Store) -> Result<Vec<String>, String> {
+    let catalog = Catalog::with_files(store)?;
+    let languages = catalog.languages();
+    *CATALOG.write().unwrap() = Arc::new(catalog);
+    Ok(languages)
+}
+
+fn kind_key(kind: Kind) -> &'static str {
+    match kind {
+        Kind::Chick => "chick",
+        Kind::Elephant => "elephant",
+        Kind::Giraffe => "giraffe",
+        Kind::Lion => "lion",
+        Kind::Hen => "hen",
+    }
+}
+
+fn player_key(player: bool) -> &'static str {
+    if player { "second" } else { "first" }
+}
+
+fn capitalized(word: &str) -> String {
+    let mut chars = word.chars();
+    chars.next().map_or_else(String::new, |first| first.to_uppercase().chain(chars).collect())
+}
+
+fn args<const N: usize>(pairs: [(&'static str, String); N]) -> FluentArgs<'static> {
+    let mut args = FluentArgs::new();
+    for (name, value) in pairs {
+        args.set(name, value);
+    }
+    args
+}
+
+// What the commentary is made of, scores are from the point of view of the first player.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Fact {
+    Move { pieces: Pieces, mov: Move },
+    Result { result: GameResult },
+    BestMove { mov: String },
+    Mistake { mov: String, best: String, severity: Severity },
+    Evaluation { score: i32 },
+}
+
+// a verdict on the score, from the largest advantage down
+const VERDICTS: [(i32, &str); 3] = [(120, "winning"), (40, "ahead"), (15, "better")];
+
+impl Catalog {
+    fn piece(&self, language: &Language, id: &str, kind: Kind) -> String {
+        self.format(language, id, &args([("kind", kind_key(kind).into())]))
+    }
+
+    // "Giraffe moves to b2, capturing the chick.", "Chick moves to b1 and promotes to hen. Check."
+    pub fn move_sentence(&self, language: &Language, pieces: &Pieces, mov: Move) -> String {
+        let piece = pieces[mov.from];
+        let after = shogi::play_move(pieces, mov);
+        let captured = pieces.iter().find(|p| p.position == mov.to as u8)
+            .map_or_else(|| "none".into(), |captured| self.piece(language, "piece-object", captured.kind));
+        let promotion = if after[mov.from].kind != piece.kind {
+            "promotes"
+        } else if piece.kind == Kind::Chick && piece.position != HAND && Standard::last_rank(mov.to, piece.owner) {
+            "declines"
+        } else {
+            "none"
+        };
+        let lion = shogi::lion(&after, !piece.owner).position;
+        let check = lion != HAND && shogi::is_attacked(&after, lion, piece.owner);
+        self.format(language, "move", &args([
+            ("piece", capitalized(&self.piece(language, "piece", piece.kind))),
+            ("to", notation::square_name(mov.to as u8)),
+            ("drop", if piece.position == HAND { "yes" } else { "no" }.into()),
+            ("captured", captured),
+            ("promotion", promotion.into()),
+            ("check", if check { "yes" } else { "no" }.into()),
+        ]))
+    }
+
+    pub fn result_sentence(&self, language: &Language, result: GameResult) -> String {
+        let Some(winner) = result.winner else {
+            let reason = match result.reason {
+                Termination::Agreement => "agreement",
+                Termination::MoveLimit => "move-limit",
+                Termination::Adjudication => "adjudication",
+                _ => "repetition",
+            };
+            return self.format(language, "draw", &args([("reason", reason.into())]));
+        };
+        let reason = match result.reason {
+            Termination::LionCaptured => "lion-captured",
+            Termination::LionReachedCamp => "lion-reached-camp",
+            Termination::Resignation => "resignation",
+            Termination::Adjudication => "adjudication",
+            _ => "time",
+        };
+        let player = self.format(language, "player", &args([("player", player_key(winner).into())]));
+        self.format(language, "win", &args([("player", player), ("reason", reason.into())]))
+    }
+
+    pub fn sentence(&self, language: &Language, fact: &Fact) -> String {
+        match fact {
+            Fact::Move { pieces, mov } => self.move_sentence(language, pieces, *mov),
+            Fact::Result { result } => self.result_sentence(language, *result),
+            Fact::BestMove { mov } => self.format(language, "best-move", &args([("move", mov.clone())])),
+            Fact::Mistake { mov, best, severity } => {
+                let severity = match severity {
+                    Severity::Inaccuracy => "inaccuracy",
+                    Severity::Material => "material",
+                    Severity::Decisive => "decisive",
+                };
+                self.format(language, "mistake", &args([("move", mov.clone()), ("best", best.clone()), ("severity", severity.into())]))
+            }
+            Fact::Evaluation { score } => {
+                let verdict = VERDICTS.iter().find(|&&(threshold, _)| score.abs() >= threshold).map_or("balanced", |&(_, verdict)| verdict);
+                let player = self.format(language, "player", &args([("player", player_key(*score < 0).into())]));
+                self.format(language, "evaluation", &args([("verdict", verdict.into()), ("player", player)]))
+            }
+        }
+    }
+}
+
+#[tauri::command]
+pub fn commentary(facts: Vec<Fact>, language: Option<Language>) -> Result<Vec<String>, String> {
+    // a move is described whether the rules had the promotion optional or not
+    let rules = Rules { optional_promotion: true, ..Rules::default() };
+    for fact in &facts {
+        if let Fact::Move { pieces, mov } = fact {
+            shogi::validate(pieces)?;
+            shogi::legal_move(pieces, &rules, mov.from, mov.to, Some(mov.promote)).filter(|legal| legal == mov).ok_or("illegal move")?;
+        }
+    }
+    let catalog = catalog();
+    let language = language.unwrap_or_default();
+    Ok(facts.iter().map(|fact| catalog.sentence(&language, fact)).collect())
+}
+
+// the codes of the languages available
+#[tauri::command]
+pub fn languages() -> Vec<String> {
+    catalog().languages()
+}
+
+// after a file of the data directory has been added or edited
+#[tauri::command]
+pub fn reload_languages(store: State<Store>) -> Result<Vec<String>, String> {
+    load(&store)
+}
@@ -0,0 +1,93 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use crate::archive::GameInfo;
+use crate::recent;
+use crate::session::{GameResult, Session};
+use crate::shogi::{Move, Rules};
+use crate::store::Store;
+
+pub const FILE: &str = "correspondence.json";
+
+// Deadlines are absolute UTC timestamps in milliseconds since the Unix epoch,
+// they survive restarts and do not depend on the time zone.
+pub fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64)
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Correspondence {
+    pub per_move_ms: u64,
+    pub deadline_ms: u64, // for the player to move
+}
+
+impl Correspondence {
+    pub fn new(per_move_ms: u64, now_ms: u64) -> Self {
+        Correspondence { per_move_ms, deadline_ms: now_ms + per_move_ms }
+    }
+
+    pub fn restart(&mut self, now_ms: u64) {
+        self.deadline_ms = now_ms + self.per_move_ms;
+    }
+
+    pub fn expired(&self, now_ms: u64) -> bool {
+        now_ms >= self.deadline_ms
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SavedGame {
+    moves: Vec<Move>,
+    #[serde(default)] // missing in games saved by older versions
+    thinking_ms: Vec<u64>,
+    correspondence: Correspondence,
+    #[serde(default)]
+    rules: Rules,
+    result: Option<GameResult>,
+    #[serde(default)]
+    info: GameInfo,
+    #[serde(default)]
+    archived: Option<u32>, // id in the archive
+}
+
+// the current correspondence game is saved after every change, a game of another kind replaces it,
+// and the game goes to the recent ones whatever its kind
+pub fn save(store: &Store, session: &Session) {
+    recent::save(store, session);
+    match session.correspondence() {
+        Some(correspondence) => {
+            let game = SavedGame {
+                moves: session.moves(),
+                thinking_ms: session.thinking_times().iter().map(|time| time.as_millis() as u64).collect(),
+                correspondence,
+                rules: session.rules(),
+                result: session.result(),
+                info: session.info().clone(),
+                archived: session.archived(),
+            };
+            if let Err(err) = store.save(FILE, &game) {
+                eprintln!("cannot save the correspondence game: {err}");
+            }
+        }
+        None => store.remove(FILE),
+    }
+}
+
+pub fn restore(store: &Store) -> Option<Session> {
+    let game: SavedGame = store.load(FILE)?;
+    let mut session = Session::default();
+    session.set_rules(game.rules);
+    for mov in game.moves {
+        session.play(mov).ok()?;
+    }
+    session.set_thinking_times(game.thinking_ms.into_iter().map(Duration::from_millis).collect());
+    session.set_correspondence(game.correspondence);
+    if let Some(result) = game.result {
+        session.set_result(result);
+    }
+    session.set_info(game.info);
+    if let Some(id) = game.archived {
+        session.set_archived(id);
+    }
+    Some(session)
+}
@@ -0,0 +1,172 @@
+use serde::Serialize;
+use crate::notation;
+use crate::selfplay::{self, Rng};
+use crate::shogi::{self, Kind, Move, Pieces, Standard, HAND};
+
+// Differential testing of the move generation. The generator of the engine goes through
+// precomputed tables and stages, the reference below steps on the board square by square, as
+// plainly as possible, and both must agree on any position.
+
+const DEFAULT_POSITIONS: usize = 1_000_000;
+
+// steps for the second player, whose forward direction is +1 row, and (file, row)
+fn steps(kind: Kind) -> &'static [(i8, i8)] {
+    match kind {
+        Kind::Chick => &[(0, 1)],
+        Kind::Elephant => &[(1, 1), (-1, 1), (1, -1), (-1, -1)],
+        Kind::Giraffe => &[(0, 1), (1, 0), (0, -1), (-1, 0)],
+        Kind::Lion => &[(0, 1), (1, 0), (0, -1), (-1, 0), (1, 1), (-1, 1), (1, -1), (-1, -1)],
+        Kind::Hen => &[(0, 1), (1, 0), (0, -1), (-1, 0), (1, 1), (-1, 1)],
+    }
+}
+
+pub fn reference_moves(pieces: &Pieces, turn: bool) -> Vec<Move> {
+    let occupant = |square: u8| pieces.iter().find(|piece| piece.position == square);
+    let mut moves = vec![];
+    for (from, piece) in pieces.iter().enumerate().filter(|(_, piece)| piece.owner == turn) {
+        if piece.position == HAND {
+            // a single drop per kind, from the piece of lowest index
+            let first = pieces.iter().position(|other| other.owner == turn && other.position == HAND && other.kind == piece.kind);
+            if first == Some(from) {
+                moves.extend((0..HAND).filter(|&to| occupant(to).is_none()).map(|to| Move { from, to: to as usize, promote: false }));
+            }
+            continue;
+        }
+        let (width, height) = (Standard::WIDTH as i8, Standard::HEIGHT as i8);
+        let (file, row) = (piece.position as i8 % width, piece.position as i8 / width);
+        for &(df, dr) in steps(piece.kind) {
+            let (df, dr) = if turn { (df, dr) } else { (-df, -dr) };
+            let (file, row) = (file + df, row + dr);
+            if !(0..width).contains(&file) || !(0..height).contains(&row) {
+                continue;
+            }
+            let to = (width * row + file) as u8;
+            if occupant(to).is_some_and(|other| other.owner == turn) {
+                continue;
+            }
+            let promote = piece.kind == Kind::Chick && row == if turn { height - 1 } else { 0 };
+            moves.push(Move { from, to: to as usize, promote });
+        }
+    }
+    moves
+}
+
+// any valid placement, reachable or not, with both lions on the board
+fn random_placement(rng: &mut Rng) -> Pieces {
+    loop {
+        let mut pieces = shogi::initial_pieces();
+        for (i, piece) in pieces.iter_mut().enumerate() {
+            if piece.kind != Kind::Lion {
+                piece.owner = rng.below(2) == 1;
+            }
+            piece.position = if piece.kind == Kind::Lion { rng.below(Standard::SQUARES) } else { rng.below(Standard::SQUARES + 1) } as u8;
+            if i % 4 == 3 && piece.position != HAND && rng.below(2) == 1 {
+                piece.kind = Kind::Hen;
+            }
+        }
+        if shogi::is_valid(&pieces) {
+            return pieces;
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Divergence {
+    pub position: String,
+    pub missing: Vec<String>, // generated by the reference only
+    pub extra: Vec<String>, // generated by the engine only
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrossCheck {
+    pub positions: usize,
+    pub divergences: Vec<Divergence>, // the first few
+}
+
+// the report keeps this many divergences
+const MAX_DIVERGENCES: usize = 20;
+
+pub fn cross_check(positions: usize, seed: u64) -> CrossCheck {
+    let mut rng = Rng::new(seed);
+    let mut divergences = vec![];
+    for _ in 0..positions {
+        let pieces = random_placement(&mut rng);
+        let turn = rng.below(2) == 1;
+        let key = |mov: &Move| (mov.from, mov.to, mov.promote);
+        let mut engine: Vec<Move> = shogi::possible_moves(&pieces, turn).to_vec();
+        let mut reference = reference_moves(&pieces, turn);
+        engine.sort_by_key(key);
+        reference.sort_by_key(key);
+        if engine != reference {
+            let names = |moves: &[Move], others: &[Move]| -> Vec<String> {
+                moves.iter().filter(|mov| !others.contains(mov)).map(|&mov| notation::move_name(&pieces, mov)).collect()
+            };
+            divergences.push(Divergence {
+                position: notation::position_string(&pieces, turn),
+                missing: names(&reference, &engine),
+                extra: names(&engine, &reference),
+            });
+            if divergences.len() == MAX_DIVERGENCES {
+                break;
+            }
+        }
+    }
+    CrossCheck { positions, divergences }
+}
+
+#[tauri::command(async)]
+pub fn cross_check_movegen(positions: Option<usize>, seed: Option<u64>) -> CrossCheck {
+    cross_check(positions.unwrap_or(DEFAULT_POSITIONS), seed.unwrap_or_else(selfplay::clock_seed))
+}
+
+// "check-movegen [<positions>]"
+pub fn check_movegen_cli(args: &[String]) -> Result<(), String> {
+    let positions = match args {
+        [] => DEFAULT_POSITIONS,
+        [positions] => positions.parse().map_err(|_| "usage: check-movegen [<positions>]")?,
+        _ => return Err("usage: check-movegen [<positions>]".into()),
+    };
+    let report = cross_check(positions, selfplay::clock_seed());
+    for divergence in &report.divergences {
+        println!("{}: missing {:?}, extra {:?}", divergence.position, divergence.missing, divergence.extra);
+    }
+    if report.divergences.is_empty() {
+        println!("{} positions, the generators agree", report.positions);
+        Ok(())
+    } else {
+        Err("the move generation diverges from the reference".into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shogi::PIECE_VALUE;
+
+    #[test]
+    fn staged_generation_matches_the_reference() {
+        let report = cross_check(20_000, 1);
+        assert!(report.divergences.is_empty(), "{:?}", report.divergences);
+    }
+
+    #[test]
+    fn stages_chain_into_the_possible_moves() {
+        let mut rng = Rng::new(2);
+        for _ in 0..20_000 {
+            let pieces = random_placement(&mut rng);
+            let turn = rng.below(2) == 1;
+            let occupied = |square: usize| pieces.iter().any(|piece| piece.position as usize == square);
+            let (captures, drops, quiet) = (shogi::captures(&pieces, turn), shogi::drops(&pieces, turn), shogi::quiet_moves(&pieces, turn));
+            assert!(captures.iter().all(|mov| pieces[mov.from].position != HAND && occupied(mov.to)));
+            assert!(drops.iter().all(|mov| pieces[mov.from].position == HAND && !occupied(mov.to)));
+            assert!(quiet.iter().all(|mov| pieces[mov.from].position != HAND && !occupied(mov.to)));
+            // the most valuable victim first
+            let victim = |mov: &Move| pieces.iter().find(|piece| piece.position as usize == mov.to).map(|piece| PIECE_VALUE[piece.kind as usize]);
+            assert!(captures.windows(2).all(|pair| victim(&pair[0]) >= victim(&pair[1])));
+            let chained: Vec<Move> = captures.iter().chain(drops.iter()).chain(quiet.iter()).copied().collect();
+            assert_eq!(shogi::possible_moves(&pieces, turn).to_vec(), chained);
+        }
+    }
+}
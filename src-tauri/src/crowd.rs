@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+use crate::notation;
+use crate::session::Session;
+use crate::shogi::{self, Move, Pieces, Rules, Search};
+
+const DEFAULT_WEBSOCKET_PORT: u16 = 7879;
+const TWITCH_SERVER: (&str, u16) = ("irc.chat.twitch.tv", 6667);
+// a viewer sending more messages during a voting window is ignored until the next one
+const MAX_MESSAGES_PER_WINDOW: u32 = 5;
+// how often the sources check whether the crowd mode has been stopped
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// where the votes come from
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum VoteSource {
+    // local WebSocket server, messages are {"user": ..., "text": ...} or plain text
+    WebSocket { port: Option<u16> },
+    // chat of a Twitch channel, read anonymously
+    Twitch { channel: String },
+}
+
+#[derive(Clone, Debug)]
+pub struct Vote {
+    pub user: String,
+    pub text: String, // a move such as "Gc1-c2", optionally preceded by "!"
+}
+
+#[derive(Deserialize)]
+struct VoteMessage {
+    user: String,
+    text: String,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TallyEntry {
+    #[serde(rename = "move")]
+    mov: String,
+    votes: u32,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TallyUpdate {
+    entries: Vec<TallyEntry>,
+    remaining_ms: u64,
+}
+
+// The votes of a window: one vote per viewer, the last one counts, and only legal moves are
+// accepted. Ties go to the move proposed first.
+#[derive(Default)]
+struct Tally {
+    votes: HashMap<String, Move>,
+    messages: HashMap<String, u32>,
+    proposed: Vec<Move>,
+}
+
+impl Tally {
+    // whether the vote has been counted
+    fn add(&mut self, pieces: &Pieces, turn: bool, rules: &Rules, vote: &Vote) -> bool {
+        let messages = self.messages.entry(vote.user.clone()).or_default();
+        *messages += 1;
+        if *messages > MAX_MESSAGES_PER_WINDOW {
+            return false;
+        }
+        let Some(mov) = notation::parse_move(pieces, turn, rules, vote.text.trim().trim_start_matches('!')) else {
+            return false;
+        };
+        if !self.proposed.contains(&mov) {
+            self.proposed.push(mov);
+        }
+        self.votes.insert(vote.user.clone(), mov);
+        true
+    }
+
+    // moves with at least one vote, the winner first
+    fn counts(&self) -> Vec<(Move, u32)> {
+        let mut counts: Vec<(Move, u32)> = self.proposed.iter()
+            .map(|&mov| (mov, self.votes.values().filter(|&&m| m == mov).count() as u32))
+            .filter(|&(_, votes)| votes > 0)
+            .collect();
+        // stable, the order of proposal breaks ties
+        counts.sort_by_key(|&(_, votes)| std::cmp::Reverse(votes));
+        counts
+    }
+}
+
+// The crowd plays one side against the engine, each of its moves is decided by a vote
+#[derive(Default)]
+pub struct Crowd {
+    running: Mutex<Option<Arc<AtomicBool>>>,
+}
+
+impl Crowd {
+    pub fn stop(&self) {
+        if let Some(stopped) = self.running.lock().unwrap().take() {
+            stopped.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+fn websocket_source(port: u16, votes: Sender<Vote>, stopped: Arc<AtomicBool>) -> Result<(), String> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port)).map_err(|err| err.to_string())?;
+    listener.set_nonblocking(true).map_err(|err| err.to_string())?;
+    thread::spawn(move || {
+        while !stopped.load(Ordering::Relaxed) {
+            let stream = match listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                    thread::sleep(POLL_INTERVAL);
+                    continue;
+                }
+                Err(_) => continue,
+            };
+            let (votes, stopped) = (votes.clone(), stopped.clone());
+            thread::spawn(move || {
+                let peer = stream.peer_addr().map_or_else(|_| "?".into(), |addr| addr.to_string());
+                let _ = stream.set_nonblocking(false);
+                let _ = stream.set_read_timeout(Some(POLL_INTERVAL));
+                let Ok(mut socket) = tungstenite::accept(stream) else { return };
+                while !stopped.load(Ordering::Relaxed) {
+                    match socket.read() {
+                        Ok(tungstenite::Message::Text(text)) => {
+                            let vote = match serde_json::from_str::<VoteMessage>(&text) {
+                                Ok(message) => Vote { user: message.user, text: message.text },
+                                Err(_) => Vote { user: peer.clone(), text: text.to_string() },
+                            };
+                            if votes.send(vote).is_err() {
+                                return;
+                            }
+                        }
+                        Ok(_) => {}
+                        Err(tungstenite::Error::Io(err)) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+                        Err(_) => return,
+                    }
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+// ":user!user@user.tmi.twitch.tv PRIVMSG #channel :text"
+fn parse_privmsg(line: &str) -> Option<Vote> {
+    let (prefix, rest) = line.strip_prefix(':')?.split_once(' ')?;
+    let (_, text) = rest.strip_prefix("PRIVMSG ")?.split_once(" :")?;
+    Some(Vote { user: prefix.split('!').next()?.to_string(), text: text.to_string() })
+}
+
+fn twitch_source(channel: &str, votes: Sender<Vote>, stopped: Arc<AtomicBool>) -> Result<(), String> {
+    let mut stream = TcpStream::connect(TWITCH_SERVER).map_err(|err| err.to_string())?;
+    stream.set_read_timeout(Some(POLL_INTERVAL)).map_err(|err| err.to_string())?;
+    // anonymous read-only login
+    let channel = channel.trim_start_matches('#').to_lowercase();
+    write!(stream, "NICK justinfan{}\r\nJOIN #{channel}\r\n", std::process::id() % 100000).map_err(|err| err.to_string())?;
+    let mut reader = BufReader::new(stream.try_clone().map_err(|err| err.to_string())?);
+    thread::spawn(move || {
+        let mut line = String::new();
+        while !stopped.load(Ordering::Relaxed) {
+            match reader.read_line(&mut line) {
+                Ok(0) => return,
+                Ok(_) => {
+                    let message = line.trim_end();
+                    if let Some(server) = message.strip_prefix("PING ") {
+                        let _ = write!(stream, "PONG {server}\r\n");
+                    } else if let Some(vote) = parse_privmsg(message) {
+                        if votes.send(vote).is_err() {
+                            return;
+                        }
+                    }
+                    line.clear();
+                }
+                Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+                Err(_) => return,
+            }
+        }
+    });
+    Ok(())
+}
+
+// Collects the votes of one window, returns the winning move, None if nobody voted.
+// Votes arriving between two windows are discarded.
+fn vote(
+    app: &AppHandle,
+    votes: &Receiver<Vote>,
+    pieces: &Pieces,
+    turn: bool,
+    rules: &Rules,
+    window: Duration,
+) -> Result<Option<Move>, String> {
+    while votes.try_recv().is_ok() {}
+    let mut tally = Tally::default();
+    let deadline = Instant::now() + window;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match votes.recv_timeout(remaining) {
+            Ok(vote) => {
+                if tally.add(pieces, turn, rules, &vote) {
+                    let entries = tally.counts().into_iter()
+                        .map(|(mov, votes)| TallyEntry { mov: notation::move_name(pieces, mov), votes })
+                        .collect();
+                    let _ = app.emit("crowd-tally", TallyUpdate { entries, remaining_ms: remaining.as_millis() as u64 });
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => return Ok(tally.counts().first().map(|&(mov, _)| mov)),
+            Err(RecvTimeoutError::Disconnected) => return Err("the vote source has been disconnected".into()),
+        }
+    }
+}
+
+fn run(app: AppHandle, votes: Receiver<Vote>, stopped: Arc<AtomicBool>, side: bool, window: Duration, depth: u8) {
+    let session = app.state::<Mutex<Session>>();
+    while !stopped.load(Ordering::Relaxed) {
+        let (pieces, turn, played, limits) = {
+            let session = session.lock().unwrap();
+            if session.result().is_some() {
+                break;
+            }
+            (*session.pieces(), session.turn(), session.played(), session.limits(depth))
+        };
+        let mov = if turn == side {
+            match vote(&app, &votes, &pieces, turn, &limits.rules, window) {
+                Ok(Some(mov)) => mov,
+                Ok(None) => continue, // nobody voted, the vote starts again
+                Err(err) => {
+                    let _ = app.emit("crowd-stopped", err);
+                    break;
+                }
+            }
+        } else {
+            match shogi::best_move(&mut Search::default(), &pieces, &played, limits, turn, &mut |_| ()) {
+                Some(result) => result.mov,
+                None => break,
+            }
+        };
+        if stopped.load(Ordering::Relaxed) {
+            break;
+        }
+        let mut session = session.lock().unwrap();
+        // the position may have changed in the meantime
+        if *session.pieces() == pieces && session.play(mov).is_ok() {
+            let _ = app.emit("crowd-move", notation::move_name(&pieces, mov));
+            let _ = app.emit("game-state", session.view());
+        }
+    }
+    stopped.store(true, Ordering::Relaxed);
+}
+
+// the crowd plays "side" in the current game
+#[tauri::command]
+pub fn start_crowd(
+    app: AppHandle,
+    crowd: State<Crowd>,
+    source: VoteSource,
+    side: bool,
+    window_ms: u64,
+    depth: u8,
+) -> Result<(), String> {
+    crowd.stop();
+    let stopped = Arc::new(AtomicBool::new(false));
+    let (sender, receiver) = mpsc::channel();
+    match &source {
+        VoteSource::WebSocket { port } => websocket_source(port.unwrap_or(DEFAULT_WEBSOCKET_PORT), sender, stopped.clone())?,
+        VoteSource::Twitch { channel } => twitch_source(channel, sender, stopped.clone())?,
+    }
+    *crowd.running.lock().unwrap() = Some(stopped.clone());
+    thread::spawn(move || run(app, receiver, stopped, side, Duration::from_millis(window_ms), depth));
+    Ok(())
+}
+
+#[tauri::command]
+pub fn stop_crowd(crowd: State<Crowd>) {
+    crowd.stop();
+}
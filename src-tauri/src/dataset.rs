@@ -0,0 +1,93 @@
+use std::fs;
+use std::path::Path;
+use tauri::State;
+use crate::selfplay::{self, Rng, Sample};
+use crate::shogi::{Standard, HAND};
+use crate::store::Store;
+
+// Self-play samples are exported as a directory of NumPy arrays, one .npy file per column,
+// all with one row per position: `{name: np.load(f"{dir}/{name}.npy") for name in COLUMNS}`.
+// Positions are seen from the first player, whose pieces move towards square 0.
+const COLUMNS: &str = "\
+planes    uint8  (n, 10, 12)        one plane per owner and kind (first player's chick, elephant, giraffe, lion, hen,
+                                    then the second player's), 1 on the squares occupied by such a piece.
+                                    Square 0 is the top left corner seen by the first player, 11 the bottom right
+hands     uint8  (n, 2, 5)          number of pieces of each kind in the hand of each player
+turn      uint8  (n,)               0 when the first player is to move, 1 otherwise
+move_from uint8  (n,)               square of the piece that moved, 12 for a drop
+move_to   uint8  (n,)               destination square
+move_kind uint8  (n,)               kind of the piece moved or dropped (0 chick, 1 elephant, 2 giraffe, 3 lion, 4 hen)
+outcome   int8   (n,)               result of the game: 1 if the first player won, -1 if the first player lost, 0 for a draw
+game      uint32 (n,)               index of the game the position comes from
+ply       uint16 (n,)               number of moves played before the position
+";
+
+// a .npy file, format version 1.0
+fn write_array(dir: &Path, name: &str, dtype: &str, shape: &[usize], data: &[u8]) -> Result<(), String> {
+    let shape = match shape {
+        [n] => format!("({n},)"),
+        _ => format!("({})", shape.iter().map(usize::to_string).collect::<Vec<_>>().join(", ")),
+    };
+    let mut header = format!("{{'descr': '{dtype}', 'fortran_order': False, 'shape': {shape}, }}");
+    // the data starts on a multiple of 64 bytes, the header ends with a newline
+    let unpadded = 10 + header.len() + 1;
+    header.push_str(&" ".repeat(unpadded.next_multiple_of(64) - unpadded));
+    header.push('\n');
+    let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+    bytes.extend((header.len() as u16).to_le_bytes());
+    bytes.extend(header.as_bytes());
+    bytes.extend(data);
+    fs::write(dir.join(format!("{name}.npy")), bytes).map_err(|err| err.to_string())
+}
+
+pub fn export(samples: &[Sample], dir: &Path) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    let n = samples.len();
+    const SQUARES: usize = Standard::SQUARES;
+    let mut planes = vec![0u8; n * 10 * SQUARES];
+    let mut hands = vec![0u8; n * 10];
+    for (i, sample) in samples.iter().enumerate() {
+        for piece in &sample.pieces {
+            let plane = piece.owner as usize * 5 + piece.kind as usize;
+            if piece.position == HAND {
+                hands[i * 10 + plane] += 1;
+            } else {
+                planes[(i * 10 + plane) * SQUARES + piece.position as usize] = 1;
+            }
+        }
+    }
+    let column = |f: &dyn Fn(&Sample) -> u8| samples.iter().map(f).collect::<Vec<u8>>();
+    write_array(dir, "planes", "|u1", &[n, 10, SQUARES], &planes)?;
+    write_array(dir, "hands", "|u1", &[n, 2, 5], &hands)?;
+    write_array(dir, "turn", "|u1", &[n], &column(&|s| s.turn as u8))?;
+    write_array(dir, "move_from", "|u1", &[n], &column(&|s| s.pieces[s.mov.from].position))?;
+    write_array(dir, "move_to", "|u1", &[n], &column(&|s| s.mov.to as u8))?;
+    write_array(dir, "move_kind", "|u1", &[n], &column(&|s| s.pieces[s.mov.from].kind as u8))?;
+    write_array(dir, "outcome", "|i1", &[n], &column(&|s| s.outcome as i8 as u8))?;
+    let game: Vec<u8> = samples.iter().flat_map(|s| (s.game as u32).to_le_bytes()).collect();
+    write_array(dir, "game", "<u4", &[n], &game)?;
+    let ply: Vec<u8> = samples.iter().flat_map(|s| (s.ply as u16).to_le_bytes()).collect();
+    write_array(dir, "ply", "<u2", &[n], &ply)?;
+    fs::write(dir.join("README.txt"), COLUMNS).map_err(|err| err.to_string())
+}
+
+// plays "games" self-play games and exports them, to the "dataset" directory of the app data by default
+#[tauri::command(async)]
+pub fn export_dataset(store: State<Store>, games: usize, depth: u8, dir: Option<String>) -> Result<String, String> {
+    let dir = dir.map_or_else(|| store.path("dataset"), Into::into);
+    let samples = selfplay::generate(games, depth, 8, &mut Rng::from_clock());
+    export(&samples, &dir)?;
+    Ok(dir.display().to_string())
+}
+
+// "export <games> <dir>" from the command line
+pub fn export_cli(args: &[String]) -> Result<(), String> {
+    let [games, dir] = args else {
+        return Err("usage: export <games> <dir>".into());
+    };
+    let games = games.parse().map_err(|_| "invalid number of games")?;
+    let samples = selfplay::generate(games, 4, 8, &mut Rng::from_clock());
+    export(&samples, Path::new(dir))?;
+    eprintln!("{} positions exported", samples.len());
+    Ok(())
+}
@@ -0,0 +1,59 @@
+use serde::Serialize;
+use crate::shogi::{self, Kind, Piece, Pieces, HAND};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Change {
+    // from is None for a drop
+    Move { piece: usize, from: Option<u8>, to: u8 },
+    // the piece goes to the hand of "owner", from is None if it was already in a hand
+    Capture { piece: usize, from: Option<u8>, owner: bool },
+    Promotion { piece: usize, kind: Kind },
+}
+
+fn changes(before: &Piece, after: &Piece, piece: usize, result: &mut Vec<Change>) {
+    let from = if before.position == HAND { None } else { Some(before.position) };
+    // a piece only changes sides by going through a hand
+    let captured = before.owner != after.owner || before.position != HAND && after.position == HAND;
+    if captured {
+        result.push(Change::Capture { piece, from, owner: after.owner });
+    }
+    if after.position != HAND {
+        if captured || before.position != after.position {
+            result.push(Change::Move { piece, from: if captured { None } else { from }, to: after.position });
+        }
+        if before.kind != after.kind {
+            result.push(Change::Promotion { piece, kind: after.kind });
+        }
+    }
+}
+
+fn cost(before: &Piece, after: &Piece) -> usize {
+    let mut result = vec![];
+    changes(before, after, 0, &mut result);
+    result.len()
+}
+
+// Pieces i and i+4 are interchangeable, so positions coming from a loaded game may have
+// swapped them. Each pair is matched in the way that involves the fewest changes.
+// Pieces are identified by their index in "after". Captures come first, then moves and promotions.
+pub fn diff(before: &Pieces, after: &Pieces) -> Vec<Change> {
+    let mut result = vec![];
+    for i in 0..4 {
+        let j = i + 4;
+        let swapped = cost(&before[i], &after[j]) + cost(&before[j], &after[i])
+            < cost(&before[i], &after[i]) + cost(&before[j], &after[j]);
+        let (bi, bj) = if swapped { (j, i) } else { (i, j) };
+        changes(&before[bi], &after[i], i, &mut result);
+        changes(&before[bj], &after[j], j, &mut result);
+    }
+    result.sort_by_key(|change| !matches!(change, Change::Capture { .. }));
+    result
+}
+
+#[tauri::command]
+pub fn diff_positions(before: Pieces, after: Pieces) -> Result<Vec<Change>, String> {
+    shogi::validate(&before)?;
+    shogi::validate(&after)?;
+    Ok(diff(&before, &after))
+}
@@ -0,0 +1,96 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use crate::analysis::Analysis;
+use crate::correspondence;
+use crate::endgame::{self, Outcome};
+use crate::notation;
+use crate::session::{GameStateView, Session};
+use crate::shogi::{self, Kind, Pieces};
+use crate::store::Store;
+
+pub const FILE: &str = "conversions.json";
+const MATERIAL_ORDER: [Kind; 5] = [Kind::Lion, Kind::Hen, Kind::Giraffe, Kind::Elephant, Kind::Chick];
+
+// Conversion drill: the player starts from a won position and the engine defends as long as
+// possible. The results are kept per endgame class.
+#[derive(Clone, Debug)]
+pub struct Drill {
+    pub class: String,
+    pub player: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConversionStats {
+    pub attempts: u32,
+    pub successes: u32,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClassStats {
+    class: String,
+    attempts: u32,
+    successes: u32,
+    rate: f64,
+}
+
+// the material of each side, the attacker first: "LGC vs LE"
+pub fn class(pieces: &Pieces, attacker: bool) -> String {
+    let material = |owner: bool| {
+        let mut kinds: Vec<Kind> = pieces.iter().filter(|piece| piece.owner == owner).map(|piece| piece.kind).collect();
+        kinds.sort_by_key(|&kind| MATERIAL_ORDER.iter().position(|&k| k == kind));
+        kinds.into_iter().map(notation::kind_letter).collect::<String>()
+    };
+    format!("{} vs {}", material(attacker), material(!attacker))
+}
+
+// counts the finished drill of the session, if any
+pub fn record(store: &Store, session: &mut Session) {
+    let Some((drill, result)) = session.take_finished_drill() else { return };
+    let mut stats: BTreeMap<String, ConversionStats> = store.load(FILE).unwrap_or_default();
+    let entry = stats.entry(drill.class).or_default();
+    entry.attempts += 1;
+    if result.winner == Some(drill.player) {
+        entry.successes += 1;
+    }
+    if let Err(err) = store.save(FILE, &stats) {
+        eprintln!("cannot save the conversion statistics: {err}");
+    }
+}
+
+// the player to move must win the position against the most stubborn defense
+#[tauri::command(async)]
+pub fn start_drill(
+    session: State<Mutex<Session>>,
+    analysis: State<Analysis>,
+    store: State<Store>,
+    pieces: Pieces,
+    turn: bool,
+) -> Result<GameStateView, String> {
+    shogi::validate(&pieces)?;
+    match endgame::solve(&pieces, turn).map(|solution| solution.outcome) {
+        Some(Outcome::Win) => {}
+        Some(_) => return Err("the position is not won for the player to move".into()),
+        None => return Err("the position is too complex to be solved".into()),
+    }
+    analysis.stop();
+    let mut session = session.lock().unwrap();
+    *session = Session::from_position(pieces, turn);
+    session.set_drill(Drill { class: class(&pieces, turn), player: turn });
+    correspondence::save(&store, &session);
+    Ok(session.view())
+}
+
+#[tauri::command]
+pub fn drill_stats(store: State<Store>) -> Vec<ClassStats> {
+    let stats: BTreeMap<String, ConversionStats> = store.load(FILE).unwrap_or_default();
+    stats.into_iter().map(|(class, stats)| ClassStats {
+        class,
+        attempts: stats.attempts,
+        successes: stats.successes,
+        rate: stats.successes as f64 / stats.attempts.max(1) as f64,
+    }).collect()
+}
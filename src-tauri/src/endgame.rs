@@ -0,0 +1,207 @@
+use std::collections::{HashMap, VecDeque};
+use serde::{Deserialize, Serialize};
+use crate::shogi::{self, Move, Pieces, SearchResult, HAND};
+
+// positions with at most this many pieces on the board are handed to the solver
+pub const SPARSE: usize = 4;
+// positions examined at most, the farthest ones are left out
+pub const MAX_POSITIONS: usize = 100_000;
+
+// a winning move may give up this many plies of the fastest win to be easier to follow
+const INSTRUCTIVE_SLACK: u32 = 4;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Outcome { Win, Loss, Draw } // for the player to move
+
+// how a solved position is played
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EndgameStyle {
+    // the fastest win, the longest resistance
+    #[default]
+    Optimal,
+    // a slightly slower win that leaves the opponent as few replies as possible
+    Instructive,
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RootMove {
+    #[serde(rename = "move")]
+    pub mov: Move,
+    pub outcome: Option<Outcome>, // for the player to move at the root, None if unknown
+    pub distance: u32,
+    pub replies: usize, // legal moves of the opponent afterwards
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Solution {
+    pub outcome: Outcome,
+    pub distance: u32, // plies until the end of the game with best play, 0 for a draw
+    pub line: Vec<Move>, // best play, the first move keeps the draw in a drawn position
+    pub moves: Vec<RootMove>,
+}
+
+impl Solution {
+    // from the point of view of the first player, faster wins score higher like the mate scores of the search
+    pub fn score(&self, turn: bool) -> i32 {
+        let score = match self.outcome {
+            Outcome::Win => 101000 - self.distance as i32,
+            Outcome::Loss => -101000 + self.distance as i32,
+            Outcome::Draw => 0,
+        };
+        if turn { -score } else { score }
+    }
+
+    // the move to play in "style", None if there is no legal move
+    pub fn choose(&self, style: EndgameStyle) -> Option<Move> {
+        if style == EndgameStyle::Instructive && self.outcome == Outcome::Win {
+            let instructive = self.moves.iter()
+                .filter(|m| m.outcome == Some(Outcome::Win) && m.distance <= self.distance + INSTRUCTIVE_SLACK)
+                .min_by_key(|m| (m.replies, m.distance));
+            if let Some(m) = instructive {
+                return Some(m.mov);
+            }
+        }
+        self.line.first().copied()
+    }
+
+    // the result of a search that would have found the solution
+    pub fn search_result(&self, turn: bool, style: EndgameStyle) -> Option<SearchResult> {
+        self.choose(style).map(|mov| SearchResult {
+            mov,
+            score: self.score(turn),
+            depth: self.distance.min(u8::MAX as u32) as u8,
+            pv: if self.line.first() == Some(&mov) { self.line.clone() } else { vec![mov] },
+        })
+    }
+}
+
+pub fn is_sparse(pieces: &Pieces) -> bool {
+    pieces.iter().filter(|piece| piece.position != HAND).count() <= SPARSE
+}
+
+struct Node {
+    pieces: Pieces,
+    turn: bool,
+    moves: Vec<(Move, usize)>, // with the index of the resulting position
+    status: Option<(Outcome, u32)>,
+    unresolved: usize, // children not yet known to be won by the opponent, usize::MAX if some are unknown
+}
+
+// The player to move has lost if their lion was captured or if the opponent's lion reached
+// the last rank safely on the previous move.
+pub fn lost(pieces: &Pieces, turn: bool) -> bool {
+    shogi::lion(pieces, turn).position == HAND || shogi::lion_reached_camp(pieces, !turn)
+}
+
+// Retrograde analysis of the positions reachable from "pieces", closest first. A position is won
+// if a move leads to a lost position and lost if every move leads to a won position. When every
+// reachable position fits in MAX_POSITIONS, the rest is drawn with best play (by repetition).
+// Otherwise only wins and losses are proven, None if the result is unknown.
+pub fn solve(pieces: &Pieces, turn: bool) -> Option<Solution> {
+    let mut nodes: Vec<Node> = vec![];
+    let mut index = HashMap::new();
+    let mut complete = true;
+    let mut add = |nodes: &mut Vec<Node>, pieces: Pieces, turn: bool| -> Option<usize> {
+        let key = shogi::encode_pieces(&pieces, turn);
+        if let Some(&i) = index.get(&key) {
+            return Some(i);
+        }
+        if nodes.len() >= MAX_POSITIONS {
+            return None;
+        }
+        nodes.push(Node { pieces, turn, moves: vec![], status: None, unresolved: usize::MAX });
+        index.insert(key, nodes.len() - 1);
+        Some(nodes.len() - 1)
+    };
+    add(&mut nodes, *pieces, turn);
+    let mut next = 0;
+    while next < nodes.len() {
+        let (pieces, turn) = (nodes[next].pieces, nodes[next].turn);
+        if lost(&pieces, turn) {
+            nodes[next].status = Some((Outcome::Loss, 0));
+        } else {
+            let legal = shogi::possible_moves(&pieces, turn);
+            let moves: Vec<(Move, usize)> = legal.iter()
+                .filter_map(|&mov| Some((mov, add(&mut nodes, shogi::play_move(&pieces, mov), !turn)?)))
+                .collect();
+            // a position with moves left out can never be proven lost
+            if moves.len() == legal.len() {
+                nodes[next].unresolved = moves.len();
+            } else {
+                complete = false;
+            }
+            nodes[next].moves = moves;
+        }
+        next += 1;
+    }
+
+    let mut parents = vec![vec![]; nodes.len()];
+    for (i, node) in nodes.iter().enumerate() {
+        for &(_, child) in &node.moves {
+            parents[child].push(i);
+        }
+    }
+    // positions are resolved in order of distance, so the first move found to win is the fastest
+    let mut queue: VecDeque<usize> = (0..nodes.len()).filter(|&i| nodes[i].status.is_some()).collect();
+    while let Some(child) = queue.pop_front() {
+        let Some((outcome, distance)) = nodes[child].status else { continue };
+        for &parent in &parents[child] {
+            let node = &mut nodes[parent];
+            if node.status.is_some() {
+                continue;
+            }
+            match outcome {
+                Outcome::Loss => {
+                    node.status = Some((Outcome::Win, distance + 1));
+                    queue.push_back(parent);
+                }
+                _ if node.unresolved != usize::MAX => {
+                    node.unresolved -= 1;
+                    if node.unresolved == 0 {
+                        node.status = Some((Outcome::Loss, distance + 1));
+                        queue.push_back(parent);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let status = |i: usize| nodes[i].status.unwrap_or((Outcome::Draw, 0));
+    let (outcome, distance) = status(0);
+    if outcome == Outcome::Draw && !complete {
+        return None;
+    }
+    let mut line = vec![];
+    let mut current = 0;
+    for remaining in (1..=distance.max(1)).rev() {
+        let wanted = match status(current).0 {
+            Outcome::Win => (Outcome::Loss, remaining - 1),
+            Outcome::Loss => (Outcome::Win, remaining - 1),
+            Outcome::Draw => (Outcome::Draw, 0),
+        };
+        let Some(&(mov, child)) = nodes[current].moves.iter().find(|&&(_, child)| status(child) == wanted) else { break };
+        line.push(mov);
+        current = child;
+    }
+    let moves = nodes[0].moves.iter().map(|&(mov, child)| {
+        let (outcome, distance) = match nodes[child].status {
+            Some((Outcome::Win, distance)) => (Some(Outcome::Loss), distance + 1),
+            Some((Outcome::Loss, distance)) => (Some(Outcome::Win), distance + 1),
+            _ if complete => (Some(Outcome::Draw), 0),
+            _ => (None, 0),
+        };
+        RootMove { mov, outcome, distance, replies: nodes[child].moves.len() }
+    }).collect();
+    Some(Solution { outcome, distance, line, moves })
+}
+
+#[tauri::command(async)]
+pub fn solve_endgame(pieces: Pieces, turn: bool) -> Result<Option<Solution>, String> {
+    shogi::validate(&pieces)?;
+    Ok(solve(&pieces, turn))
+}
@@ -0,0 +1,261 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, State};
+use crate::human::{self, HumanStyle};
+use crate::shogi::{self, Hint, Limits, Move, Pieces, Progress, Rules, Search, SearchControl, SearchResult};
+use crate::timeman::TimeBudget;
+
+// pondering stops there if the opponent takes long
+const MAX_PONDER_DEPTH: u8 = 30;
+
+pub enum Message {
+    // the position of the next search, after the positions "played" that count for the repetitions
+    SetPosition { pieces: Pieces, played: Vec<Pieces>, turn: bool },
+    // the result is sent on "reply", which is dropped when no position is set
+    Go {
+        limits: Limits,
+        control: Arc<SearchControl>,
+        on_progress: Box<dyn FnMut(&Progress) + Send>,
+        reply: Sender<SearchResult>,
+    },
+    // the move of a human-like player in the position, after its thinking time
+    Human {
+        limits: Limits,
+        style: HumanStyle,
+        budget: Option<Duration>,
+        control: Arc<SearchControl>,
+        reply: Sender<SearchResult>,
+    },
+    // searches the position after the reply the engine expects, until the next search
+    Ponder { pieces: Pieces, turn: bool, rules: Rules, moves_left: Option<u32>, control: Arc<SearchControl> },
+    // ages the table rather than dropping it
+    NewGame,
+    // stops the search or the pondering in progress and those queued, it does not wait its turn
+    Stop,
+}
+
+// controls of the work queued or in progress, the pondering ones flagged
+type Running = Arc<Mutex<Vec<(Arc<SearchControl>, bool)>>>;
+
+// what keeps the searches waiting, they only run when nothing holds them
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Hold {
+    Game, // the game is paused
+    Background, // the window is minimized or the app in the background
+}
+
+// The engine of the session, a thread owning the search and its table for the whole life of the
+// app and driven by messages. The commands queue up, so concurrent ones never share the search,
+// and a correct guess of the pondering turns into a deep reply at once.
+// A held search waits where it is, with its table, its move ordering and its best move so far,
+// and goes on when released, without using the processor in the meantime.
+pub struct Engine {
+    sender: Mutex<Sender<Message>>,
+    running: Running,
+    holds: Mutex<Vec<Hold>>,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let running = Running::default();
+        let worker_running = running.clone();
+        thread::spawn(move || work(receiver, worker_running));
+        Engine { sender: Mutex::new(sender), running, holds: Mutex::default() }
+    }
+}
+
+impl Engine {
+    pub fn send(&self, message: Message) {
+        self.post(&self.sender.lock().unwrap(), message);
+    }
+
+    fn post(&self, sender: &Sender<Message>, message: Message) {
+        let held = !self.holds.lock().unwrap().is_empty();
+        let mut running = self.running.lock().unwrap();
+        // the work queued while held waits as well
+        if let Message::Go { control, .. } | Message::Human { control, .. } | Message::Ponder { control, .. } = &message {
+            if held {
+                control.pause();
+            }
+        }
+        match &message {
+            Message::Stop => {
+                for (control, _) in running.iter() {
+                    control.stop();
+                }
+                return;
+            }
+            // a search takes over from the pondering
+            Message::Go { control, .. } | Message::Human { control, .. } => {
+                for (control, _) in running.iter().filter(|(_, pondering)| *pondering) {
+                    control.stop();
+                }
+                running.push((control.clone(), false));
+            }
+            Message::Ponder { control, .. } => running.push((control.clone(), true)),
+            Message::SetPosition { .. } | Message::NewGame => {}
+        }
+        let _ = sender.send(message);
+    }
+
+    // the best move in the position, None if the engine is gone
+    pub fn search(
+        &self,
+        pieces: Pieces,
+        played: Vec<Pieces>,
+        turn: bool,
+        limits: Limits,
+        control: Arc<SearchControl>,
+        on_progress: Box<dyn FnMut(&Progress) + Send>,
+    ) -> Option<SearchResult> {
+        let (reply, result) = mpsc::channel();
+        self.post_at(pieces, played, turn, Message::Go { limits, control, on_progress, reply });
+        result.recv().ok()
+    }
+
+    // the move of a human-like player of "style", None if the engine is gone
+    pub fn play_human(
+        &self,
+        pieces: Pieces,
+        turn: bool,
+        limits: Limits,
+        style: HumanStyle,
+        budget: Option<Duration>,
+        control: Arc<SearchControl>,
+    ) -> Option<SearchResult> {
+        let (reply, result) = mpsc::channel();
+        self.post_at(pieces, vec![], turn, Message::Human { limits, style, budget, control, reply });
+        result.recv().ok()
+    }
+
+    // no other position comes in between
+    fn post_at(&self, pieces: Pieces, played: Vec<Pieces>, turn: bool, message: Message) {
+        let sender = self.sender.lock().unwrap();
+        self.post(&sender, Message::SetPosition { pieces, played, turn });
+        self.post(&sender, message);
+    }
+
+    // Stops the search in progress only, the pondering and the searches queued behind it go on.
+    // A queued search stops the pondering before it, so the first search in the list is running.
+    pub fn stop_search(&self) {
+        if let Some((control, _)) = self.running.lock().unwrap().iter().find(|(_, pondering)| !pondering) {
+            control.stop();
+        }
+    }
+
+    // after a reply the engine did not expect, or at the end of the game
+    pub fn stop_pondering(&self) {
+        for (control, _) in self.running.lock().unwrap().iter().filter(|(_, pondering)| *pondering) {
+            control.stop();
+        }
+    }
+
+    pub fn hold(&self, hold: Hold) {
+        let mut holds = self.holds.lock().unwrap();
+        if !holds.contains(&hold) {
+            holds.push(hold);
+        }
+        for (control, _) in self.running.lock().unwrap().iter() {
+            control.pause();
+        }
+    }
+
+    pub fn release(&self, hold: Hold) {
+        let mut holds = self.holds.lock().unwrap();
+        holds.retain(|&other| other != hold);
+        if holds.is_empty() {
+            for (control, _) in self.running.lock().unwrap().iter() {
+                control.resume();
+            }
+        }
+    }
+}
+
+fn work(receiver: Receiver<Message>, running: Running) {
+    let mut search = Search::new(Arc::new(SearchControl::default()));
+    let mut position = None;
+    // what the last search expected after the reply it predicted
+    let mut expected = None;
+    let finished = |control: &Arc<SearchControl>| {
+        running.lock().unwrap().retain(|(other, _)| !Arc::ptr_eq(other, control));
+    };
+    for message in receiver {
+        match message {
+            Message::SetPosition { pieces, played, turn } => position = Some((pieces, played, turn)),
+            Message::Go { limits, control, mut on_progress, reply } => {
+                search.resume(control.clone());
+                if let Some((pieces, played, turn)) = &position {
+                    if let Some(hint) = expected.take() {
+                        search.set_hint(hint);
+                    }
+                    // without a legal move the reply is dropped
+                    if let Some(result) = shogi::best_move(&mut search, pieces, played, limits, *turn, &mut *on_progress) {
+                        expected = Hint::after_reply(pieces, *turn, &result);
+                        let _ = reply.send(result);
+                    }
+                }
+                finished(&control);
+            }
+            Message::Human { limits, style, budget, control, reply } => {
+                search.resume(control.clone());
+                if let Some((pieces, _, turn)) = &position {
+                    if let Some(result) = human::play(&mut search, pieces, *turn, &limits, style, budget) {
+                        let _ = reply.send(result);
+                    }
+                }
+                finished(&control);
+            }
+            Message::Ponder { pieces, turn, rules, moves_left, control } => {
+                search.resume(control.clone());
+                search.set_rules(rules, moves_left);
+                for depth in 1..=MAX_PONDER_DEPTH {
+                    match search.evaluate(&pieces, turn, depth) {
+                        Some(score) if score.abs() < 100000 => {}
+                        _ => break, // stopped or forced win found
+                    }
+                }
+                finished(&control);
+            }
+            Message::NewGame => search.next_generation(),
+            Message::Stop => {}
+        }
+    }
+}
+
+// the best move at a fixed depth, searched by the engine with its table
+#[tauri::command(async)]
+pub fn shogi_ai(engine: State<Engine>, pieces: Pieces, played: Vec<Pieces>, depth: u8, turn: bool) -> Result<Move, String> {
+    shogi::validate(&pieces)?;
+    let result = engine.search(pieces, played, turn, Limits::depth(depth), Arc::default(), Box::new(|_| ()));
+    result.map(|result| result.mov).ok_or_else(|| "no legal move".into())
+}
+
+// Thinks for "time_ms" at most, deepening as long as the next iteration can complete in time, so
+// that a level takes the same time on any machine. The table of the engine is kept from one call
+// to the next. The progress is sent as "thinking" after each iteration, the result has the depth
+// reached, the score and the principal variation.
+#[tauri::command(async)]
+pub fn shogi_ai_timed(
+    app: AppHandle,
+    engine: State<Engine>,
+    pieces: Pieces,
+    played: Vec<Pieces>,
+    turn: bool,
+    time_ms: u64,
+) -> Result<SearchResult, String> {
+    shogi::validate(&pieces)?;
+    let limits = Limits::time(TimeBudget::fixed(Duration::from_millis(time_ms)));
+    let on_progress = Box::new(move |progress: &Progress| {
+        let _ = app.emit("thinking", progress);
+    });
+    engine.search(pieces, played, turn, limits, Arc::default(), on_progress).ok_or_else(|| "the engine has stopped".into())
+}
+
+// the search in progress ends at once with the best move of its last complete iteration
+#[tauri::command]
+pub fn stop_thinking(engine: State<Engine>) {
+    engine.stop_search();
+}
@@ -0,0 +1,92 @@
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use crate::clock::ClockView;
+use crate::notation;
+use crate::session::{GameResult, GameStateView, Session};
+use crate::shogi::Progress;
+use crate::stream::StreamServer;
+
+// One stream of events per game, "session://<id>" with the id of the session, so that the
+// frontend, a spectator view or an overlay subscribes once instead of polling the state. The
+// watcher compares the session with what it last sent, whatever command changed it.
+
+const WATCH_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Clone, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum SessionEvent {
+    #[serde(rename_all = "camelCase")]
+    MovePlayed { ply: usize, mov: String, state: Box<GameStateView> },
+    // the whole state again, after a takeback or a new game
+    #[serde(rename_all = "camelCase")]
+    Reset { state: Box<GameStateView> },
+    // once a second while a clock runs
+    ClockTick { clock: ClockView },
+    // after each iteration of the engine search
+    Search { progress: Progress },
+    GameOver { result: GameResult },
+    // the local stream server, None when it is stopped
+    Network { port: Option<u16> },
+}
+
+pub fn emit(app: &AppHandle, id: u64, event: SessionEvent) {
+    let _ = app.emit(&format!("session://{id}"), event);
+}
+
+// what the watcher last sent
+struct Sent {
+    id: u64,
+    ply: usize,
+    over: bool,
+    tick: Instant,
+    port: Option<u16>,
+}
+
+pub fn watch(app: AppHandle) {
+    thread::spawn(move || {
+        let mut sent: Option<Sent> = None;
+        loop {
+            let port = app.state::<StreamServer>().port();
+            let session = app.state::<Mutex<Session>>();
+            let session = session.lock().unwrap();
+            let (id, ply, result) = (session.id(), session.ply(), session.result());
+            let mut events = vec![];
+            match &mut sent {
+                Some(last) if last.id == id => {
+                    if ply == last.ply + 1 {
+                        let (pieces, mov) = session.history()[ply - 1];
+                        events.push(SessionEvent::MovePlayed { ply, mov: notation::move_name(&pieces, mov), state: Box::new(session.view()) });
+                    } else if ply != last.ply {
+                        events.push(SessionEvent::Reset { state: Box::new(session.view()) });
+                    }
+                    if let (Some(result), false) = (result, last.over) {
+                        events.push(SessionEvent::GameOver { result });
+                    }
+                    if port != last.port {
+                        events.push(SessionEvent::Network { port });
+                    }
+                }
+                _ => {
+                    events.push(SessionEvent::Reset { state: Box::new(session.view()) });
+                    events.push(SessionEvent::Network { port });
+                }
+            }
+            let mut tick = sent.as_ref().filter(|last| last.id == id).map_or(Instant::now(), |last| last.tick);
+            if let Some(clock) = session.clock().filter(|_| !session.paused() && result.is_none()) {
+                if tick.elapsed() >= Duration::from_secs(1) {
+                    events.push(SessionEvent::ClockTick { clock: clock.view(Instant::now()) });
+                    tick = Instant::now();
+                }
+            }
+            drop(session);
+            for event in events {
+                emit(&app, id, event);
+            }
+            sent = Some(Sent { id, ply, over: result.is_some(), tick, port });
+            thread::sleep(WATCH_INTERVAL);
+        }
+    });
+}
@@ -0,0 +1,65 @@
+use serde::Serialize;
+use crate::notation;
+use crate::shogi::{self, EvalParams, Kind, Piece, Pieces, HAND};
+
+// Breakdown of the static evaluation into its terms, to tell why a position is scored the way it
+// is. Every contribution is from the point of view of the first player, like the score, and the
+// terms add up to the total. The evaluation has no tempo term, the player to move does not count.
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PieceTerms {
+    pub index: usize,
+    pub kind: Kind,
+    pub owner: bool,
+    pub square: Option<String>, // None in hand
+    pub material: i32,
+    pub placement: i32, // bonus of the square it stands on
+    pub mobility: i32,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvalBreakdown {
+    pub pieces: Vec<PieceTerms>,
+    pub lion_safety: [i32; 2], // penalty of each player, first player first
+    pub total: i32,
+}
+
+fn piece_terms(pieces: &Pieces, index: usize, params: &EvalParams) -> PieceTerms {
+    let piece = pieces[index];
+    let sign = if piece.owner { -1 } else { 1 };
+    let on_board = piece.position < HAND;
+    let square = if piece.owner { HAND - 1 - piece.position } else { piece.position };
+    // squares not occupied by an own piece
+    let free = shogi::attacks(piece).filter(|&s| !pieces.iter().any(|other| other.position == s && other.owner == piece.owner));
+    PieceTerms {
+        index,
+        kind: piece.kind,
+        owner: piece.owner,
+        square: on_board.then(|| notation::square_name(piece.position)),
+        material: sign * params.piece_value[piece.kind as usize],
+        placement: if on_board { sign * params.square_bonus[piece.kind as usize][square as usize] } else { 0 },
+        mobility: sign * params.mobility * free.count() as i32,
+    }
+}
+
+fn lion_safety(pieces: &Pieces, owner: bool, params: &EvalParams) -> i32 {
+    let sign = if owner { -1 } else { 1 };
+    let around = shogi::attacks(Piece { kind: Kind::Lion, ..*shogi::lion(pieces, owner) });
+    -sign * params.lion_safety * around.filter(|&square| shogi::is_attacked(pieces, square, !owner)).count() as i32
+}
+
+pub fn explain(pieces: &Pieces, params: &EvalParams) -> EvalBreakdown {
+    EvalBreakdown {
+        pieces: (0..pieces.len()).map(|i| piece_terms(pieces, i, params)).collect(),
+        lion_safety: [false, true].map(|owner| lion_safety(pieces, owner, params)),
+        total: shogi::evaluate_position(pieces, params),
+    }
+}
+
+#[tauri::command]
+pub fn explain_eval(pieces: Pieces) -> Result<EvalBreakdown, String> {
+    shogi::validate(&pieces)?;
+    Ok(explain(&pieces, &shogi::eval_params()))
+}
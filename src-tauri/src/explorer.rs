@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use serde::Serialize;
+use tauri::State;
+use crate::archive::{self, ArchivedGame};
+use crate::census;
+use crate::notation;
+use crate::shogi::{self, Pieces};
+use crate::store::Store;
+
+// Opening explorer over the archived games: the moves played in a position, how often and with
+// what results. Positions are indexed by their code with the twins in a fixed order, so that the
+// same position reached by different move orders counts once, and moves by their name.
+
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MoveStats {
+    pub mov: String,
+    pub games: usize,
+    // for the player to move in the position
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+}
+
+fn key(pieces: &Pieces, turn: bool) -> u64 {
+    shogi::encode_pieces(&census::sort_twins(pieces), turn)
+}
+
+// a move of an archived game: the position, the name of the move and the player who made it
+type Entry = (u64, String, bool);
+
+// every position of the games with the moves played from it
+#[derive(Default)]
+pub struct Index {
+    positions: HashMap<u64, Vec<MoveStats>>,
+    // what each game adds, to take it out again when its record is replaced
+    games: HashMap<u32, (Vec<Entry>, Option<bool>)>,
+}
+
+impl Index {
+    pub fn new(games: &[ArchivedGame]) -> Self {
+        let mut index = Index::default();
+        for game in games {
+            index.insert(game);
+        }
+        index
+    }
+
+    // a game joining the archive, or a new record of one already there
+    pub fn insert(&mut self, game: &ArchivedGame) {
+        self.remove(game.id);
+        let Some((mut pieces, mut turn)) = notation::parse_position(&game.start) else { return };
+        let winner = game.result.and_then(|result| result.winner);
+        let mut entries = vec![];
+        for &mov in &game.moves {
+            let entry = (key(&pieces, turn), notation::move_name(&pieces, mov), turn);
+            self.count(&entry, winner, true);
+            entries.push(entry);
+            pieces = shogi::play_move(&pieces, mov);
+            turn = !turn;
+        }
+        self.games.insert(game.id, (entries, winner));
+    }
+
+    fn remove(&mut self, id: u32) {
+        let Some((entries, winner)) = self.games.remove(&id) else { return };
+        for entry in &entries {
+            self.count(entry, winner, false);
+        }
+    }
+
+    // the game counted in the statistics of the move, or taken out of them
+    fn count(&mut self, (key, name, turn): &Entry, winner: Option<bool>, add: bool) {
+        let moves = self.positions.entry(*key).or_default();
+        let i = match moves.iter().position(|stats| stats.mov == *name) {
+            Some(i) => i,
+            None => {
+                moves.push(MoveStats { mov: name.clone(), ..MoveStats::default() });
+                moves.len() - 1
+            }
+        };
+        let stats = &mut moves[i];
+        let step = |count: &mut usize| if add { *count += 1 } else { *count = count.saturating_sub(1) };
+        step(&mut stats.games);
+        match winner {
+            Some(winner) if winner == *turn => step(&mut stats.wins),
+            Some(_) => step(&mut stats.losses),
+            None => step(&mut stats.draws),
+        }
+        if stats.games == 0 {
+            moves.remove(i);
+        }
+    }
+
+    // the most played first
+    pub fn moves(&self, pieces: &Pieces, turn: bool) -> Vec<MoveStats> {
+        let mut moves = self.positions.get(&key(pieces, turn)).cloned().unwrap_or_default();
+        moves.sort_by(|a, b| b.games.cmp(&a.games).then(a.mov.cmp(&b.mov)));
+        moves
+    }
+}
+
+// The index of the archive, built on first use and kept up to date as games are archived
+// rather than built again for each position.
+#[derive(Default)]
+pub struct Explorer(Mutex<Option<Index>>);
+
+impl Explorer {
+    pub fn archived(&self, game: &ArchivedGame) {
+        if let Some(index) = self.0.lock().unwrap().as_mut() {
+            index.insert(game);
+        }
+    }
+
+    // after the archive has been rewritten as a whole
+    pub fn reset(&self) {
+        *self.0.lock().unwrap() = None;
+    }
+}
+
+#[tauri::command]
+pub fn explore_position(store: State<Store>, explorer: State<Explorer>, pieces: Pieces, turn: bool) -> Result<Vec<MoveStats>, String> {
+    shogi::validate(&pieces)?;
+    let mut index = explorer.0.lock().unwrap();
+    Ok(index.get_or_insert_with(|| Index::new(&archive::load(&store))).moves(&pieces, turn))
+}
@@ -0,0 +1,99 @@
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use crate::endgame;
+use crate::selfplay::Rng;
+use crate::shogi::{self, Limits, Move, Pieces, Search, SearchResult, Standard, HAND};
+
+// An opponent that plays like a person rather than like a weakened engine. Every move is scored,
+// then the move is drawn among them, the better ones more likely the stronger the player. Some
+// moves that people tend to overlook, drops far from the action and moves backward, are left out
+// at times, and the reply comes after a thinking time that grows with the difficulty of the choice.
+
+// scores beyond this are all alike to a person, a mate is a mate
+const SCORE_CAP: i32 = 2000;
+const BASE_THINKING: Duration = Duration::from_millis(1500);
+const MAX_THINKING: Duration = Duration::from_secs(12);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HumanStyle {
+    pub strength: u8, // from 0, a beginner, to 100, the engine at the depth of the level
+}
+
+impl HumanStyle {
+    fn weakness(&self) -> f64 {
+        1.0 - self.strength.min(100) as f64 / 100.0
+    }
+
+    // how far below the best move, in points, a move still has a fair chance
+    fn temperature(&self) -> f64 {
+        5.0 + 150.0 * self.weakness().powi(2)
+    }
+
+    fn oversight(&self) -> f64 {
+        0.5 * self.weakness()
+    }
+}
+
+const WIDTH: u8 = Standard::WIDTH as u8;
+
+fn distance(a: u8, b: u8) -> u8 {
+    (a / WIDTH).abs_diff(b / WIDTH).max((a % WIDTH).abs_diff(b % WIDTH))
+}
+
+// a drop away from every piece of the player, or a piece going back towards its own side
+fn easily_overlooked(pieces: &Pieces, mov: Move) -> bool {
+    let piece = pieces[mov.from];
+    let to = mov.to as u8;
+    if piece.position == HAND {
+        pieces.iter().all(|other| other.owner != piece.owner || other.position == HAND || distance(other.position, to) >= 2)
+    } else if piece.owner {
+        to / WIDTH < piece.position / WIDTH
+    } else {
+        to / WIDTH > piece.position / WIDTH
+    }
+}
+
+// the move and how long a person would have thought about it, the moves are scored by "search" at
+// the depth of "limits", None if the search was stopped
+pub fn choose(search: &mut Search, pieces: &Pieces, turn: bool, limits: &Limits, style: HumanStyle, rng: &mut Rng) -> Option<(SearchResult, Duration)> {
+    let (depth, rules) = (limits.depth, limits.rules);
+    search.set_rules(rules, limits.moves_left);
+    let sign = if turn { -1 } else { 1 };
+    let mut scored = vec![];
+    for mov in shogi::legal_moves(pieces, turn, &rules) {
+        let child = shogi::play_move(pieces, mov);
+        let score = if endgame::lost(&child, !turn) { sign * 100000 } else { search.evaluate(&child, !turn, depth.max(1) - 1)? };
+        scored.push((mov, score));
+    }
+    let best = scored.iter().map(|&(_, score)| sign * score).max()?;
+    let seen: Vec<(Move, i32)> = scored.iter().copied()
+        .filter(|&(mov, score)| sign * score == best || !(easily_overlooked(pieces, mov) && rng.unit() < style.oversight()))
+        .collect();
+    let weights: Vec<f64> = seen.iter()
+        .map(|&(_, score)| ((sign * score).min(SCORE_CAP) - best.min(SCORE_CAP)) as f64 / style.temperature())
+        .map(f64::exp)
+        .collect();
+    let mut draw = rng.unit() * weights.iter().sum::<f64>();
+    let i = weights.iter().position(|&weight| {
+        draw -= weight;
+        draw < 0.0
+    }).unwrap_or(seen.len() - 1);
+    let (mov, score) = seen[i];
+    // the more moves look alike, the longer the hesitation
+    let close = weights.iter().filter(|&&weight| weight > 0.5).count();
+    let thinking = BASE_THINKING.mul_f64((1.0 + 0.5 * (close as f64).ln()) * (0.5 + rng.unit())).min(MAX_THINKING);
+    Some((SearchResult { mov, score, depth, pv: vec![mov] }, thinking))
+}
+
+// The move after the thinking time, "budget" caps the time in timed games. The thinking time
+// stands still while the search is paused and ends with a stop.
+pub fn play(search: &mut Search, pieces: &Pieces, turn: bool, limits: &Limits, style: HumanStyle, budget: Option<Duration>) -> Option<SearchResult> {
+    let start = Instant::now();
+    let (result, thinking) = choose(search, pieces, turn, limits, style, &mut Rng::from_clock())?;
+    let thinking = budget.map_or(thinking, |budget| thinking.min(budget));
+    if let Some(rest) = thinking.checked_sub(start.elapsed()) {
+        search.control().sleep(rest);
+    }
+    Some(result)
+}
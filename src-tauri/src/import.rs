@@ -0,0 +1,285 @@
+use std::sync::Mutex;
+use serde::Serialize;
+use tauri::State;
+use crate::analysis::Analysis;
+use crate::correspondence;
+use crate::notation;
+use crate::reachability::{self, Issue};
+use crate::session::{GameStateView, Session};
+use crate::shogi::{self, Kind, Move, Standard, HAND};
+use crate::store::Store;
+
+// Text pasted by the user, whatever its format. Game records start from the initial position.
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ImportFormat { Position, ShareCode, MoveList, Kif, Csa }
+
+// the first move of a record that cannot be played
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Offense {
+    pub number: usize, // of the move in the record, from 1
+    pub text: String,
+    pub reason: String,
+}
+
+impl Offense {
+    fn new(number: usize, text: &str, reason: impl Into<String>) -> Self {
+        Offense { number, text: text.trim().to_string(), reason: reason.into() }
+    }
+
+    pub fn message(&self) -> String {
+        format!("move {} \"{}\": {}", self.number, self.text, self.reason)
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Imported {
+    pub format: ImportFormat,
+    pub state: GameStateView,
+    pub issues: Vec<Issue>, // why an imported position cannot occur in a game
+}
+
+const KIF_PIECES: [(&str, Kind); 11] = [
+    ("ひよこ", Kind::Chick), ("歩", Kind::Chick),
+    ("ぞう", Kind::Elephant), ("象", Kind::Elephant), ("角", Kind::Elephant),
+    ("きりん", Kind::Giraffe), ("飛", Kind::Giraffe),
+    ("ライオン", Kind::Lion), ("玉", Kind::Lion),
+    ("にわとり", Kind::Hen), ("と", Kind::Hen),
+];
+const CSA_PIECES: [(&str, Kind); 9] = [
+    ("FU", Kind::Chick), ("TO", Kind::Hen), ("KA", Kind::Elephant), ("ZO", Kind::Elephant),
+    ("HI", Kind::Giraffe), ("KI", Kind::Giraffe), ("OU", Kind::Lion), ("LI", Kind::Lion), ("NW", Kind::Hen),
+];
+
+// Squares of the records are numbered as in shogi: files 1 to 3 from right to left, ranks 1 to 4
+// from top to bottom, seen from the first player.
+fn record_square(file: u32, rank: u32) -> Option<u8> {
+    let (width, height) = (Standard::WIDTH as u32, Standard::HEIGHT as u32);
+    ((1..=width).contains(&file) && (1..=height).contains(&rank)).then(|| (width * (rank - 1) + width - file) as u8)
+}
+
+// The legal move from "from" (None for a drop of "kind") to "to", records do not decline promotions
+fn find_move(session: &Session, from: Option<u8>, to: u8, kind: Option<Kind>) -> Option<Move> {
+    let pieces = session.pieces();
+    shogi::legal_moves(pieces, session.turn(), &session.rules()).into_iter().find(|mov| {
+        let piece = pieces[mov.from];
+        mov.to as u8 == to && match from {
+            Some(from) => piece.position == from,
+            None => piece.position == HAND && kind.is_none_or(|kind| piece.kind == kind),
+        }
+    })
+}
+
+// the value of a tag pair '[Name "value"]' of an exported game
+fn tag<'a>(text: &'a str, name: &str) -> Option<&'a str> {
+    text.lines().find_map(|line| line.trim().strip_prefix('[')?.strip_prefix(name)?.trim().strip_prefix('"')?.strip_suffix("\"]"))
+}
+
+// "Gc1-c2 Cb3xb2+ ...", move numbers, results and tag pairs are skipped
+fn parse_move_list(mut session: Session, text: &str) -> Result<Session, Offense> {
+    let mut number = 0;
+    let moves = text.lines().filter(|line| !line.trim_start().starts_with('['));
+    for token in moves.flat_map(str::split_whitespace) {
+        if token.trim_end_matches('.').chars().all(|c| c.is_ascii_digit()) || matches!(token, "1-0" | "0-1" | "1/2-1/2" | "*") {
+            continue;
+        }
+        number += 1;
+        let mov = notation::parse_move(session.pieces(), session.turn(), &session.rules(), token)
+            .ok_or_else(|| Offense::new(number, token, "not a legal move"))?;
+        session.play(mov).map_err(|err| Offense::new(number, token, err))?;
+    }
+    Ok(session)
+}
+
+fn kif_digit(c: char) -> Option<u32> {
+    match c {
+        '1'..='9' => c.to_digit(10),
+        '１'..='９' => Some(c as u32 - '１' as u32 + 1),
+        '一' => Some(1),
+        '二' => Some(2),
+        '三' => Some(3),
+        '四' => Some(4),
+        _ => None,
+    }
+}
+
+// "   3 ２三きりん(33)", "   4 同　ひよこ(22)" or "   5 ３二ぞう打"
+fn parse_kif(mut session: Session, text: &str) -> Result<Session, Offense> {
+    let mut last_to = None;
+    for line in text.lines() {
+        let mut fields = line.split_whitespace();
+        let Some(Ok(number)) = fields.next().map(str::parse::<usize>) else {
+            continue; // headers and comments
+        };
+        let mut notation: String = fields.collect::<Vec<_>>().join("");
+        notation = notation.replace('　', "");
+        if notation.starts_with("投了") || notation.starts_with("中断") || notation.starts_with("千日手") {
+            break;
+        }
+        let error = || Offense::new(number, line, "cannot read the move");
+        let (to, rest) = match notation.strip_prefix('同') {
+            Some(rest) => (last_to.ok_or_else(error)?, rest.to_string()),
+            None => {
+                let mut chars = notation.chars();
+                let file = chars.next().and_then(kif_digit).ok_or_else(error)?;
+                let rank = chars.next().and_then(kif_digit).ok_or_else(error)?;
+                (record_square(file, rank).ok_or_else(error)?, chars.collect())
+            }
+        };
+        let (name, kind) = KIF_PIECES.iter().find(|(name, _)| rest.starts_with(name)).ok_or_else(error)?;
+        let rest = &rest[name.len()..];
+        let from = if rest.starts_with('打') {
+            None
+        } else {
+            let digits: Vec<u32> = rest.chars().filter_map(|c| c.to_digit(10)).collect();
+            match digits.as_slice() {
+                [file, rank, ..] => Some(record_square(*file, *rank).ok_or_else(error)?),
+                _ => return Err(error()),
+            }
+        };
+        let mov = find_move(&session, from, to, Some(*kind)).ok_or_else(|| Offense::new(number, line, "illegal move"))?;
+        session.play(mov).map_err(|err| Offense::new(number, line, err))?;
+        last_to = Some(to);
+    }
+    Ok(session)
+}
+
+// "+2332HI", a drop starts with "00", several moves may share a line separated by commas
+fn parse_csa(mut session: Session, text: &str) -> Result<Session, Offense> {
+    let mut number = 0;
+    for token in text.lines().flat_map(|line| line.split(',')).map(str::trim) {
+        if token.starts_with('%') {
+            break; // end of the game
+        }
+        let bytes = token.as_bytes();
+        if bytes.len() < 7 || !matches!(bytes[0], b'+' | b'-') || !bytes[1..5].iter().all(u8::is_ascii_digit) {
+            continue; // headers, times and comments
+        }
+        number += 1;
+        let error = || Offense::new(number, token, "cannot read the move");
+        let digit = |i: usize| (bytes[i] - b'0') as u32;
+        let from = match (digit(1), digit(2)) {
+            (0, 0) => None,
+            (file, rank) => Some(record_square(file, rank).ok_or_else(error)?),
+        };
+        let to = record_square(digit(3), digit(4)).ok_or_else(error)?;
+        let kind = token.get(5..7).and_then(|code| CSA_PIECES.iter().find(|(c, _)| *c == code)).map(|&(_, kind)| kind);
+        let mov = find_move(&session, from, to, kind).ok_or_else(|| Offense::new(number, token, "illegal move"))?;
+        session.play(mov).map_err(|err| Offense::new(number, token, err))?;
+    }
+    Ok(session)
+}
+
+fn is_csa(text: &str) -> bool {
+    text.lines().any(|line| {
+        let bytes = line.trim().as_bytes();
+        bytes.len() >= 7 && matches!(bytes[0], b'+' | b'-') && bytes[1..5].iter().all(u8::is_ascii_digit)
+    })
+}
+
+fn is_kif(text: &str) -> bool {
+    text.contains("手数") || KIF_PIECES.iter().any(|(name, _)| name.chars().count() > 1 && text.contains(name))
+}
+
+// A game record played from "start", its format recognized. A text that is no record at all
+// is read as a move list.
+pub fn parse_record(start: Session, text: &str) -> (ImportFormat, Result<Session, Offense>) {
+    if is_kif(text) {
+        (ImportFormat::Kif, parse_kif(start, text))
+    } else if is_csa(text) {
+        (ImportFormat::Csa, parse_csa(start, text))
+    } else {
+        (ImportFormat::MoveList, parse_move_list(start, text))
+    }
+}
+
+pub fn import(text: &str) -> Result<(ImportFormat, Session), String> {
+    let text = text.trim();
+    if let Some((pieces, turn)) = notation::parse_position(text) {
+        return Ok((ImportFormat::Position, Session::from_position(pieces, turn)));
+    }
+    if let Some((pieces, turn)) = notation::parse_share_code(text) {
+        return Ok((ImportFormat::ShareCode, Session::from_position(pieces, turn)));
+    }
+    // an exported game tells the position it started from
+    let start = match tag(text, "Position").and_then(notation::parse_position) {
+        Some((pieces, turn)) => Session::from_position(pieces, turn),
+        None => Session::default(),
+    };
+    match parse_record(start, text) {
+        (ImportFormat::MoveList, Ok(session)) if session.ply() > 0 => Ok((ImportFormat::MoveList, session)),
+        (ImportFormat::MoveList, _) => Err("the text is not a position, a share code or a game record".into()),
+        (format, record) => Ok((format, record.map_err(|offense| offense.message())?)),
+    }
+}
+
+// a position replaces the game by a game from this position, a record by the game it describes
+#[tauri::command]
+pub fn import_from_clipboard(
+    session: State<Mutex<Session>>,
+    analysis: State<Analysis>,
+    store: State<Store>,
+    text: String,
+) -> Result<Imported, String> {
+    let (format, imported) = import(&text)?;
+    analysis.stop();
+    let mut session = session.lock().unwrap();
+    *session = imported;
+    correspondence::save(&store, &session);
+    let issues = match format {
+        ImportFormat::Position | ImportFormat::ShareCode => reachability::issues(session.pieces(), session.turn()),
+        _ => vec![],
+    };
+    Ok(Imported { format, state: session.view(), issues })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // names of the moves of an imported record
+    fn names(session: &Session) -> Vec<String> {
+        notation::move_list(&shogi::initial_pieces(), &session.moves())
+    }
+
+    #[test]
+    fn record_files_go_from_right_to_left() {
+        assert_eq!(record_square(3, 1), Some(0));
+        assert_eq!(record_square(1, 1), Some(2));
+        assert_eq!(record_square(2, 3), notation::parse_square("b2"));
+        assert_eq!(record_square(1, 4), notation::parse_square("c1"));
+        assert_eq!(record_square(0, 1), None);
+        assert_eq!(record_square(4, 1), None);
+        assert_eq!(record_square(1, 5), None);
+    }
+
+    #[test]
+    fn kif_reads_same_square_full_width_digits_and_drops() {
+        let text = "手数----指手---------消費時間--\n   1 ２二ひよこ(23)\n   2 同　ライオン(21)\n   3 3三ひよこ打\n   4 ３二きりん(31)\n   5 投了\n";
+        let session = parse_kif(Session::default(), text).unwrap();
+        assert_eq!(names(&session), ["Cb2xb3", "Lb4xb3", "C*a2", "Ga4-a3"]);
+    }
+
+    #[test]
+    fn kif_reports_the_illegal_move() {
+        let offense = parse_kif(Session::default(), "   1 ２二ひよこ(23)\n   2 ２二ライオン(21)\n   3 １一ぞう打\n").err().unwrap();
+        assert_eq!((offense.number, offense.reason.as_str()), (3, "illegal move"));
+    }
+
+    #[test]
+    fn csa_reads_comma_separated_moves_and_drops() {
+        let text = "V2.2\nPI\n+\n+2322FU,-2122OU\n+0033FU\nT3\n-3132HI\n+1413KI\n%TORYO\n+3323FU\n";
+        let session = parse_csa(Session::default(), text).unwrap();
+        assert_eq!(names(&session), ["Cb2xb3", "Lb4xb3", "C*a2", "Ga4-a3", "Gc1-c2"]);
+    }
+
+    #[test]
+    fn records_are_recognized() {
+        assert_eq!(parse_record(Session::default(), "   1 ２二ひよこ(23)").0, ImportFormat::Kif);
+        assert_eq!(parse_record(Session::default(), "+2322FU").0, ImportFormat::Csa);
+        assert_eq!(parse_record(Session::default(), "1. Cb2xb3 Lb4xb3").0, ImportFormat::MoveList);
+    }
+}
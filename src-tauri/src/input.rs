@@ -0,0 +1,134 @@
+use std::sync::Mutex;
+use serde::Serialize;
+use tauri::State;
+use crate::notation;
+use crate::session::Session;
+use crate::shogi::{self, Kind, Move, Pieces, Rules, Standard, HAND};
+
+// Tolerant reading of moves typed or dictated in free form, such as "chick takes b2",
+// "drop elephant a3" or "giraffe bee three". English and French words are understood.
+
+const KIND_WORDS: [(&str, Kind); 16] = [
+    ("chick", Kind::Chick), ("chicken", Kind::Chick), ("pawn", Kind::Chick), ("poussin", Kind::Chick),
+    ("elephant", Kind::Elephant), ("éléphant", Kind::Elephant), ("bishop", Kind::Elephant),
+    ("giraffe", Kind::Giraffe), ("girafe", Kind::Giraffe), ("rook", Kind::Giraffe),
+    ("lion", Kind::Lion), ("king", Kind::Lion), ("roi", Kind::Lion),
+    ("hen", Kind::Hen), ("poule", Kind::Hen), ("tokin", Kind::Hen),
+];
+const DROP_WORDS: [&str; 5] = ["drop", "drops", "dropped", "parachute", "parachuté"];
+const CAPTURE_WORDS: [&str; 8] = ["takes", "take", "captures", "capture", "capturing", "x", "prend", "mange"];
+// how speech recognition tends to write the files and ranks
+const FILE_WORDS: [(&str, u8); 6] = [("a", 0), ("b", 1), ("bee", 1), ("be", 1), ("c", 2), ("see", 2)];
+const RANK_WORDS: [(&str, u8); 12] = [
+    ("1", 1), ("2", 2), ("3", 3), ("4", 4),
+    ("one", 1), ("two", 2), ("three", 3), ("four", 4),
+    ("un", 1), ("deux", 2), ("trois", 3), ("quatre", 4),
+];
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Candidate {
+    #[serde(rename = "move")]
+    pub mov: Move,
+    pub name: String,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Interpretation {
+    #[serde(rename = "move")]
+    pub mov: Option<Move>, // the only legal move matching the input
+    pub candidates: Vec<Candidate>, // the legal moves matching the input, to choose from when there are several
+}
+
+// what the input says about the move
+#[derive(Default)]
+struct Clues {
+    kinds: Vec<Kind>, // the moving piece, then the captured one
+    squares: Vec<u8>, // the origin and the destination, or only the destination
+    drop: bool,
+    capture: bool,
+}
+
+fn rank(word: &str) -> Option<u8> {
+    RANK_WORDS.iter().find(|(w, _)| *w == word).map(|&(_, rank)| rank)
+}
+
+fn clues(text: &str) -> Clues {
+    let text = text.to_lowercase().replace('*', " drop ");
+    let words: Vec<&str> = text.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()).collect();
+    let mut clues = Clues::default();
+    let mut i = 0;
+    while i < words.len() {
+        let word = words[i];
+        if let Some(&(_, kind)) = KIND_WORDS.iter().find(|(w, _)| *w == word) {
+            clues.kinds.push(kind);
+        } else if DROP_WORDS.contains(&word) {
+            clues.drop = true;
+        } else if CAPTURE_WORDS.contains(&word) {
+            clues.capture = true;
+        } else if let Some(square) = notation::parse_square(word) {
+            clues.squares.push(square);
+        } else if let Some(&(_, file)) = FILE_WORDS.iter().find(|(w, _)| *w == word) {
+            // a file alone is only a square when a rank follows, "a" is also an article
+            if let Some(rank) = words.get(i + 1).and_then(|w| rank(w)) {
+                clues.squares.push(Standard::WIDTH as u8 * (Standard::HEIGHT as u8 - rank) + file);
+                i += 1;
+            }
+        } else if let Some((kind, from, to)) = compact_move(word) {
+            // "gc1c2" or "c1c2" once the separators are gone
+            clues.kinds.extend(kind);
+            clues.squares.extend([from, to]);
+        }
+        i += 1;
+    }
+    clues
+}
+
+// a move written without separators, with or without the piece letter
+fn compact_move(word: &str) -> Option<(Option<Kind>, u8, u8)> {
+    let (kind, squares) = match word.len() {
+        5 => (Some(notation::parse_kind(word.chars().next()?)?), &word[1..]),
+        4 => (None, word),
+        _ => return None,
+    };
+    Some((kind, notation::parse_square(squares.get(..2)?)?, notation::parse_square(squares.get(2..)?)?))
+}
+
+pub fn interpret(pieces: &Pieces, turn: bool, rules: &Rules, text: &str) -> Interpretation {
+    let legal = shogi::legal_moves(pieces, turn, rules);
+    // the usual notation is read first
+    if let Some(mov) = notation::parse_move(pieces, turn, rules, text) {
+        return Interpretation { mov: Some(mov), candidates: vec![Candidate { mov, name: notation::move_name(pieces, mov) }] };
+    }
+    let clues = clues(text);
+    if clues.kinds.is_empty() && clues.squares.is_empty() {
+        return Interpretation::default();
+    }
+    let matches: Vec<Move> = legal.into_iter().filter(|&mov| {
+        let piece = pieces[mov.from];
+        let captured = pieces.iter().find(|p| p.position == mov.to as u8);
+        let dropped = piece.position == HAND;
+        clues.kinds.first().is_none_or(|&kind| kind == piece.kind)
+            && clues.kinds.get(1).is_none_or(|&kind| captured.is_some_and(|p| p.kind == kind))
+            && (!clues.drop || dropped)
+            && (!clues.capture || captured.is_some())
+            && match clues.squares.as_slice() {
+                [] => true,
+                [to] => mov.to as u8 == *to,
+                [from, to, ..] => !dropped && piece.position == *from && mov.to as u8 == *to,
+            }
+    }).collect();
+    let candidates = matches.iter().map(|&mov| Candidate { mov, name: notation::move_name(pieces, mov) }).collect();
+    match matches.as_slice() {
+        [mov] => Interpretation { mov: Some(*mov), candidates },
+        _ => Interpretation { mov: None, candidates },
+    }
+}
+
+// the input read against the position of the current game
+#[tauri::command]
+pub fn interpret_move(session: State<Mutex<Session>>, text: String) -> Interpretation {
+    let session = session.lock().unwrap();
+    interpret(session.pieces(), session.turn(), &session.rules(), &text)
+}
@@ -0,0 +1,150 @@
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
+use crate::calibration::Calibration;
+use crate::correspondence;
+use crate::human::{self, HumanStyle};
+use crate::selfplay::{self, Adjudication, Mover, Rng};
+use crate::session::{AiLevel, Session};
+use crate::shogi::{self, Limits, Search};
+use crate::store::Store;
+use crate::strength;
+
+// The strength of the engine settings measured on this machine: each setting plays pairs of games
+// against the one below it in the ladder, and the score gives the difference in rating. The first
+// setting is anchored at its nominal rating, so that the ladder can label the difficulty levels.
+
+pub const FILE: &str = "ladder.json";
+const RANDOM_PLIES: usize = 4;
+// the largest difference a match can show, a clean sweep says little more than "much stronger"
+const MAX_DIFFERENCE: f64 = 800.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LevelSetting {
+    pub depth: u8,
+    #[serde(default)]
+    pub human: Option<HumanStyle>, // the noise of the human-like opponent
+}
+
+fn default_settings() -> Vec<LevelSetting> {
+    (1..=6).map(|depth| LevelSetting { depth, human: None }).collect()
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Rung {
+    pub setting: LevelSetting,
+    pub rating: f64,
+    pub score: f64, // points per game against the setting below, 0.5 for the first
+    pub games: usize,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Ladder {
+    pub rungs: Vec<Rung>,
+    pub measured_ms: u64,
+}
+
+impl Ladder {
+    // the rung of the setting, or the one at the same depth without noise
+    pub fn rating(&self, setting: LevelSetting) -> Option<f64> {
+        let rung = |setting: LevelSetting| self.rungs.iter().find(|rung| rung.setting == setting);
+        rung(setting).or_else(|| rung(LevelSetting { human: None, ..setting })).map(|rung| rung.rating)
+    }
+}
+
+pub fn load(store: &Store) -> Option<Ladder> {
+    store.load(FILE)
+}
+
+// the engine of a setting, searching under the rules of the game
+pub fn mover(setting: LevelSetting, seed: u64) -> impl FnMut(&Session) -> Option<shogi::SearchResult> {
+    let mut search = Search::default();
+    let mut rng = Rng::new(seed);
+    move |session: &Session| {
+        let (pieces, turn) = (session.pieces(), session.turn());
+        let limits = Limits::depth(setting.depth).with_rules(session.rules(), session.ply());
+        let chosen = setting.human.and_then(|style| human::choose(&mut search, pieces, turn, &limits, style, &mut rng));
+        chosen.map(|(result, _)| result)
+            .or_else(|| shogi::best_move(&mut search, pieces, &session.played(), limits, turn, &mut |_| ()))
+    }
+}
+
+// points of "a" per game over "pairs" pairs of games with the colours reversed
+fn match_score(a: LevelSetting, b: LevelSetting, pairs: usize, rng: &mut Rng) -> f64 {
+    let mut points = 0.0;
+    for _ in 0..pairs {
+        let seed = rng.next_u64();
+        for a_first in [true, false] {
+            let (mut first, mut second) = (mover(a, seed), mover(b, seed ^ 1));
+            let movers: [&mut Mover; 2] = if a_first { [&mut first, &mut second] } else { [&mut second, &mut first] };
+            let session = selfplay::play_game_with(movers, RANDOM_PLIES, Some(&Adjudication::default()), &mut Rng::new(seed));
+            points += match session.result().and_then(|result| result.winner) {
+                Some(winner) => (winner != a_first) as u8 as f64,
+                None => 0.5,
+            };
+        }
+    }
+    points / (2 * pairs) as f64
+}
+
+// the difference in rating given by a score, one game more for each side keeps a sweep finite
+fn difference(score: f64, games: usize) -> f64 {
+    let score = (score * games as f64 + 0.5) / (games as f64 + 1.0);
+    (400.0 * (score / (1.0 - score)).log10()).clamp(-MAX_DIFFERENCE, MAX_DIFFERENCE)
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LadderProgress {
+    rung: usize,
+    rungs: usize,
+    rating: f64,
+}
+
+// the settings in increasing strength, "on_rung" is called as each one is rated
+pub fn build(settings: &[LevelSetting], pairs: usize, rng: &mut Rng, on_rung: &mut dyn FnMut(&Rung)) -> Ladder {
+    let mut rungs: Vec<Rung> = vec![];
+    for &setting in settings {
+        let rung = match rungs.last() {
+            None => Rung { setting, rating: strength::nominal_rating(setting.depth), score: 0.5, games: 0 },
+            Some(below) => {
+                let score = match_score(setting, below.setting, pairs, rng);
+                Rung { setting, rating: below.rating + difference(score, 2 * pairs), score, games: 2 * pairs }
+            }
+        };
+        on_rung(&rung);
+        rungs.push(rung);
+    }
+    Ladder { rungs, measured_ms: correspondence::now_ms() }
+}
+
+#[tauri::command(async)]
+pub fn calibrate_levels(app: AppHandle, store: State<Store>, settings: Option<Vec<LevelSetting>>, pairs: usize) -> Result<Ladder, String> {
+    let settings = settings.unwrap_or_else(default_settings);
+    let rungs = settings.len();
+    let mut rung = 0;
+    let ladder = build(&settings, pairs.max(1), &mut Rng::from_clock(), &mut |done| {
+        rung += 1;
+        let _ = app.emit("ladder-progress", LadderProgress { rung, rungs, rating: done.rating });
+    });
+    store.save(FILE, &ladder)?;
+    Ok(ladder)
+}
+
+#[tauri::command]
+pub fn level_ladder(store: State<Store>) -> Option<Ladder> {
+    load(&store)
+}
+
+// the measured rating of a difficulty level, None until the ladder has been built
+#[tauri::command]
+pub fn level_rating(store: State<Store>, calibration: State<Calibration>, level: AiLevel) -> Option<f64> {
+    let depth = match level.respond_within_ms {
+        Some(ms) => calibration.limits(Duration::from_millis(ms)).depth,
+        None => level.depth,
+    };
+    load(&store)?.rating(LevelSetting { depth, human: level.human })
+}
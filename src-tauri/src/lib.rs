@@ -1,6 +1,119 @@
+pub mod analysis;
+pub mod analysis_board;
+pub mod arbiter;
+pub mod archive;
+pub mod assets;
+pub mod balance;
+pub mod board_image;
+pub mod bookmarks;
+pub mod calibration;
+pub mod census;
+pub mod clock;
+pub mod commentary;
+pub mod correspondence;
+pub mod crosscheck;
+pub mod crowd;
+pub mod dataset;
+pub mod diff;
+pub mod drill;
+pub mod endgame;
+pub mod engine;
+pub mod events;
+pub mod explain;
+pub mod explorer;
+pub mod human;
+pub mod import;
+pub mod input;
+pub mod ladder;
+pub mod mate;
+pub mod nn;
+pub mod notation;
+pub mod overlay;
+pub mod policy;
+pub mod raster;
+pub mod reachability;
+pub mod recent;
+pub mod repair;
+pub mod repertoire;
+pub mod replay;
+pub mod review;
+pub mod rollout;
+pub mod selfplay;
+pub mod session;
 pub mod shogi;
+pub mod simul;
+pub mod speech;
+pub mod store;
+pub mod strength;
+pub mod stream;
+pub mod study;
+pub mod suite;
+pub mod summary;
+pub mod symmetry;
+pub mod timeman;
+pub mod tournament;
+pub mod trace;
+pub mod tuning;
+pub mod warning;
 
-use shogi::shogi_ai;
+use std::sync::Mutex;
+use tauri::{Manager, WindowEvent};
+use analysis::{evaluate_batch, start_analysis, stop_analysis, Analysis};
+use analysis_board::{
+    analysis_board_delete, analysis_board_goto, analysis_board_play, analysis_board_state, open_analysis_board, play_from_analysis_board,
+    AnalysisBoard,
+};
+use arbiter::validate_record;
+use archive::{export_game, search_games, set_game_info, update_archived_game};
+use assets::{install_asset, installed_assets, remove_asset};
+use balance::simulate;
+use board_image::export_board_image;
+use bookmarks::{add_bookmark, bookmarks, delete_bookmark, open_bookmark};
+use calibration::{machine_speed, run_benchmark, Calibration};
+use census::position_census;
+use commentary::{commentary, languages, reload_languages};
+use crosscheck::cross_check_movegen;
+use crowd::{start_crowd, stop_crowd, Crowd};
+use dataset::export_dataset;
+use diff::diff_positions;
+use drill::{drill_stats, start_drill};
+use endgame::solve_endgame;
+use engine::{shogi_ai, shogi_ai_timed, stop_thinking, Engine};
+use explain::explain_eval;
+use explorer::{explore_position, Explorer};
+use import::import_from_clipboard;
+use input::interpret_move;
+use ladder::{calibrate_levels, level_ladder, level_rating};
+use mate::solve_mate;
+use nn::train_network;
+use notation::board_coordinates;
+use overlay::overlay;
+use reachability::check_position;
+use recent::{list_recent_sessions, resume_session};
+use repair::migrate_and_repair;
+use repertoire::{add_repertoire_line, delete_repertoire_line, repertoire, repertoire_quiz_move, start_repertoire_quiz, RepertoireTrainer};
+use replay::export_replay_animation;
+use review::{game_review, set_auto_review, Review};
+use rollout::rollout_estimate;
+use session::{
+    ai_play, apply_takeback, cancel_premove, game_state, new_game, offer_draw, pause_game, play, play_network_move, request_takeback,
+    resign, respond_draw, respond_takeback, resume_engine, resume_game, set_ai_level, set_premove, suspend_engine, undo,
+};
+use shogi::{hands, set_deterministic};
+use simul::{simul_play, simul_state, start_simul, stop_simul, Simul};
+use speech::{speak_move, speak_result};
+use store::Store;
+use strength::{build_strength_reference, estimate_strength};
+use stream::{start_stream_server, stop_stream_server, StreamServer};
+use study::{export_study, import_study};
+use suite::run_test_suite;
+use summary::position_summary;
+use symmetry::validate_evaluation;
+use tournament::{create_tournament, delete_tournament, report_tournament_result, tournament_standings, tournaments};
+use timeman::{power_state, set_power_mode};
+use trace::{query_trace, trace_search};
+use tuning::{compare_eval_params, eval_params, reload_eval_params, tune_eval_params};
+use warning::{check_move, MoveChecker};
 
 // Learn more about Tauri commands at https://tauri.app/v1/guides/features/command
 
@@ -9,7 +122,63 @@ use shogi::shogi_ai;
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
-        .invoke_handler(tauri::generate_handler![shogi_ai])
+        .manage(Analysis::default())
+        .manage(Mutex::new(AnalysisBoard::default()))
+        .manage(Engine::default())
+        .manage(Calibration::default())
+        .manage(StreamServer::default())
+        .manage(Crowd::default())
+        .manage(Simul::default())
+        .manage(Review::default())
+        .manage(Explorer::default())
+        .manage(RepertoireTrainer::default())
+        .manage(MoveChecker::default())
+        .setup(|app| {
+            let store = Store::new(app.path().app_data_dir()?);
+            repair::startup(&store);
+            if let Err(err) = tuning::load(&store) {
+                eprintln!("cannot load the evaluation parameters: {err}");
+            }
+            if let Err(err) = commentary::load(&store) {
+                eprintln!("cannot load the languages: {err}");
+            }
+            assets::load(&store);
+            let session = correspondence::restore(&store).unwrap_or_default();
+            app.manage(Mutex::new(session));
+            app.manage(store);
+            session::watch_time(app.handle().clone());
+            events::watch(app.handle().clone());
+            calibration::start(app.handle().clone());
+            Ok(())
+        })
+        // a minimized window holds the search
+        .on_window_event(|window, event| {
+            if let WindowEvent::Resized(_) = event {
+                match window.is_minimized() {
+                    Ok(true) => suspend_engine(window.state(), window.state()),
+                    Ok(false) => resume_engine(window.state()),
+                    Err(_) => {}
+                }
+            }
+        })
+        .invoke_handler(tauri::generate_handler![
+            shogi_ai, shogi_ai_timed, stop_thinking, hands, position_summary, check_position, diff_positions, board_coordinates,
+            new_game, game_state, check_move, play, play_network_move, undo, ai_play, set_ai_level, set_premove, cancel_premove, pause_game, resume_game, suspend_engine, resume_engine,
+            start_analysis, stop_analysis, evaluate_batch, set_auto_review, game_review, run_test_suite, set_deterministic, overlay, machine_speed, run_benchmark,
+            reload_eval_params, eval_params, explain_eval, tune_eval_params, compare_eval_params, validate_evaluation, cross_check_movegen, build_strength_reference, estimate_strength,
+            calibrate_levels, level_ladder, level_rating, simulate, train_network, export_dataset,
+            rollout_estimate, solve_endgame, solve_mate, position_census, trace_search, query_trace,
+            start_drill, drill_stats, start_stream_server, stop_stream_server, set_power_mode, power_state,
+            start_crowd, stop_crowd, export_replay_animation, export_board_image,
+            speak_move, speak_result, commentary, languages, reload_languages, interpret_move, import_from_clipboard, validate_record,
+            create_tournament, tournaments, delete_tournament, report_tournament_result, tournament_standings,
+            start_simul, simul_play, simul_state, stop_simul, resign, offer_draw, respond_draw,
+            request_takeback, respond_takeback, apply_takeback, set_game_info, update_archived_game, search_games, export_game, explore_position, export_study, import_study,
+            add_bookmark, bookmarks, delete_bookmark, open_bookmark, list_recent_sessions, resume_session, migrate_and_repair,
+            install_asset, installed_assets, remove_asset,
+            add_repertoire_line, delete_repertoire_line, repertoire, start_repertoire_quiz, repertoire_quiz_move,
+            open_analysis_board, analysis_board_state, analysis_board_play, analysis_board_goto, analysis_board_delete, play_from_analysis_board,
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
@@ -2,5 +2,26 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
-    catch_the_lion_lib::run()
+    // "catch-the-lion train ..." trains the models, exports self-play data, runs a test suite, the benchmark, the engine checks or the variant simulations without opening the window
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    // "catch-the-lion --deterministic suite ..." gives the same output on every run
+    if args.first().is_some_and(|arg| arg == "--deterministic") {
+        args.remove(0);
+        catch_the_lion_lib::shogi::set_deterministic(true);
+    }
+    let result = match args.first().map(String::as_str) {
+        Some("train") => catch_the_lion_lib::nn::train_cli(&args[1..]),
+        Some("train-policy") => catch_the_lion_lib::policy::train_cli(&args[1..]),
+        Some("export") => catch_the_lion_lib::dataset::export_cli(&args[1..]),
+        Some("suite") => catch_the_lion_lib::suite::suite_cli(&args[1..]),
+        Some("bench") => catch_the_lion_lib::calibration::bench_cli(&args[1..]),
+        Some("check-eval") => catch_the_lion_lib::symmetry::check_eval_cli(&args[1..]),
+        Some("check-movegen") => catch_the_lion_lib::crosscheck::check_movegen_cli(&args[1..]),
+        Some("simulate") => catch_the_lion_lib::balance::simulate_cli(&args[1..]),
+        _ => return catch_the_lion_lib::run(),
+    };
+    if let Err(err) = result {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,147 @@
+use serde::Serialize;
+use crate::endgame;
+use crate::shogi::{self, Move, Pieces};
+
+const INFINITE: u32 = u32::MAX;
+const DEFAULT_MAX_PLIES: u8 = 15;
+const DEFAULT_MAX_NODES: usize = 1_000_000;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MateStatus {
+    Win, // the player to move wins by force
+    NoWin, // no forced win within the number of plies
+    Unknown, // the node budget ran out first
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MateResult {
+    pub status: MateStatus,
+    pub line: Vec<Move>, // main line of a forced win
+    pub nodes: usize,
+}
+
+struct Node {
+    pieces: Pieces,
+    turn: bool,
+    parent: usize, // the root is its own parent
+    mov: Option<Move>,
+    ply: u8,
+    children: Vec<usize>,
+    proof: u32,
+    disproof: u32,
+}
+
+// Proof-number search for a forced win of the player to move within "max_plies", by capture
+// of the lion or by the lion reaching the last rank. A position repeated along a line counts
+// as a failure of the attacker.
+pub fn solve(pieces: &Pieces, turn: bool, max_plies: u8, max_nodes: usize) -> MateResult {
+    let attacker = turn;
+    let mut nodes = vec![Node { pieces: *pieces, turn, parent: 0, mov: None, ply: 0, children: vec![], proof: 1, disproof: 1 }];
+    initialize(&mut nodes, 0, attacker, max_plies);
+    while nodes[0].proof != 0 && nodes[0].disproof != 0 && nodes.len() < max_nodes {
+        // most proving node
+        let mut current = 0;
+        while !nodes[current].children.is_empty() {
+            let or_node = nodes[current].turn == attacker;
+            current = *nodes[current].children.iter().min_by_key(|&&child| {
+                if or_node { nodes[child].proof } else { nodes[child].disproof }
+            }).unwrap();
+        }
+        let (pieces, turn, ply) = (nodes[current].pieces, nodes[current].turn, nodes[current].ply);
+        for mov in shogi::possible_moves(&pieces, turn) {
+            nodes.push(Node {
+                pieces: shogi::play_move(&pieces, mov),
+                turn: !turn,
+                parent: current,
+                mov: Some(mov),
+                ply: ply + 1,
+                children: vec![],
+                proof: 1,
+                disproof: 1,
+            });
+            let child = nodes.len() - 1;
+            initialize(&mut nodes, child, attacker, max_plies);
+            nodes[current].children.push(child);
+        }
+        // the numbers of the ancestors are updated up to the root
+        loop {
+            let or_node = nodes[current].turn == attacker;
+            let children = nodes[current].children.iter().map(|&child| (nodes[child].proof, nodes[child].disproof));
+            let (proof, disproof) = if or_node {
+                children.fold((INFINITE, 0u32), |(p, d), (cp, cd)| (p.min(cp), d.saturating_add(cd)))
+            } else {
+                children.fold((0u32, INFINITE), |(p, d), (cp, cd)| (p.saturating_add(cp), d.min(cd)))
+            };
+            nodes[current].proof = proof;
+            nodes[current].disproof = disproof;
+            if current == 0 {
+                break;
+            }
+            current = nodes[current].parent;
+        }
+    }
+
+    let status = match (nodes[0].proof, nodes[0].disproof) {
+        (0, _) => MateStatus::Win,
+        (_, 0) => MateStatus::NoWin,
+        _ => MateStatus::Unknown,
+    };
+    let mut line = vec![];
+    if status == MateStatus::Win {
+        // the attacker plays a proven move, the defender the one with the largest proof tree
+        let sizes = subtree_sizes(&nodes);
+        let mut current = 0;
+        while !nodes[current].children.is_empty() {
+            let children = &nodes[current].children;
+            current = if nodes[current].turn == attacker {
+                *children.iter().find(|&&child| nodes[child].proof == 0).unwrap()
+            } else {
+                *children.iter().max_by_key(|&&child| sizes[child]).unwrap()
+            };
+            line.extend(nodes[current].mov);
+        }
+    }
+    MateResult { status, line, nodes: nodes.len() }
+}
+
+// numbers of a new node
+fn initialize(nodes: &mut [Node], i: usize, attacker: bool, max_plies: u8) {
+    let node = &nodes[i];
+    let (proof, disproof) = if endgame::lost(&node.pieces, node.turn) {
+        if node.turn == attacker { (INFINITE, 0) } else { (0, INFINITE) }
+    } else if node.ply >= max_plies || repeated(nodes, i) {
+        (INFINITE, 0)
+    } else {
+        (1, 1)
+    };
+    nodes[i].proof = proof;
+    nodes[i].disproof = disproof;
+}
+
+fn repeated(nodes: &[Node], i: usize) -> bool {
+    let mut current = i;
+    while current != 0 {
+        current = nodes[current].parent;
+        if nodes[current].pieces == nodes[i].pieces && nodes[current].turn == nodes[i].turn {
+            return true;
+        }
+    }
+    false
+}
+
+// the number of nodes under each node and itself, in one pass since children come after their parent
+fn subtree_sizes(nodes: &[Node]) -> Vec<usize> {
+    let mut sizes = vec![1; nodes.len()];
+    for i in (0..nodes.len()).rev() {
+        sizes[i] += nodes[i].children.iter().map(|&child| sizes[child]).sum::<usize>();
+    }
+    sizes
+}
+
+#[tauri::command(async)]
+pub fn solve_mate(pieces: Pieces, turn: bool, max_plies: Option<u8>, max_nodes: Option<usize>) -> Result<MateResult, String> {
+    shogi::validate(&pieces)?;
+    Ok(solve(&pieces, turn, max_plies.unwrap_or(DEFAULT_MAX_PLIES), max_nodes.unwrap_or(DEFAULT_MAX_NODES)))
+}
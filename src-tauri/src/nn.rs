@@ -0,0 +1,172 @@
+use std::fs;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
+use crate::assets;
+use crate::endgame::{self, Outcome};
+use crate::selfplay::{self, Rng, Sample};
+use crate::shogi::{Pieces, HAND};
+use crate::store::Store;
+
+pub const FILE: &str = "network.json";
+
+// one plane of 12 squares per owner and kind, then the number of pieces of each kind
+// in each hand and the side to move
+pub const PLANES: usize = 2 * 5;
+pub const INPUTS: usize = PLANES * 12 + 2 * 5 + 1;
+const HIDDEN: usize = 32;
+
+// the inputs of the network, from the point of view of the first player
+pub fn features(pieces: &Pieces, turn: bool) -> [f32; INPUTS] {
+    let mut input = [0.0; INPUTS];
+    for piece in pieces {
+        let plane = piece.owner as usize * 5 + piece.kind as usize;
+        if piece.position == HAND {
+            input[PLANES * 12 + plane] += 1.0;
+        } else {
+            input[plane * 12 + piece.position as usize] = 1.0;
+        }
+    }
+    input[INPUTS - 1] = if turn { 1.0 } else { 0.0 };
+    input
+}
+
+// Tiny value network: one hidden layer, the output between -1 and 1 estimates the outcome
+// of the game for the first player.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Network {
+    hidden: Vec<Vec<f32>>, // INPUTS weights per hidden unit
+    hidden_bias: Vec<f32>,
+    output: Vec<f32>,
+    output_bias: f32,
+}
+
+impl Network {
+    pub fn new(rng: &mut Rng) -> Self {
+        let mut weight = |fan_in: usize| {
+            let uniform = (rng.next_u64() >> 11) as f32 / (1u64 << 53) as f32;
+            (2.0 * uniform - 1.0) / (fan_in as f32).sqrt()
+        };
+        Network {
+            hidden: (0..HIDDEN).map(|_| (0..INPUTS).map(|_| weight(INPUTS)).collect()).collect(),
+            hidden_bias: vec![0.0; HIDDEN],
+            output: (0..HIDDEN).map(|_| weight(HIDDEN)).collect(),
+            output_bias: 0.0,
+        }
+    }
+
+    fn hidden_layer(&self, input: &[f32; INPUTS]) -> Vec<f32> {
+        self.hidden.iter().zip(&self.hidden_bias).map(|(weights, bias)| {
+            let sum: f32 = weights.iter().zip(input).map(|(w, x)| w * x).sum();
+            (sum + bias).max(0.0)
+        }).collect()
+    }
+
+    pub fn evaluate(&self, pieces: &Pieces, turn: bool) -> f32 {
+        let hidden = self.hidden_layer(&features(pieces, turn));
+        let sum: f32 = self.output.iter().zip(&hidden).map(|(w, h)| w * h).sum();
+        (sum + self.output_bias).tanh()
+    }
+
+    // one step of gradient descent on the squared error, returns the error before the step
+    fn train(&mut self, sample: &Sample, rate: f32) -> f32 {
+        let input = features(&sample.pieces, sample.turn);
+        let hidden = self.hidden_layer(&input);
+        let sum: f32 = self.output.iter().zip(&hidden).map(|(w, h)| w * h).sum();
+        let value = (sum + self.output_bias).tanh();
+        let error = value - sample.outcome;
+        let grad = error * (1.0 - value * value);
+        for (j, &h) in hidden.iter().enumerate() {
+            let hidden_grad = if h > 0.0 { grad * self.output[j] } else { 0.0 };
+            self.output[j] -= rate * grad * h;
+            if hidden_grad != 0.0 {
+                for (w, x) in self.hidden[j].iter_mut().zip(&input) {
+                    *w -= rate * hidden_grad * x;
+                }
+                self.hidden_bias[j] -= rate * hidden_grad;
+            }
+        }
+        self.output_bias -= rate * grad;
+        error * error
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrainingOptions {
+    pub games: usize,
+    pub depth: u8,
+    pub epochs: usize,
+    pub learning_rate: f32,
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrainingProgress {
+    pub stage: &'static str, // "selfplay" or "training"
+    pub step: usize,
+    pub steps: usize,
+    pub loss: Option<f32>, // mean squared error of the last epoch
+}
+
+// Self-play with exploration, then stochastic gradient descent towards the outcomes: the exact one
+// for the endgames the solver settles, the result of the game for the other positions.
+pub fn train(options: TrainingOptions, network: Option<Network>, seed: u64, on_progress: &mut dyn FnMut(TrainingProgress)) -> Network {
+    let mut rng = Rng::new(seed);
+    let mut samples = vec![];
+    for game in 0..options.games {
+        samples.extend(selfplay::generate(1, options.depth, 8, &mut rng));
+        on_progress(TrainingProgress { stage: "selfplay", step: game + 1, steps: options.games, loss: None });
+    }
+    for sample in samples.iter_mut().filter(|sample| endgame::is_sparse(&sample.pieces)) {
+        if let Some(solution) = endgame::solve(&sample.pieces, sample.turn) {
+            let first_player = if sample.turn { -1.0 } else { 1.0 };
+            sample.outcome = match solution.outcome {
+                Outcome::Win => first_player,
+                Outcome::Loss => -first_player,
+                Outcome::Draw => 0.0,
+            };
+        }
+    }
+    let mut network = network.unwrap_or_else(|| Network::new(&mut rng));
+    for epoch in 0..options.epochs {
+        // shuffled at each epoch
+        for i in (1..samples.len()).rev() {
+            samples.swap(i, rng.below(i + 1));
+        }
+        let loss: f32 = samples.iter().map(|sample| network.train(sample, options.learning_rate)).sum();
+        let loss = loss / samples.len().max(1) as f32;
+        on_progress(TrainingProgress { stage: "training", step: epoch + 1, steps: options.epochs, loss: Some(loss) });
+    }
+    network
+}
+
+// the weights file is replaced, training resumes from it the next time, or from the downloaded
+// weights the first time
+#[tauri::command(async)]
+pub fn train_network(app: AppHandle, store: State<Store>, options: TrainingOptions) -> Result<(), String> {
+    let previous = store.load(FILE).or_else(|| assets::network(&store));
+    let network = train(options, previous, selfplay::clock_seed(), &mut |progress| {
+        let _ = app.emit("training", progress);
+    });
+    store.save(FILE, &network)
+}
+
+// "train <games> <epochs> <weights file>" from the command line, the other options have default values
+pub fn train_cli(args: &[String]) -> Result<(), String> {
+    let [games, epochs, path] = args else {
+        return Err("usage: train <games> <epochs> <weights file>".into());
+    };
+    let options = TrainingOptions {
+        games: games.parse().map_err(|_| "invalid number of games")?,
+        depth: 4,
+        epochs: epochs.parse().map_err(|_| "invalid number of epochs")?,
+        learning_rate: 0.01,
+    };
+    let previous = fs::read_to_string(path).ok().and_then(|text| serde_json::from_str(&text).ok());
+    let network = train(options, previous, selfplay::clock_seed(), &mut |progress| {
+        let loss = progress.loss.map_or(String::new(), |loss| format!(" loss {loss:.4}"));
+        eprintln!("{} {}/{}{loss}", progress.stage, progress.step, progress.steps);
+    });
+    let text = serde_json::to_string(&network).map_err(|err| err.to_string())?;
+    fs::write(path, text).map_err(|err| err.to_string())
+}
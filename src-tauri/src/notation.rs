@@ -0,0 +1,221 @@
+use serde::Serialize;
+use crate::shogi::{self, Kind, Move, Piece, Pieces, Rules, Standard, HAND};
+
+// Squares are named from the point of view of the first player: files a to c from left to right
+// and ranks 1 to 4 from bottom to top, so the first player's lion starts on b1.
+// Names never depend on the orientation of the board, only the display does.
+
+const WIDTH: u8 = Standard::WIDTH as u8;
+const HEIGHT: u8 = Standard::HEIGHT as u8;
+
+pub fn square_name(square: u8) -> String {
+    let file = (b'a' + square % WIDTH) as char;
+    let rank = HEIGHT - square / WIDTH;
+    format!("{file}{rank}")
+}
+
+pub fn parse_square(name: &str) -> Option<u8> {
+    match *name.as_bytes() {
+        [file, rank] if (b'a'..b'a' + WIDTH).contains(&file) && (b'1'..b'1' + HEIGHT).contains(&rank) => {
+            Some(WIDTH * (b'0' + HEIGHT - rank) + file - b'a')
+        }
+        _ => None,
+    }
+}
+
+// index of the display cell showing "square", the board is turned around when the second player is at the bottom.
+// The mapping is its own inverse.
+pub fn oriented(square: u8, flipped: bool) -> u8 {
+    if flipped { Standard::SQUARES as u8 - 1 - square } else { square }
+}
+
+pub fn kind_letter(kind: Kind) -> char {
+    match kind {
+        Kind::Chick => 'C',
+        Kind::Elephant => 'E',
+        Kind::Giraffe => 'G',
+        Kind::Lion => 'L',
+        Kind::Hen => 'H',
+    }
+}
+
+pub fn parse_kind(letter: char) -> Option<Kind> {
+    match letter.to_ascii_uppercase() {
+        'C' => Some(Kind::Chick),
+        'E' => Some(Kind::Elephant),
+        'G' => Some(Kind::Giraffe),
+        'L' => Some(Kind::Lion),
+        'H' => Some(Kind::Hen),
+        _ => None,
+    }
+}
+
+// "Gc1-c2", "Cb3xb2+" (capture and promotion), "Cb3-b4=" (promotion declined) or "E*a3" (drop)
+pub fn move_name(pieces: &Pieces, mov: Move) -> String {
+    let piece = pieces[mov.from];
+    let letter = kind_letter(piece.kind);
+    let to = square_name(mov.to as u8);
+    if piece.position == HAND {
+        return format!("{letter}*{to}");
+    }
+    let capture = if pieces.iter().any(|p| p.position == mov.to as u8) { 'x' } else { '-' };
+    let last_rank = Standard::last_rank(mov.to, piece.owner);
+    let promotion = match (piece.kind, mov.promote) {
+        (Kind::Chick, true) => "+",
+        (Kind::Chick, false) if last_rank => "=",
+        _ => "",
+    };
+    format!("{letter}{}{capture}{to}{promotion}", square_name(piece.position))
+}
+
+fn normalize(name: &str) -> String {
+    name.chars().filter(|c| !matches!(c, 'x' | 'X' | '-' | '+' | '=')).collect::<String>().to_ascii_uppercase()
+}
+
+// the legal move written as "name", capture and promotion marks are optional,
+// a chick reaching the last rank promotes unless the name ends with "="
+pub fn parse_move(pieces: &Pieces, turn: bool, rules: &Rules, name: &str) -> Option<Move> {
+    let declined = name.trim().ends_with('=');
+    let name = normalize(name.trim());
+    shogi::legal_moves(pieces, turn, rules).into_iter()
+        .find(|&mov| normalize(&move_name(pieces, mov)) == name && !(declined && mov.promote))
+}
+
+// names of the moves played from "pieces"
+pub fn move_list(pieces: &Pieces, moves: &[Move]) -> Vec<String> {
+    let mut pieces = *pieces;
+    moves.iter().map(|&mov| {
+        let name = move_name(&pieces, mov);
+        pieces = shogi::play_move(&pieces, mov);
+        name
+    }).collect()
+}
+
+// Positions are written in the style of SFEN: the ranks from 4 down to 1 separated by "/", uppercase
+// letters for the first player and digits for empty squares, then the player to move, "b" for the
+// first player and "w" for the second, and the pieces in hand, "-" if none. The initial position
+// is "gle/1c1/1C1/ELG b -".
+pub fn position_string(pieces: &Pieces, turn: bool) -> String {
+    let letter = |kind, owner: bool| {
+        let letter = kind_letter(kind);
+        if owner { letter.to_ascii_lowercase() } else { letter }
+    };
+    let mut ranks = vec![];
+    for row in 0..HEIGHT {
+        let mut rank = String::new();
+        let mut empty = 0;
+        for square in WIDTH * row..WIDTH * row + WIDTH {
+            match pieces.iter().find(|p| p.position == square) {
+                Some(piece) => {
+                    if empty > 0 {
+                        rank.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+                    rank.push(letter(piece.kind, piece.owner));
+                }
+                None => empty += 1,
+            }
+        }
+        if empty > 0 {
+            rank.push_str(&empty.to_string());
+        }
+        ranks.push(rank);
+    }
+    let hands: String = pieces.iter().filter(|p| p.position == HAND).map(|p| letter(p.kind, p.owner)).collect();
+    format!("{} {} {}", ranks.join("/"), if turn { 'w' } else { 'b' }, if hands.is_empty() { "-" } else { &hands })
+}
+
+// Puts the pieces described as (kind, position, owner) at the indices they have in the initial
+// position, twins interchangeably.
+fn arrange(described: &[(Kind, u8, bool)]) -> Option<Pieces> {
+    let mut pieces = shogi::initial_pieces();
+    let mut placed = [false; 8];
+    for &(kind, position, owner) in described {
+        let base = if kind == Kind::Hen { Kind::Chick } else { kind };
+        let twins = (0..4).find(|&i| pieces[i].kind == base)?;
+        let (own, other) = if owner { (twins + 4, twins) } else { (twins, twins + 4) };
+        let i = if placed[own] { other } else { own };
+        if placed[i] {
+            return None;
+        }
+        placed[i] = true;
+        pieces[i] = Piece { kind, position, owner };
+    }
+    (placed.iter().all(|&p| p) && shogi::is_valid(&pieces)).then_some(pieces)
+}
+
+pub fn parse_position(text: &str) -> Option<(Pieces, bool)> {
+    let mut fields = text.split_whitespace();
+    let (board, turn, hands) = (fields.next()?, fields.next()?, fields.next()?);
+    if fields.next().is_some() {
+        return None;
+    }
+    let piece = |c: char| Some((parse_kind(c)?, c.is_ascii_lowercase()));
+    let mut described = vec![];
+    let ranks: Vec<&str> = board.split('/').collect();
+    if ranks.len() != Standard::HEIGHT {
+        return None;
+    }
+    for (row, rank) in ranks.iter().enumerate() {
+        let mut square = WIDTH * row as u8;
+        for c in rank.chars() {
+            if let Some(empty) = c.to_digit(10) {
+                square += empty as u8;
+            } else {
+                let (kind, owner) = piece(c)?;
+                described.push((kind, square, owner));
+                square += 1;
+            }
+        }
+        if square != WIDTH * row as u8 + WIDTH {
+            return None;
+        }
+    }
+    if hands != "-" {
+        for c in hands.chars() {
+            let (kind, owner) = piece(c)?;
+            described.push((kind, HAND, owner));
+        }
+    }
+    let turn = match turn {
+        "b" => false,
+        "w" => true,
+        _ => return None,
+    };
+    Some((arrange(&described)?, turn))
+}
+
+// Short codes for sharing a position, the encoding of the position in base 32
+const SHARE_ALPHABET: &[u8; 32] = b"0123456789abcdefghjkmnpqrstvwxyz";
+const SHARE_CODE_LEN: usize = 9;
+
+pub fn share_code(pieces: &Pieces, turn: bool) -> String {
+    let encoding = shogi::encode_pieces(pieces, turn);
+    (0..SHARE_CODE_LEN).rev().map(|i| SHARE_ALPHABET[(encoding >> (5 * i) & 31) as usize] as char).collect()
+}
+
+pub fn parse_share_code(code: &str) -> Option<(Pieces, bool)> {
+    if code.len() != SHARE_CODE_LEN {
+        return None;
+    }
+    let mut encoding = 0;
+    for c in code.to_ascii_lowercase().bytes() {
+        encoding = encoding << 5 | SHARE_ALPHABET.iter().position(|&a| a == c)? as u64;
+    }
+    shogi::decode_pieces(encoding)
+}
+
+#[derive(Serialize)]
+pub struct Coordinate {
+    square: u8,
+    name: String,
+}
+
+// the squares in display order, top left first
+#[tauri::command]
+pub fn board_coordinates(flipped: bool) -> Vec<Coordinate> {
+    (0..Standard::SQUARES as u8).map(|cell| {
+        let square = oriented(cell, flipped);
+        Coordinate { square, name: square_name(square) }
+    }).collect()
+}
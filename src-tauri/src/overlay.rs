@@ -0,0 +1,103 @@
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use crate::session::Session;
+use crate::shogi::{self, Kind, Limits, Move, Pieces, Search, HAND, PIECE_VALUE};
+
+const HINT_DEPTH: u8 = 6;
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OverlaySource { Hint, Pv, Threats }
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HighlightKind { Hint, Pv, Threatened }
+
+#[derive(Clone, Copy, Serialize)]
+pub struct Arrow {
+    from: Option<u8>, // None for a drop
+    to: u8,
+    kind: Kind,
+    owner: bool,
+    strength: f32, // between 0 and 1
+}
+
+#[derive(Clone, Copy, Serialize)]
+pub struct Highlight {
+    square: u8,
+    kind: HighlightKind,
+}
+
+#[derive(Default, Serialize)]
+pub struct Overlay {
+    arrows: Vec<Arrow>,
+    squares: Vec<Highlight>,
+}
+
+// arrows fade along the line, the destination of its first move is highlighted
+fn line_overlay(pieces: &Pieces, line: &[Move], kind: HighlightKind) -> Overlay {
+    let mut overlay = Overlay::default();
+    let mut pieces = *pieces;
+    for (i, &mov) in line.iter().enumerate() {
+        let piece = pieces[mov.from];
+        overlay.arrows.push(Arrow {
+            from: if piece.position == HAND { None } else { Some(piece.position) },
+            to: mov.to as u8,
+            kind: piece.kind,
+            owner: piece.owner,
+            strength: 1.0 / (i + 1) as f32,
+        });
+        pieces = shogi::play_move(&pieces, mov);
+    }
+    if let Some(mov) = line.first() {
+        overlay.squares.push(Highlight { square: mov.to as u8, kind });
+    }
+    overlay
+}
+
+// captures the opponent of "turn" threatens to make
+fn threats(pieces: &Pieces, turn: bool) -> Overlay {
+    let mut overlay = Overlay::default();
+    for &attacker in pieces.iter().filter(|p| p.owner != turn) {
+        for square in shogi::attacks(attacker) {
+            let Some(target) = pieces.iter().find(|p| p.position == square && p.owner == turn) else { continue };
+            let value = if target.kind == Kind::Lion {
+                1.0
+            } else {
+                PIECE_VALUE[target.kind as usize] as f32 / PIECE_VALUE[Kind::Hen as usize] as f32
+            };
+            let defended = shogi::is_attacked(pieces, square, turn);
+            overlay.arrows.push(Arrow {
+                from: Some(attacker.position),
+                to: square,
+                kind: attacker.kind,
+                owner: attacker.owner,
+                strength: if defended && target.kind != Kind::Lion { value / 2.0 } else { value },
+            });
+            if !overlay.squares.iter().any(|h| h.square == square) {
+                overlay.squares.push(Highlight { square, kind: HighlightKind::Threatened });
+            }
+        }
+    }
+    overlay
+}
+
+#[tauri::command(async)]
+pub fn overlay(session: State<Mutex<Session>>, source: OverlaySource, depth: Option<u8>) -> Overlay {
+    let (pieces, played, turn, line) = {
+        let session = session.lock().unwrap();
+        if session.result().is_some() {
+            return Overlay::default();
+        }
+        (*session.pieces(), session.played(), session.turn(), session.expected_line().to_vec())
+    };
+    match source {
+        OverlaySource::Hint => {
+            let hint = shogi::best_move(&mut Search::default(), &pieces, &played, Limits::depth(depth.unwrap_or(HINT_DEPTH)), turn, &mut |_| ());
+            line_overlay(&pieces, &hint.map(|hint| hint.mov).into_iter().collect::<Vec<_>>(), HighlightKind::Hint)
+        }
+        OverlaySource::Pv => line_overlay(&pieces, &line, HighlightKind::Pv),
+        OverlaySource::Threats => threats(&pieces, turn),
+    }
+}
@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+use crate::selfplay::{self, Rng, Sample};
+use crate::shogi::{self, Move, MoveList, Pieces, Standard, HAND, MAX_MOVES};
+
+// Linear move-ordering model: a weight for each kind of piece and destination, seen from the
+// player to move, one for each kind of captured piece and one for a drop of each kind.
+// It is trained on the moves chosen in self-play games by `train` and embedded in WEIGHTS.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Policy {
+    pub destination: [[f32; Standard::SQUARES]; 5],
+    pub capture: [f32; 6], // by kind of captured piece, the last one when nothing is captured
+    pub drop: [f32; 5],
+}
+
+pub const WEIGHTS: Policy = Policy {
+    destination: [
+        [-1.589, -0.757, -1.356, 0.381, -0.167, 0.356, 0.544, 1.558, 0.179, -0.647, -0.178, -0.113],
+        [-2.156, 0.513, -1.699, 0.813, 0.413, 0.428, 1.848, 1.668, 0.919, -0.174, 0.850, 0.039],
+        [-0.554, -0.760, -0.108, -1.717, -0.834, -1.239, 0.844, 0.792, 0.802, -0.414, 0.733, 0.650],
+        [2.966, -1.164, -0.158, -1.999, -2.065, -1.864, 0.124, 0.357, 0.024, 1.316, 1.335, 0.777],
+        [-0.145, 0.050, 0.325, -0.089, -0.216, 0.559, 0.000, 0.000, 0.000, 0.000, 0.000, 0.000],
+    ],
+    capture: [-1.396, -0.571, -0.876, 5.572, 0.189, -2.917],
+    drop: [0.054, -0.005, -0.632, 0.000, 0.000],
+};
+
+// indices of the weights involved in a move, in the order destination, capture, drop
+fn features(pieces: &Pieces, mov: Move) -> (usize, usize, usize, Option<usize>) {
+    let piece = pieces[mov.from];
+    let to = if piece.owner { Standard::SQUARES - 1 - mov.to } else { mov.to };
+    let captured = pieces.iter().find(|p| p.position == mov.to as u8).map_or(5, |p| p.kind as usize);
+    let drop = (piece.position == HAND).then_some(piece.kind as usize);
+    (piece.kind as usize, to, captured, drop)
+}
+
+impl Policy {
+    pub fn score(&self, pieces: &Pieces, mov: Move) -> f32 {
+        let (kind, to, captured, drop) = features(pieces, mov);
+        self.destination[kind][to] + self.capture[captured] + drop.map_or(0.0, |kind| self.drop[kind])
+    }
+
+    // Most promising moves first. The moves of a same score keep the order of the generation, the
+    // captures first and the most valuable victims first. Each move is scored once, and the
+    // insertion sort is stable and works on the stack, for a list this short at every node.
+    pub fn order(&self, pieces: &Pieces, moves: &mut MoveList) {
+        let mut scores = [0.0; MAX_MOVES];
+        for (score, &mov) in scores.iter_mut().zip(moves.iter()) {
+            *score = self.score(pieces, mov);
+        }
+        for i in 1..moves.len() {
+            let (score, mov) = (scores[i], moves[i]);
+            let mut j = i;
+            while j > 0 && scores[j - 1] < score {
+                scores[j] = scores[j - 1];
+                moves[j] = moves[j - 1];
+                j -= 1;
+            }
+            scores[j] = score;
+            moves[j] = mov;
+        }
+    }
+
+    // one step of gradient ascent on the log-likelihood of the move played, with a softmax over the legal moves
+    fn train(&mut self, sample: &Sample, moves: &[Move], rate: f32) -> f32 {
+        let scores: Vec<f32> = moves.iter().map(|&mov| self.score(&sample.pieces, mov)).collect();
+        let max = scores.iter().copied().fold(f32::MIN, f32::max);
+        let exps: Vec<f32> = scores.iter().map(|s| (s - max).exp()).collect();
+        let total: f32 = exps.iter().sum();
+        let mut loss = 0.0;
+        for (&mov, exp) in moves.iter().zip(exps) {
+            let probability = exp / total;
+            let target = if mov == sample.mov { 1.0 } else { 0.0 };
+            if target == 1.0 {
+                loss = -probability.max(1e-9).ln();
+            }
+            let step = rate * (target - probability);
+            let (kind, to, captured, drop) = features(&sample.pieces, mov);
+            self.destination[kind][to] += step;
+            self.capture[captured] += step;
+            if let Some(kind) = drop {
+                self.drop[kind] += step;
+            }
+        }
+        loss
+    }
+
+    // the source of WEIGHTS for the trained model
+    pub fn to_rust(&self) -> String {
+        let list = |values: &[f32]| values.iter().map(|v| format!("{v:.3}")).collect::<Vec<_>>().join(", ");
+        let destination: Vec<String> = self.destination.iter().map(|row| format!("        [{}],\n", list(row))).collect();
+        format!(
+            "pub const WEIGHTS: Policy = Policy {{\n    destination: [\n{}    ],\n    capture: [{}],\n    drop: [{}],\n}};\n",
+            destination.concat(), list(&self.capture), list(&self.drop),
+        )
+    }
+}
+
+// trains a model from scratch on the moves of "samples", returns it with the loss of the last epoch
+pub fn train(samples: &mut [Sample], epochs: usize, rate: f32, rng: &mut Rng) -> (Policy, f32) {
+    let mut policy = Policy { destination: [[0.0; Standard::SQUARES]; 5], capture: [0.0; 6], drop: [0.0; 5] };
+    let mut loss = 0.0;
+    for _ in 0..epochs {
+        for i in (1..samples.len()).rev() {
+            samples.swap(i, rng.below(i + 1));
+        }
+        loss = samples.iter().map(|sample| {
+            let moves = shogi::possible_moves(&sample.pieces, sample.turn);
+            policy.train(sample, &moves, rate)
+        }).sum::<f32>() / samples.len().max(1) as f32;
+    }
+    (policy, loss)
+}
+
+// "train-policy <games> <epochs>" from the command line, prints the new WEIGHTS
+pub fn train_cli(args: &[String]) -> Result<(), String> {
+    let [games, epochs] = args else {
+        return Err("usage: train-policy <games> <epochs>".into());
+    };
+    let games = games.parse().map_err(|_| "invalid number of games")?;
+    let epochs = epochs.parse().map_err(|_| "invalid number of epochs")?;
+    let mut rng = Rng::from_clock();
+    let mut samples = selfplay::generate(games, 6, 8, &mut rng);
+    let (policy, loss) = train(&mut samples, epochs, 0.05, &mut rng);
+    eprintln!("{} positions, loss {loss:.4}", samples.len());
+    print!("{}", policy.to_rust());
+    Ok(())
+}
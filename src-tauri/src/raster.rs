@@ -0,0 +1,172 @@
+use crate::notation;
+use crate::shogi::{Pieces, Standard, HAND};
+
+// Pixel rendering of a position for the image exports. Images use a fixed palette, which
+// both GIF and indexed PNG store as is.
+pub const PALETTE: [[u8; 3]; 6] = [
+    [0xf4, 0xe2, 0xb8], // board
+    [0x6b, 0x4f, 0x2a], // grid and coordinates
+    [0x20, 0x20, 0x80], // first player
+    [0xa0, 0x20, 0x20], // second player
+    [0xe8, 0xc8, 0x78], // last move
+    [0xe0, 0xc8, 0x98], // hands
+];
+const BOARD: u8 = 0;
+const GRID: u8 = 1;
+const PLAYER: [u8; 2] = [2, 3];
+const HIGHLIGHT: u8 = 4;
+const HANDS: u8 = 5;
+
+// 5x7 glyphs of the piece letters and coordinates, one row per byte, the leftmost pixel in the highest bit
+fn glyph(c: char) -> [u8; 7] {
+    match c {
+        'C' => [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110],
+        'E' => [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111],
+        'G' => [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111],
+        'H' => [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
+        'L' => [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111],
+        'a' => [0b00000, 0b00000, 0b01110, 0b00001, 0b01111, 0b10001, 0b01111],
+        'b' => [0b10000, 0b10000, 0b10110, 0b11001, 0b10001, 0b10001, 0b11110],
+        'c' => [0b00000, 0b00000, 0b01110, 0b10000, 0b10000, 0b10001, 0b01110],
+        '1' => [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+        '2' => [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111],
+        '3' => [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110],
+        '4' => [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010],
+        _ => [0; 7],
+    }
+}
+
+// what is drawn besides the pieces, shared with the SVG rendering
+#[derive(Clone, Debug, Default)]
+pub struct RenderOptions {
+    pub cell: usize, // size of a square in pixels
+    pub flipped: bool, // the second player at the bottom
+    pub coordinates: bool,
+    pub highlight: Vec<u8>, // squares of the last move
+    pub eval_bar: Option<i32>, // a bar on the left showing the share of the first player from a score of the search
+}
+
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>, // indices in PALETTE, row by row
+}
+
+impl Image {
+    fn new(width: usize, height: usize, color: u8) -> Self {
+        Image { width, height, pixels: vec![color; width * height] }
+    }
+
+    fn fill(&mut self, x: usize, y: usize, width: usize, height: usize, color: u8) {
+        for row in y..(y + height).min(self.height) {
+            let start = row * self.width;
+            self.pixels[start + x.min(self.width)..start + (x + width).min(self.width)].fill(color);
+        }
+    }
+
+    fn outline(&mut self, x: usize, y: usize, width: usize, height: usize, color: u8) {
+        self.fill(x, y, width, 1, color);
+        self.fill(x, y + height - 1, width, 1, color);
+        self.fill(x, y, 1, height, color);
+        self.fill(x + width - 1, y, 1, height, color);
+    }
+
+    // a character with its top left corner at (x, y), upside down if "turned"
+    fn text(&mut self, c: char, x: usize, y: usize, scale: usize, turned: bool, color: u8) {
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for column in 0..5 {
+                if bits >> (4 - column) & 1 == 1 {
+                    let (column, row) = if turned { (4 - column, 6 - row) } else { (column, row) };
+                    self.fill(x + column * scale, y + row * scale, scale, scale, color);
+                }
+            }
+        }
+    }
+
+    // a piece centered on (x, y)
+    fn piece(&mut self, letter: char, owner: bool, turned: bool, x: usize, y: usize, size: usize) {
+        let scale = (size / 9).max(1);
+        self.fill(x - size / 2, y - size / 2, size, size, PLAYER[owner as usize]);
+        self.text(letter, x - 5 * scale / 2, y - 7 * scale / 2, scale, turned, BOARD);
+    }
+}
+
+pub fn palette_bytes() -> Vec<u8> {
+    PALETTE.concat()
+}
+
+// The board with the hand of the player at the top above it and the other one below, like
+// the board of the stream overlay.
+pub fn render(pieces: &Pieces, options: &RenderOptions) -> Image {
+    let cell = options.cell;
+    let bar = if options.eval_bar.is_some() { cell / 4 } else { 0 };
+    let (width, height) = (Standard::WIDTH, Standard::HEIGHT);
+    let mut image = Image::new(bar + width * cell, (height + 2) * cell, BOARD);
+    image.fill(bar, 0, width * cell, cell, HANDS);
+    image.fill(bar, (height + 1) * cell, width * cell, cell, HANDS);
+    let origin = |square: u8| {
+        let shown = notation::oriented(square, options.flipped) as usize;
+        (bar + shown % width * cell, (shown / width + 1) * cell)
+    };
+    for square in 0..Standard::SQUARES as u8 {
+        let (x, y) = origin(square);
+        if options.highlight.contains(&square) {
+            image.fill(x, y, cell, cell, HIGHLIGHT);
+        }
+        image.outline(x, y, cell, cell, GRID);
+    }
+    if options.coordinates {
+        // files along the bottom edge, ranks along the left edge
+        let scale = (cell / 40).max(1);
+        let margin = cell / 16 + 1;
+        for square in 0..Standard::SQUARES as u8 {
+            let (x, y) = origin(square);
+            let name: Vec<char> = notation::square_name(square).chars().collect();
+            let shown = notation::oriented(square, options.flipped) as usize;
+            let (row, column) = (shown / width, shown % width);
+            if row == height - 1 {
+                image.text(name[0], x + cell - 5 * scale - margin, y + cell - 7 * scale - margin, scale, false, GRID);
+            }
+            if column == 0 {
+                image.text(name[1], x + margin, y + margin, scale, false, GRID);
+            }
+        }
+    }
+    let mut in_hand = [0; 2];
+    for piece in pieces {
+        let letter = notation::kind_letter(piece.kind);
+        let turned = piece.owner != options.flipped;
+        if piece.position == HAND {
+            let slot = &mut in_hand[piece.owner as usize];
+            let x = bar + *slot * cell / 2 + cell / 4;
+            *slot += 1;
+            let y = if turned { cell / 2 } else { (height + 1) * cell + cell / 2 };
+            image.piece(letter, piece.owner, turned, x, y, cell * 2 / 5);
+        } else {
+            let (x, y) = origin(piece.position);
+            image.piece(letter, piece.owner, turned, x + cell / 2, y + cell / 2, cell * 7 / 10);
+        }
+    }
+    if let Some(score) = options.eval_bar {
+        let share = 1.0 / (1.0 + (-score as f64 / 300.0).exp());
+        let (top, bottom) = if options.flipped { (PLAYER[0], PLAYER[1]) } else { (PLAYER[1], PLAYER[0]) };
+        let bottom_share = if options.flipped { 1.0 - share } else { share };
+        let bottom_height = (bottom_share * image.height as f64).round() as usize;
+        image.fill(0, 0, bar, image.height - bottom_height, top);
+        image.fill(0, image.height - bottom_height, bar, bottom_height, bottom);
+    }
+    image
+}
+
+// an indexed PNG of the image
+pub fn png_bytes(image: &Image) -> Result<Vec<u8>, String> {
+    let mut bytes = vec![];
+    let mut encoder = png::Encoder::new(&mut bytes, image.width as u32, image.height as u32);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_palette(palette_bytes());
+    let mut writer = encoder.write_header().map_err(|err| err.to_string())?;
+    writer.write_image_data(&image.pixels).map_err(|err| err.to_string())?;
+    writer.finish().map_err(|err| err.to_string())?;
+    Ok(bytes)
+}
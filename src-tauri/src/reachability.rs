@@ -0,0 +1,68 @@
+use serde::Serialize;
+use crate::notation;
+use crate::shogi::{self, Kind, Piece, Pieces, Standard, HAND};
+
+// Reachability of a position from the initial setup, for the position editor and the imports.
+// Every issue found makes the position unreachable, but a position without issues is not proven
+// reachable: captures and drops let almost any placement of the pieces happen. Without the moves
+// nothing tells who promoted a hen, a hen changes sides as a chick, so hens are only counted.
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Issue {
+    // two pieces on the same square, a lion or a hen in hand
+    Malformed,
+    // not two pieces of each kind, hens counted as chicks, or a player without a lion: pieces
+    // change sides by captures but the capture of a lion ends the game
+    PieceCount,
+    // the lion of the player who has just moved stands safely on the last rank, the game is over
+    GameOver { winner: bool },
+    // no move of the player who has just moved can have led to the position
+    NoLastMove,
+}
+
+// could "piece" have just arrived on its square
+fn has_arrived(pieces: &Pieces, piece: Piece) -> bool {
+    if piece.position == HAND {
+        return false;
+    }
+    let empty = |square: u8| pieces.iter().all(|p| p.position != square);
+    let came_from = |kind, square| shogi::attacks(Piece { kind, position: square, ..piece }).any(|s| s == piece.position);
+    match piece.kind {
+        // dropped
+        Kind::Chick | Kind::Elephant | Kind::Giraffe => true,
+        Kind::Lion => (0..Standard::SQUARES as u8).any(|square| empty(square) && came_from(Kind::Lion, square)),
+        Kind::Hen => (0..Standard::SQUARES as u8).any(|square| {
+            empty(square) && (came_from(Kind::Hen, square) || Standard::last_rank(piece.position as usize, piece.owner) && came_from(Kind::Chick, square))
+        }),
+    }
+}
+
+pub fn issues(pieces: &Pieces, turn: bool) -> Vec<Issue> {
+    if !shogi::is_valid(pieces) {
+        return vec![Issue::Malformed];
+    }
+    let mut issues = vec![];
+    let count = |kinds: &[Kind]| pieces.iter().filter(|piece| kinds.contains(&piece.kind)).count();
+    let lions = |owner| pieces.iter().any(|piece| piece.kind == Kind::Lion && piece.owner == owner);
+    let counts = [count(&[Kind::Chick, Kind::Hen]), count(&[Kind::Elephant]), count(&[Kind::Giraffe]), count(&[Kind::Lion])];
+    if counts != [2; 4] || !lions(false) || !lions(true) {
+        issues.push(Issue::PieceCount);
+        return issues;
+    }
+    if shogi::lion_reached_camp(pieces, !turn) {
+        issues.push(Issue::GameOver { winner: !turn });
+    }
+    // twins may be swapped in an edited position
+    let initial = notation::position_string(pieces, turn) == notation::position_string(&shogi::initial_pieces(), false);
+    if !initial && !pieces.iter().any(|&piece| piece.owner != turn && has_arrived(pieces, piece)) {
+        issues.push(Issue::NoLastMove);
+    }
+    issues
+}
+
+#[tauri::command]
+pub fn check_position(pieces: Pieces, turn: bool) -> Result<Vec<Issue>, String> {
+    shogi::validate(&pieces)?;
+    Ok(issues(&pieces, turn))
+}
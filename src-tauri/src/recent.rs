@@ -0,0 +1,116 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use crate::analysis::Analysis;
+use crate::archive::GameInfo;
+use crate::clock::TimeControl;
+use crate::correspondence::{self, Correspondence};
+use crate::engine::{Engine, Message};
+use crate::notation;
+use crate::session::{AiLevel, GameStateView, Session};
+use crate::shogi::{Move, Rules};
+use crate::store::Store;
+
+// The games in progress, saved after every change so that one can be taken up again later, with
+// its clocks and the level of the engine. A finished game leaves the list for the archive.
+
+pub const FILE: &str = "recent.json";
+const MAX_RECENT: usize = 10;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentSession {
+    pub id: u64,
+    pub updated_ms: u64, // UTC timestamp
+    pub start: String, // position string of the position the game started from
+    pub moves: Vec<Move>,
+    pub position: String, // the current one, for the preview
+    pub thinking_ms: Vec<u64>,
+    pub rules: Rules,
+    pub clock: Option<[TimeControl; 2]>, // what is left to each player
+    pub correspondence: Option<Correspondence>,
+    pub info: GameInfo,
+    pub level: Option<AiLevel>,
+}
+
+fn load(store: &Store) -> Vec<RecentSession> {
+    store.load(FILE).unwrap_or_default()
+}
+
+// the games without any move are not worth taking up again
+pub fn save(store: &Store, session: &Session) {
+    let mut recent = load(store);
+    let before = recent.len();
+    recent.retain(|saved| saved.id != session.id());
+    if session.result().is_none() && !session.history().is_empty() {
+        let (start, turn) = session.start();
+        recent.insert(0, RecentSession {
+            id: session.id(),
+            updated_ms: correspondence::now_ms(),
+            start: notation::position_string(&start, turn),
+            moves: session.moves(),
+            position: notation::position_string(session.pieces(), session.turn()),
+            thinking_ms: session.thinking_times().iter().map(|time| time.as_millis() as u64).collect(),
+            rules: session.rules(),
+            clock: session.clock().map(|clock| clock.controls(Instant::now())),
+            correspondence: session.correspondence(),
+            info: session.info().clone(),
+            level: session.ai_level(),
+        });
+        recent.truncate(MAX_RECENT);
+    } else if recent.len() == before {
+        return;
+    }
+    if let Err(err) = store.save(FILE, &recent) {
+        eprintln!("cannot save the recent games: {err}");
+    }
+}
+
+fn restore(saved: RecentSession) -> Option<Session> {
+    let (pieces, turn) = notation::parse_position(&saved.start)?;
+    let mut session = Session::from_position(pieces, turn);
+    session.set_id(saved.id);
+    session.set_rules(saved.rules);
+    for mov in saved.moves {
+        session.play(mov).ok()?;
+    }
+    session.set_thinking_times(saved.thinking_ms.into_iter().map(Duration::from_millis).collect());
+    if let Some(controls) = saved.clock {
+        session.restore_clock(controls);
+    }
+    if let Some(correspondence) = saved.correspondence {
+        session.set_correspondence(correspondence);
+    }
+    session.set_info(saved.info);
+    if let Some(level) = saved.level {
+        session.set_ai_level(level);
+    }
+    Some(session)
+}
+
+// the most recent first
+#[tauri::command]
+pub fn list_recent_sessions(store: State<Store>) -> Vec<RecentSession> {
+    load(&store)
+}
+
+// The game replaces the current one, whose search is stopped. A timed game comes back paused.
+#[tauri::command]
+pub fn resume_session(
+    session: State<Mutex<Session>>,
+    engine: State<Engine>,
+    analysis: State<Analysis>,
+    store: State<Store>,
+    id: u64,
+) -> Result<GameStateView, String> {
+    let saved = load(&store).into_iter().find(|saved| saved.id == id).ok_or("no such game")?;
+    let restored = restore(saved).ok_or("the saved game cannot be replayed")?;
+    analysis.stop();
+    engine.send(Message::Stop);
+    engine.send(Message::NewGame);
+    let mut session = session.lock().unwrap();
+    *session = restored;
+    correspondence::save(&store, &session);
+    Ok(session.view())
+}
@@ -0,0 +1,307 @@
+use std::collections::BTreeMap;
+use std::fs;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use tauri::State;
+use crate::archive::{self, ArchivedGame};
+use crate::bookmarks::{self, Bookmark};
+use crate::correspondence::{self, SavedGame};
+use crate::drill::{self, ConversionStats};
+use crate::explorer::Explorer;
+use crate::ladder::{self, Ladder};
+use crate::nn::{self, Network};
+use crate::notation;
+use crate::recent::{self, RecentSession};
+use crate::repertoire::{self, Repertoire};
+use crate::shogi::{self, Move, Pieces, Rules};
+use crate::store::Store;
+use crate::strength::{self, ReferencePoint};
+use crate::tournament::{self, Tournament};
+
+// Brings the save files of older versions to the current schema and repairs the damaged ones,
+// rather than let the app read them as empty and write over them. A game whose moves stop being
+// legal keeps the legal part, and what cannot be read at all is moved aside to the quarantine
+// directory, for the user or a later version to recover by hand. It runs at startup after an
+// update, and on demand.
+
+const SCHEMA_FILE: &str = "schema.json";
+const SCHEMA_VERSION: u32 = 1;
+const QUARANTINE_DIR: &str = "quarantine";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Action {
+    Upgraded, // fields of an older version filled in
+    Truncated, // the moves after the first illegal one dropped
+    Quarantined,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Fix {
+    pub file: String,
+    pub record: Option<usize>, // index in the file, None for the whole file
+    pub action: Action,
+    pub detail: String,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RepairReport {
+    pub from_version: u32, // 0 before the schema was versioned
+    pub version: u32,
+    pub checked: Vec<String>, // the files found
+    pub fixes: Vec<Fix>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Schema {
+    version: u32,
+}
+
+struct Repair<'a> {
+    store: &'a Store,
+    report: RepairReport,
+}
+
+impl Repair<'_> {
+    fn fix(&mut self, file: &str, record: Option<usize>, action: Action, detail: String) {
+        self.report.fixes.push(Fix { file: file.into(), record, action, detail });
+    }
+
+    // "text" goes to the quarantine directory under the name of the file and the time
+    fn quarantine(&mut self, file: &str, record: Option<usize>, text: &str, reason: String) -> Result<(), String> {
+        let dir = self.store.path(QUARANTINE_DIR);
+        fs::create_dir_all(&dir).map_err(|err| err.to_string())?;
+        let stem = file.trim_end_matches(".json");
+        let suffix = record.map_or(String::new(), |record| format!("-{record}"));
+        let path = dir.join(format!("{stem}-{}{suffix}.json", correspondence::now_ms()));
+        fs::write(&path, text).map_err(|err| format!("cannot write {}: {err}", path.display()))?;
+        self.fix(file, record, Action::Quarantined, format!("{reason}, kept in {}", path.display()));
+        Ok(())
+    }
+
+    // the document of "file", None if there is none or if it has been quarantined
+    fn document(&mut self, file: &str) -> Result<Option<Value>, String> {
+        let Ok(text) = fs::read_to_string(self.store.path(file)) else { return Ok(None) };
+        self.report.checked.push(file.into());
+        match serde_json::from_str(&text) {
+            Ok(value) => Ok(Some(value)),
+            Err(err) => {
+                self.quarantine(file, None, &text, format!("not readable: {err}"))?;
+                self.store.remove(file);
+                Ok(None)
+            }
+        }
+    }
+
+    // a file the app reads as a whole, moved aside if it no longer fits its type
+    fn check<T: DeserializeOwned>(&mut self, file: &str) -> Result<(), String> {
+        let Some(value) = self.document(file)? else { return Ok(()) };
+        if let Err(err) = serde_json::from_value::<T>(value.clone()) {
+            self.quarantine(file, None, &value.to_string(), format!("invalid: {err}"))?;
+            self.store.remove(file);
+        }
+        Ok(())
+    }
+
+    // A list of games: each one is upgraded and replayed, then read as "T"; those that cannot be
+    // are quarantined one by one and the others saved back.
+    fn games<T: Serialize + DeserializeOwned>(&mut self, file: &str, upgrade: fn(&mut Map<String, Value>) -> Vec<&'static str>) -> Result<(), String> {
+        let Some(value) = self.document(file)? else { return Ok(()) };
+        let Value::Array(records) = value else {
+            self.quarantine(file, None, &value.to_string(), "not a list of games".into())?;
+            self.store.remove(file);
+            return Ok(());
+        };
+        let mut kept: Vec<T> = vec![];
+        let mut changed = false;
+        for (i, record) in records.into_iter().enumerate() {
+            let raw = record.to_string();
+            match self.game(file, Some(i), record, upgrade) {
+                Ok((game, fixed)) => {
+                    kept.push(game);
+                    changed |= fixed;
+                }
+                Err(reason) => {
+                    self.quarantine(file, Some(i), &raw, reason)?;
+                    changed = true;
+                }
+            }
+        }
+        if changed {
+            self.store.save(file, &kept)?;
+        }
+        Ok(())
+    }
+
+    // the game and whether it was changed, Err with the reason if it cannot be saved
+    fn game<T: DeserializeOwned>(
+        &mut self,
+        file: &str,
+        record: Option<usize>,
+        value: Value,
+        upgrade: fn(&mut Map<String, Value>) -> Vec<&'static str>,
+    ) -> Result<(T, bool), String> {
+        let Value::Object(mut game) = value else { return Err("not a game".into()) };
+        let filled = upgrade(&mut game);
+        let truncated = replay(&mut game)?;
+        let game = serde_json::from_value(Value::Object(game)).map_err(|err| format!("invalid: {err}"))?;
+        if !filled.is_empty() {
+            self.fix(file, record, Action::Upgraded, format!("added {}", filled.join(", ")));
+        }
+        let changed = !filled.is_empty() || truncated.is_some();
+        if let Some(detail) = truncated {
+            self.fix(file, record, Action::Truncated, detail);
+        }
+        Ok((game, changed))
+    }
+}
+
+fn initial_position() -> String {
+    notation::position_string(&shogi::initial_pieces(), false)
+}
+
+// the fields missing in "game" set to "default", their names returned
+fn fill(game: &mut Map<String, Value>, defaults: Vec<(&'static str, Value)>) -> Vec<&'static str> {
+    let mut filled = vec![];
+    for (name, default) in defaults {
+        if !game.contains_key(name) {
+            game.insert(name.into(), default);
+            filled.push(name);
+        }
+    }
+    filled
+}
+
+fn upgrade_archived(game: &mut Map<String, Value>) -> Vec<&'static str> {
+    fill(game, vec![
+        ("info", json!({})),
+        ("start", json!(initial_position())),
+        ("moveList", json!([])),
+        ("rules", json!(Rules::default())),
+        ("result", Value::Null),
+        ("endedMs", json!(0)),
+    ])
+}
+
+fn upgrade_saved(game: &mut Map<String, Value>) -> Vec<&'static str> {
+    fill(game, vec![("thinking_ms", json!([])), ("rules", json!(Rules::default())), ("result", Value::Null)])
+}
+
+fn upgrade_recent(game: &mut Map<String, Value>) -> Vec<&'static str> {
+    fill(game, vec![
+        ("id", json!(correspondence::now_ms())),
+        ("updatedMs", json!(0)),
+        ("start", json!(initial_position())),
+        ("position", json!(initial_position())),
+        ("thinkingMs", json!([])),
+        ("rules", json!(Rules::default())),
+        ("clock", Value::Null),
+        ("correspondence", Value::Null),
+        ("info", json!({})),
+        ("level", Value::Null),
+    ])
+}
+
+// The moves of the game are replayed from its start, those after the first illegal one are dropped
+// along with the result, which no longer follows from them. The names and the current position are
+// rewritten from the moves. Returns what was dropped, if anything.
+fn replay(game: &mut Map<String, Value>) -> Result<Option<String>, String> {
+    let start = match game.get("start") {
+        Some(start) => start.as_str().ok_or("invalid start")?.to_string(),
+        None => initial_position(),
+    };
+    let (start, turn) = notation::parse_position(&start).ok_or("invalid start position")?;
+    let rules: Rules = match game.get("rules") {
+        Some(rules) => serde_json::from_value(rules.clone()).map_err(|err| format!("invalid rules: {err}"))?,
+        None => Rules::default(),
+    };
+    let Some(Value::Array(moves)) = game.get("moves") else { return Err("no moves".into()) };
+    let total = moves.len();
+    let (mut pieces, mut turn_now): (Pieces, bool) = (start, turn);
+    let mut legal: Vec<Move> = vec![];
+    for value in moves {
+        let Ok(mov) = serde_json::from_value::<Move>(value.clone()) else { break };
+        let Some(mov) = shogi::legal_move(&pieces, &rules, mov.from, mov.to, Some(mov.promote)).filter(|mov| pieces[mov.from].owner == turn_now) else {
+            break;
+        };
+        legal.push(mov);
+        pieces = shogi::play_move(&pieces, mov);
+        turn_now = !turn_now;
+    }
+    if game.contains_key("moveList") {
+        game.insert("moveList".into(), json!(notation::move_list(&start, &legal)));
+    }
+    if game.contains_key("position") {
+        game.insert("position".into(), json!(notation::position_string(&pieces, turn_now)));
+    }
+    if legal.len() == total {
+        return Ok(None);
+    }
+    game.insert("moves".into(), json!(legal));
+    for times in ["thinkingMs", "thinking_ms"] {
+        if let Some(Value::Array(times)) = game.get_mut(times) {
+            times.truncate(legal.len());
+        }
+    }
+    if game.contains_key("result") {
+        game.insert("result".into(), Value::Null);
+    }
+    Ok(Some(format!("kept the first {} of {total} moves", legal.len())))
+}
+
+pub fn migrate_and_repair_store(store: &Store) -> Result<RepairReport, String> {
+    let from_version = store.load::<Schema>(SCHEMA_FILE).map_or(0, |schema| schema.version);
+    if from_version > SCHEMA_VERSION {
+        return Err("the save files come from a newer version of the app".into());
+    }
+    let mut repair = Repair { store, report: RepairReport { from_version, version: SCHEMA_VERSION, ..RepairReport::default() } };
+    repair.games::<ArchivedGame>(archive::FILE, upgrade_archived)?;
+    repair.games::<RecentSession>(recent::FILE, upgrade_recent)?;
+    // the correspondence game is a single one
+    if let Some(value) = repair.document(correspondence::FILE)? {
+        let raw = value.to_string();
+        match repair.game::<SavedGame>(correspondence::FILE, None, value, upgrade_saved) {
+            Ok((game, true)) => store.save(correspondence::FILE, &game)?,
+            Ok((_, false)) => {}
+            Err(reason) => {
+                repair.quarantine(correspondence::FILE, None, &raw, reason)?;
+                store.remove(correspondence::FILE);
+            }
+        }
+    }
+    repair.check::<Vec<Bookmark>>(bookmarks::FILE)?;
+    repair.check::<Repertoire>(repertoire::FILE)?;
+    repair.check::<Vec<Tournament>>(tournament::FILE)?;
+    repair.check::<BTreeMap<String, ConversionStats>>(drill::FILE)?;
+    repair.check::<Ladder>(ladder::FILE)?;
+    repair.check::<Vec<ReferencePoint>>(strength::FILE)?;
+    repair.check::<Network>(nn::FILE)?;
+    // the parameter file is TOML written by hand, its errors are reported when it is loaded
+    store.save(SCHEMA_FILE, &Schema { version: SCHEMA_VERSION })?;
+    Ok(repair.report)
+}
+
+// at startup, only when the files were saved by an older version
+pub fn startup(store: &Store) {
+    if store.load::<Schema>(SCHEMA_FILE).is_some_and(|schema| schema.version == SCHEMA_VERSION) {
+        return;
+    }
+    match migrate_and_repair_store(store) {
+        Ok(report) => {
+            for fix in &report.fixes {
+                eprintln!("{}: {:?} {}", fix.file, fix.action, fix.detail);
+            }
+        }
+        Err(err) => eprintln!("cannot repair the save files: {err}"),
+    }
+}
+
+#[tauri::command]
+pub fn migrate_and_repair(store: State<Store>, explorer: State<Explorer>) -> Result<RepairReport, String> {
+    let report = migrate_and_repair_store(&store)?;
+    explorer.reset();
+    Ok(report)
+}
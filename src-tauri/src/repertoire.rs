@@ -0,0 +1,303 @@
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use crate::correspondence;
+use crate::notation;
+use crate::shogi::{self, Pieces, Rules};
+use crate::store::Store;
+
+// The opening lines the user wants to know by heart, one tree of moves from the initial position
+// for each side played. A quiz takes the line most due for review, plays the moves of the
+// opponent and checks those of the user. Each line is then scheduled again, further away after
+// every success and back to the next day after a mistake.
+
+pub const FILE: &str = "repertoire.json";
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+const INITIAL_EASE: f64 = 2.5;
+const MIN_EASE: f64 = 1.3;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Recall {
+    pub time_ms: u64,
+    pub recalled: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Schedule {
+    pub due_ms: u64,
+    pub interval_days: f64,
+    pub ease: f64,
+    pub history: Vec<Recall>,
+}
+
+impl Schedule {
+    fn new(now_ms: u64) -> Self {
+        Schedule { due_ms: now_ms, interval_days: 0.0, ease: INITIAL_EASE, history: vec![] }
+    }
+
+    fn record(&mut self, recalled: bool, now_ms: u64) {
+        if recalled {
+            self.interval_days = if self.interval_days < 1.0 { 1.0 } else { self.interval_days * self.ease };
+            self.ease += 0.1;
+        } else {
+            self.interval_days = 1.0;
+            self.ease = (self.ease - 0.2).max(MIN_EASE);
+        }
+        self.due_ms = now_ms + (self.interval_days * DAY_MS as f64) as u64;
+        self.history.push(Recall { time_ms: now_ms, recalled });
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LineNode {
+    pub mov: Option<String>, // None at the root
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    pub schedule: Option<Schedule>, // the ends of the lines only
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LineTree {
+    pub nodes: Vec<LineNode>,
+}
+
+impl Default for LineTree {
+    fn default() -> Self {
+        LineTree { nodes: vec![LineNode { mov: None, parent: None, children: vec![], schedule: None }] }
+    }
+}
+
+impl LineTree {
+    fn child(&self, node: usize, name: &str) -> Option<usize> {
+        self.nodes[node].children.iter().copied().find(|&child| self.nodes[child].mov.as_deref() == Some(name))
+    }
+
+    // the moves from the root to "node"
+    fn line(&self, node: usize) -> Vec<String> {
+        let mut line = vec![];
+        let mut node = node;
+        while let (Some(parent), Some(mov)) = (self.nodes[node].parent, &self.nodes[node].mov) {
+            line.push(mov.clone());
+            node = parent;
+        }
+        line.reverse();
+        line
+    }
+
+    fn add(&mut self, moves: &[String], now_ms: u64) {
+        let mut node = 0;
+        for name in moves {
+            node = match self.child(node, name) {
+                Some(child) => child,
+                None => {
+                    let index = self.nodes.len();
+                    self.nodes.push(LineNode { mov: Some(name.clone()), parent: Some(node), children: vec![], schedule: None });
+                    self.nodes[node].children.push(index);
+                    index
+                }
+            };
+        }
+        // a line that goes on from the end of another replaces it in the reviews, a line that
+        // stops inside another is already reviewed with it
+        if self.nodes[node].children.is_empty() {
+            self.nodes[node].schedule.get_or_insert_with(|| Schedule::new(now_ms));
+            let mut ancestor = self.nodes[node].parent;
+            while let Some(i) = ancestor {
+                self.nodes[i].schedule = None;
+                ancestor = self.nodes[i].parent;
+            }
+        }
+    }
+
+    // removes the move and the moves after it, the parent may become the end of a line
+    fn delete(&mut self, index: usize, now_ms: u64) -> Result<(), String> {
+        let parent = self.nodes.get(index).ok_or("no such node")?.parent.ok_or("the root cannot be deleted")?;
+        let mut removed = vec![false; self.nodes.len()];
+        let mut stack = vec![index];
+        while let Some(i) = stack.pop() {
+            removed[i] = true;
+            stack.extend(&self.nodes[i].children);
+        }
+        let mut renumbered = vec![0; self.nodes.len()];
+        let mut next = 0;
+        for (i, &gone) in removed.iter().enumerate() {
+            renumbered[i] = next;
+            next += !gone as usize;
+        }
+        let nodes = std::mem::take(&mut self.nodes);
+        self.nodes = nodes.into_iter().zip(&removed).filter(|(_, &gone)| !gone).map(|(mut node, _)| {
+            node.parent = node.parent.map(|parent| renumbered[parent]);
+            node.children = node.children.iter().filter(|&&child| !removed[child]).map(|&child| renumbered[child]).collect();
+            node
+        }).collect();
+        let parent = renumbered[parent];
+        if parent != 0 && self.nodes[parent].children.is_empty() {
+            self.nodes[parent].schedule = Some(Schedule::new(now_ms));
+        }
+        Ok(())
+    }
+
+    // the ends of the lines, the most due first
+    fn due(&self) -> Vec<usize> {
+        let mut ends: Vec<usize> = (0..self.nodes.len()).filter(|&i| self.nodes[i].schedule.is_some()).collect();
+        ends.sort_by_key(|&i| self.nodes[i].schedule.as_ref().map_or(0, |schedule| schedule.due_ms));
+        ends
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Repertoire {
+    pub trees: [LineTree; 2], // for the first player and for the second
+}
+
+fn load(store: &Store) -> Repertoire {
+    store.load(FILE).unwrap_or_default()
+}
+
+// the moves as played from the initial position, None if one of them is illegal
+fn replay(moves: &[String]) -> Option<(Pieces, bool)> {
+    moves.iter().try_fold((shogi::initial_pieces(), false), |(pieces, turn), name| {
+        let mov = notation::parse_move(&pieces, turn, &Rules::default(), name)?;
+        Some((shogi::play_move(&pieces, mov), !turn))
+    })
+}
+
+// the quiz in progress
+pub struct Quiz {
+    side: bool,
+    end: usize, // the end of the line quizzed
+    line: Vec<String>,
+    ply: usize, // moves of the line played so far
+    failed: bool,
+}
+
+#[derive(Default)]
+pub struct RepertoireTrainer(Mutex<Option<Quiz>>);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Answer {
+    Correct,
+    OtherLine, // a move of the repertoire, but of another line: the user tries again
+    Wrong,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuizView {
+    pub side: bool,
+    pub position: String,
+    pub played: Vec<String>,
+    pub answer: Option<Answer>,
+    pub expected: Option<String>, // after a wrong move
+    pub finished: bool,
+}
+
+impl Quiz {
+    // the opponent replies until it is the turn of the user
+    fn advance(&mut self) {
+        while self.ply < self.line.len() && (self.ply % 2 == 1) != self.side {
+            self.ply += 1;
+        }
+    }
+
+    fn view(&self, answer: Option<Answer>, expected: Option<String>) -> QuizView {
+        let played = self.line[..self.ply].to_vec();
+        let (pieces, turn) = replay(&played).unwrap_or((shogi::initial_pieces(), false));
+        QuizView {
+            side: self.side,
+            position: notation::position_string(&pieces, turn),
+            played,
+            answer,
+            expected,
+            finished: self.ply >= self.line.len(),
+        }
+    }
+}
+
+// the moves are named from the initial position, the line is merged into the tree of "side"
+#[tauri::command]
+pub fn add_repertoire_line(store: State<Store>, side: bool, moves: Vec<String>) -> Result<Repertoire, String> {
+    if moves.is_empty() || replay(&moves).is_none() {
+        return Err("invalid line".into());
+    }
+    let mut repertoire = load(&store);
+    repertoire.trees[side as usize].add(&moves, correspondence::now_ms());
+    store.save(FILE, &repertoire)?;
+    Ok(repertoire)
+}
+
+#[tauri::command]
+pub fn delete_repertoire_line(store: State<Store>, side: bool, node: usize) -> Result<Repertoire, String> {
+    let mut repertoire = load(&store);
+    repertoire.trees[side as usize].delete(node, correspondence::now_ms())?;
+    store.save(FILE, &repertoire)?;
+    Ok(repertoire)
+}
+
+#[tauri::command]
+pub fn repertoire(store: State<Store>) -> Repertoire {
+    load(&store)
+}
+
+// the line most due for review among those of "side"
+#[tauri::command]
+pub fn start_repertoire_quiz(store: State<Store>, trainer: State<RepertoireTrainer>, side: bool) -> Result<QuizView, String> {
+    let tree = &load(&store).trees[side as usize];
+    let end = *tree.due().first().ok_or("no line to review")?;
+    let mut quiz = Quiz { side, end, line: tree.line(end), ply: 0, failed: false };
+    quiz.advance();
+    let view = quiz.view(None, None);
+    *trainer.0.lock().unwrap() = Some(quiz);
+    Ok(view)
+}
+
+// the move of the user, the line is scheduled again once finished
+#[tauri::command]
+pub fn repertoire_quiz_move(store: State<Store>, trainer: State<RepertoireTrainer>, mov: String) -> Result<QuizView, String> {
+    let mut trainer = trainer.0.lock().unwrap();
+    let quiz = trainer.as_mut().ok_or("no quiz in progress")?;
+    if quiz.ply >= quiz.line.len() {
+        return Err("the line is finished".into());
+    }
+    let (pieces, turn) = replay(&quiz.line[..quiz.ply]).ok_or("invalid line")?;
+    let mov = notation::parse_move(&pieces, turn, &Rules::default(), &mov).map(|mov| notation::move_name(&pieces, mov)).ok_or("illegal move")?;
+    let mut repertoire = load(&store);
+    let tree = &mut repertoire.trees[quiz.side as usize];
+    let expected = quiz.line[quiz.ply].clone();
+    let answer = if mov == expected {
+        quiz.ply += 1;
+        quiz.advance();
+        Answer::Correct
+    } else {
+        // the node reached so far, to tell a move of another line from a mistake
+        let node = quiz.line[..quiz.ply].iter().try_fold(0, |node, name| tree.child(node, name));
+        match node.and_then(|node| tree.child(node, &mov)) {
+            Some(_) => Answer::OtherLine,
+            None => Answer::Wrong,
+        }
+    };
+    // the first mistake fails the line, otherwise it is recalled once played to the end
+    let recalled = match answer {
+        Answer::Wrong if !quiz.failed => Some(false),
+        Answer::Correct if !quiz.failed && quiz.ply >= quiz.line.len() => Some(true),
+        _ => None,
+    };
+    if let (Some(recalled), Some(schedule)) = (recalled, tree.nodes.get_mut(quiz.end).and_then(|node| node.schedule.as_mut())) {
+        schedule.record(recalled, correspondence::now_ms());
+        store.save(FILE, &repertoire)?;
+    }
+    quiz.failed |= answer == Answer::Wrong;
+    // after a mistake the right move is shown and played, the quiz goes on to the end of the line
+    let shown = (answer == Answer::Wrong).then_some(expected);
+    if shown.is_some() {
+        quiz.ply += 1;
+        quiz.advance();
+    }
+    Ok(quiz.view(Some(answer), shown))
+}
@@ -0,0 +1,133 @@
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::sync::Mutex;
+use serde::Deserialize;
+use tauri::State;
+use crate::raster::{self, Image, RenderOptions};
+use crate::session::{GameResult, Session};
+use crate::shogi::{Move, Pieces, Search, HAND};
+
+const DEFAULT_CELL: usize = 48;
+const DEFAULT_FRAME_MS: u64 = 800;
+const DEFAULT_EVAL_DEPTH: u8 = 5;
+// the final position stays on screen longer before the animation loops
+const LAST_FRAME_FACTOR: u64 = 3;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AnimationFormat { Gif, Apng }
+
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayOptions {
+    pub format: Option<AnimationFormat>, // from the extension of the file by default
+    pub cell_size: Option<usize>, // in pixels
+    pub frame_ms: Option<u64>,
+    #[serde(default)]
+    pub eval_bar: bool,
+    pub eval_depth: Option<u8>, // depth of the searches scoring the eval bar
+}
+
+// one frame per position of the game, the squares of the last move highlighted
+fn frames(
+    history: &[(Pieces, Move)],
+    (current, turn): (Pieces, bool),
+    result: Option<GameResult>,
+    options: &ReplayOptions,
+) -> Vec<Image> {
+    let cell = options.cell_size.unwrap_or(DEFAULT_CELL).clamp(16, 256);
+    let depth = options.eval_depth.unwrap_or(DEFAULT_EVAL_DEPTH).max(1);
+    let mut positions: Vec<(Pieces, bool, Vec<u8>)> = vec![];
+    for (ply, (pieces, mov)) in history.iter().enumerate() {
+        if ply == 0 {
+            positions.push((*pieces, pieces[mov.from].owner, vec![]));
+        }
+        let from = pieces[mov.from].position;
+        let highlight = if from == HAND { vec![mov.to as u8] } else { vec![from, mov.to as u8] };
+        let next = history.get(ply + 1).map_or(current, |(pieces, _)| *pieces);
+        positions.push((next, !pieces[mov.from].owner, highlight));
+    }
+    if positions.is_empty() {
+        positions.push((current, turn, vec![]));
+    }
+    let last = positions.len() - 1;
+    positions.iter().enumerate().map(|(i, (pieces, turn, highlight))| {
+        let eval = options.eval_bar.then(|| match result {
+            Some(result) if i == last => match result.winner {
+                Some(winner) => if winner { -100000 } else { 100000 },
+                None => 0,
+            },
+            _ => Search::default().evaluate(pieces, *turn, depth).unwrap_or(0),
+        });
+        raster::render(pieces, &RenderOptions { cell, highlight: highlight.clone(), eval_bar: eval, ..RenderOptions::default() })
+    }).collect()
+}
+
+fn delays(count: usize, frame_ms: u64) -> impl Iterator<Item = u64> {
+    (0..count).map(move |i| if i + 1 == count { frame_ms * LAST_FRAME_FACTOR } else { frame_ms })
+}
+
+fn write_gif(path: &Path, frames: &[Image], frame_ms: u64) -> Result<(), String> {
+    let (width, height) = (frames[0].width as u16, frames[0].height as u16);
+    let file = File::create(path).map_err(|err| err.to_string())?;
+    let mut encoder = gif::Encoder::new(BufWriter::new(file), width, height, &raster::palette_bytes())
+        .map_err(|err| err.to_string())?;
+    encoder.set_repeat(gif::Repeat::Infinite).map_err(|err| err.to_string())?;
+    for (image, delay) in frames.iter().zip(delays(frames.len(), frame_ms)) {
+        let frame = gif::Frame {
+            width,
+            height,
+            delay: (delay / 10).min(u16::MAX as u64) as u16, // in hundredths of a second
+            buffer: Cow::Borrowed(&image.pixels),
+            ..gif::Frame::default()
+        };
+        encoder.write_frame(&frame).map_err(|err| err.to_string())?;
+    }
+    Ok(())
+}
+
+fn write_apng(path: &Path, frames: &[Image], frame_ms: u64) -> Result<(), String> {
+    let file = File::create(path).map_err(|err| err.to_string())?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), frames[0].width as u32, frames[0].height as u32);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_palette(raster::palette_bytes());
+    encoder.set_animated(frames.len() as u32, 0).map_err(|err| err.to_string())?;
+    let mut writer = encoder.write_header().map_err(|err| err.to_string())?;
+    for (image, delay) in frames.iter().zip(delays(frames.len(), frame_ms)) {
+        writer.set_frame_delay(delay.min(u16::MAX as u64) as u16, 1000).map_err(|err| err.to_string())?;
+        writer.write_image_data(&image.pixels).map_err(|err| err.to_string())?;
+    }
+    writer.finish().map_err(|err| err.to_string())
+}
+
+// Animated replay of the current game, a frame per move. The app has a single game session,
+// the replay is always taken from it.
+#[tauri::command(async)]
+pub fn export_replay_animation(
+    session: State<Mutex<Session>>,
+    path: String,
+    options: Option<ReplayOptions>,
+) -> Result<(), String> {
+    let options = options.unwrap_or_default();
+    let path = Path::new(&path);
+    let format = options.format.unwrap_or_else(|| {
+        match path.extension().and_then(|ext| ext.to_str()).map(str::to_lowercase).as_deref() {
+            Some("png" | "apng") => AnimationFormat::Apng,
+            _ => AnimationFormat::Gif,
+        }
+    });
+    // the searches of the eval bar run without holding the session
+    let (history, current, result) = {
+        let session = session.lock().unwrap();
+        (session.history().to_vec(), (*session.pieces(), session.turn()), session.result())
+    };
+    let frames = frames(&history, current, result, &options);
+    let frame_ms = options.frame_ms.unwrap_or(DEFAULT_FRAME_MS).max(20);
+    match format {
+        AnimationFormat::Gif => write_gif(path, &frames, frame_ms),
+        AnimationFormat::Apng => write_apng(path, &frames, frame_ms),
+    }
+}
@@ -0,0 +1,123 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State};
+use crate::analysis::{self, BatchPosition};
+use crate::notation;
+use crate::session::Session;
+use crate::shogi::{Move, Pieces};
+
+// Review of a finished game, the score of every position and what each move cost compared to the
+// best one. When enabled, a game is queued for review as soon as it ends and analyzed in the
+// background, so that the report is ready when the player opens the review screen.
+
+pub const REVIEW_DEPTH: u8 = 8;
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MoveReview {
+    pub ply: usize,
+    pub played: String,
+    pub best: Option<String>, // None when the game was already over
+    pub score_before: i32, // from the point of view of the first player
+    pub score_after: i32,
+    pub loss: i32, // what the move cost the player who made it, 0 for the best move
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GameReview {
+    pub moves: Vec<MoveReview>,
+    pub depth: u8,
+}
+
+// the positions of the game, with the player to move, the initial one first and the final one last
+struct Job {
+    positions: Vec<BatchPosition>,
+    moves: Vec<Move>,
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReviewReady {
+    plies: usize,
+}
+
+pub struct Review {
+    enabled: AtomicBool,
+    // the last game queued, so that it is reviewed once
+    queued: Mutex<Vec<(Pieces, Move)>>,
+    sender: Mutex<Sender<(AppHandle, Job)>>,
+    report: Arc<Mutex<Option<GameReview>>>,
+}
+
+impl Default for Review {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel::<(AppHandle, Job)>();
+        let report = Arc::new(Mutex::new(None));
+        let worker_report = report.clone();
+        thread::spawn(move || {
+            for (app, job) in receiver {
+                let review = review(&job);
+                let plies = review.moves.len();
+                *worker_report.lock().unwrap() = Some(review);
+                let _ = app.emit("review-ready", ReviewReady { plies });
+            }
+        });
+        Review { enabled: AtomicBool::new(false), queued: Mutex::default(), sender: Mutex::new(sender), report }
+    }
+}
+
+impl Review {
+    // called when the game ends, a game ended again the same way after a takeback is not queued again
+    pub fn game_over(&self, app: &AppHandle, session: &Session) {
+        let history = session.history();
+        if !self.enabled.load(Ordering::Relaxed) || history.is_empty() {
+            return;
+        }
+        let mut queued = self.queued.lock().unwrap();
+        if *queued == history {
+            return;
+        }
+        *queued = history.to_vec();
+        // the turns alternate back from the final position
+        let turn = |ply: usize| session.turn() ^ ((history.len() - ply) % 2 == 1);
+        let mut positions: Vec<BatchPosition> = history.iter().enumerate()
+            .map(|(ply, &(pieces, _))| BatchPosition { pieces, turn: turn(ply) })
+            .collect();
+        positions.push(BatchPosition { pieces: *session.pieces(), turn: session.turn() });
+        let moves = history.iter().map(|&(_, mov)| mov).collect();
+        let _ = self.sender.lock().unwrap().send((app.clone(), Job { positions, moves }));
+    }
+}
+
+fn review(job: &Job) -> GameReview {
+    let evaluations = analysis::evaluate_all(&job.positions, REVIEW_DEPTH);
+    let moves = job.moves.iter().enumerate().map(|(ply, &mov)| {
+        let position = job.positions[ply];
+        let (before, after) = (&evaluations[ply], &evaluations[ply + 1]);
+        let sign = if position.turn { -1 } else { 1 };
+        MoveReview {
+            ply,
+            played: notation::move_name(&position.pieces, mov),
+            best: before.pv.first().map(|&best| notation::move_name(&position.pieces, best)),
+            score_before: before.score,
+            score_after: after.score,
+            loss: (sign * (before.score - after.score)).max(0),
+        }
+    }).collect();
+    GameReview { moves, depth: REVIEW_DEPTH }
+}
+
+#[tauri::command]
+pub fn set_auto_review(review: State<Review>, enabled: bool) {
+    review.enabled.store(enabled, Ordering::Relaxed);
+}
+
+// the report of the last game reviewed, None until one is ready
+#[tauri::command]
+pub fn game_review(review: State<Review>) -> Option<GameReview> {
+    review.report.lock().unwrap().clone()
+}
@@ -0,0 +1,72 @@
+use serde::Serialize;
+use crate::policy;
+use crate::selfplay::Rng;
+use crate::shogi::{self, Pieces, HAND};
+
+// a playout still going on after this many plies counts as a draw
+const MAX_PLIES: usize = 100;
+// z-score of the 95% confidence interval
+const Z: f64 = 1.96;
+
+// outcome of the playouts from the point of view of the first player, like the engine scores
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RolloutEstimate {
+    pub playouts: u32,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+    pub win_rate: f64, // a draw counts as half a win
+    pub low: f64, // Wilson score interval of the win rate
+    pub high: f64,
+}
+
+// Plays random moves weighted by the move-ordering model until the game ends,
+// a move capturing the lion is always played. Returns the winner, None for a draw.
+fn playout(mut pieces: Pieces, mut turn: bool, rng: &mut Rng) -> Option<bool> {
+    for _ in 0..MAX_PLIES {
+        let moves = shogi::possible_moves(&pieces, turn);
+        let enemy_lion = shogi::lion(&pieces, !turn).position;
+        let mov = match moves.iter().find(|mov| mov.to as u8 == enemy_lion) {
+            Some(&mov) => mov,
+            None => {
+                let weights: Vec<f64> = moves.iter().map(|&mov| (policy::WEIGHTS.score(&pieces, mov) as f64).exp()).collect();
+                let mut target = (rng.next_u64() >> 11) as f64 / (1u64 << 53) as f64 * weights.iter().sum::<f64>();
+                let index = weights.iter().position(|&w| {
+                    target -= w;
+                    target < 0.0
+                });
+                moves[index.unwrap_or(moves.len() - 1)]
+            }
+        };
+        pieces = shogi::play_move(&pieces, mov);
+        if shogi::lion(&pieces, !turn).position == HAND || shogi::lion_reached_camp(&pieces, turn) {
+            return Some(turn);
+        }
+        turn = !turn;
+    }
+    None
+}
+
+pub fn estimate(pieces: &Pieces, turn: bool, playouts: u32, rng: &mut Rng) -> RolloutEstimate {
+    let (mut wins, mut draws, mut losses) = (0, 0, 0);
+    for _ in 0..playouts {
+        match playout(*pieces, turn, rng) {
+            Some(false) => wins += 1,
+            Some(true) => losses += 1,
+            None => draws += 1,
+        }
+    }
+    let n = playouts.max(1) as f64;
+    let p = (wins as f64 + draws as f64 / 2.0) / n;
+    let center = (p + Z * Z / (2.0 * n)) / (1.0 + Z * Z / n);
+    let margin = Z / (1.0 + Z * Z / n) * (p * (1.0 - p) / n + Z * Z / (4.0 * n * n)).sqrt();
+    RolloutEstimate { playouts, wins, draws, losses, win_rate: p, low: center - margin, high: center + margin }
+}
+
+// a cheap second opinion next to the alpha-beta score
+#[tauri::command(async)]
+pub fn rollout_estimate(pieces: Pieces, turn: bool, n: u32) -> Result<RolloutEstimate, String> {
+    shogi::validate(&pieces)?;
+    Ok(estimate(&pieces, turn, n, &mut Rng::from_clock()))
+}
@@ -0,0 +1,200 @@
+use std::sync::Arc;
+use crate::correspondence;
+use crate::endgame::{self, Outcome};
+use crate::session::{GameResult, Session, Termination};
+use crate::shogi::{self, EvalParams, Limits, Move, Pieces, Search, SearchResult, HAND};
+
+// a game still going on after this many plies is a draw
+const MAX_PLIES: usize = 200;
+
+// a fixed seed in deterministic mode, the clock otherwise
+pub fn clock_seed() -> u64 {
+    if shogi::deterministic() { 1 } else { correspondence::now_ms() }
+}
+
+// xorshift generator, good enough to vary openings and perturbations
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed.max(1))
+    }
+
+    // seeded by the clock, with a fixed seed in deterministic mode
+    pub fn from_clock() -> Self {
+        Rng::new(clock_seed())
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    // uniform in [0, 1)
+    pub fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    pub fn sign(&mut self) -> f64 {
+        if self.next_u64() & 1 == 0 { 1.0 } else { -1.0 }
+    }
+}
+
+// Ends engine games whose result is no longer in doubt, so that long matches finish sooner.
+// Scores are those of the searches of both engines, from the point of view of the first player.
+#[derive(Clone, Copy, Debug)]
+pub struct Adjudication {
+    pub win_score: i32,
+    pub win_moves: usize, // consecutive searches at or beyond win_score for the same player
+    pub draw_score: i32,
+    pub draw_moves: usize, // consecutive searches within draw_score
+    pub draw_after: usize, // no draw is adjudicated earlier, level openings are still played out
+    pub tablebase: bool, // a position proven drawn by the endgame solver is a draw
+}
+
+impl Default for Adjudication {
+    fn default() -> Self {
+        Adjudication { win_score: 200, win_moves: 8, draw_score: 5, draw_moves: 20, draw_after: 40, tablebase: true }
+    }
+}
+
+#[derive(Default)]
+struct Streaks {
+    win: (bool, usize), // the player ahead and for how many searches
+    draw: usize,
+    // pieces on the board at the last call to the solver, it is only called again once they change
+    // and only in level positions, where a draw is likely
+    solved: Option<usize>,
+}
+
+impl Streaks {
+    // the adjudicated winner after the search of "score" in "pieces", None for a draw
+    fn adjudicate(&mut self, rules: &Adjudication, ply: usize, score: i32, pieces: &Pieces, turn: bool) -> Option<Option<bool>> {
+        if score.abs() >= rules.win_score {
+            let leader = score < 0;
+            self.win = if self.win.0 == leader { (leader, self.win.1 + 1) } else { (leader, 1) };
+        } else {
+            self.win.1 = 0;
+        }
+        self.draw = if score.abs() <= rules.draw_score { self.draw + 1 } else { 0 };
+        if self.win.1 >= rules.win_moves {
+            return Some(Some(self.win.0));
+        }
+        if ply >= rules.draw_after && self.draw >= rules.draw_moves {
+            return Some(None);
+        }
+        let on_board = pieces.iter().filter(|piece| piece.position != HAND).count();
+        if !rules.tablebase || self.draw == 0 || !endgame::is_sparse(pieces) || self.solved == Some(on_board) {
+            return None;
+        }
+        self.solved = Some(on_board);
+        let proven_draw = endgame::solve(pieces, turn).is_some_and(|solution| solution.outcome == Outcome::Draw);
+        proven_draw.then_some(None)
+    }
+}
+
+// chooses the move of one player in the position of the session, None without a legal move
+pub type Mover<'a> = dyn FnMut(&Session) -> Option<SearchResult> + 'a;
+
+// Plays a game between two players, "movers[0]" moves first.
+// The first "random_plies" moves are random so that repeated games differ.
+// Returns the finished session, a game that lasts too long has no result.
+pub fn play_game_with(movers: [&mut Mover; 2], random_plies: usize, adjudication: Option<&Adjudication>, rng: &mut Rng) -> Session {
+    play_from(Session::default(), movers, random_plies, adjudication, rng)
+}
+
+// the game of "session" played on from where it is, under its rules, "movers[0]" playing the first player
+pub fn play_from(mut session: Session, movers: [&mut Mover; 2], random_plies: usize, adjudication: Option<&Adjudication>, rng: &mut Rng) -> Session {
+    let mut streaks = Streaks::default();
+    while session.result().is_none() && session.ply() < MAX_PLIES {
+        let turn = session.turn();
+        let mov = if session.ply() < random_plies {
+            let moves = shogi::legal_moves(session.pieces(), turn, &session.rules());
+            if moves.is_empty() {
+                break;
+            }
+            moves[rng.below(moves.len())]
+        } else {
+            let Some(result) = movers[turn as usize](&session) else { break };
+            let verdict = adjudication.and_then(|rules| streaks.adjudicate(rules, session.ply(), result.score, session.pieces(), turn));
+            if let Some(winner) = verdict {
+                session.set_result(GameResult { winner, reason: Termination::Adjudication });
+                break;
+            }
+            result.mov
+        };
+        if session.play(mov).is_err() {
+            break;
+        }
+    }
+    session
+}
+
+// a game between two sets of weights searching at "depth"
+pub fn play_game(
+    players: [&Arc<EvalParams>; 2],
+    depth: u8,
+    random_plies: usize,
+    adjudication: Option<&Adjudication>,
+    rng: &mut Rng,
+) -> Session {
+    let [mut first, mut second] = players.map(|params| {
+        let mut search = Search::with_params(Arc::default(), params.clone());
+        move |session: &Session| {
+            shogi::best_move(&mut search, session.pieces(), &session.played(), Limits::depth(depth), session.turn(), &mut |_| ())
+        }
+    });
+    play_game_with([&mut first, &mut second], random_plies, adjudication, rng)
+}
+
+// points of "players[0]" over a pair of games with the same random opening and colours reversed:
+// 1 for a win and 0.5 for a draw
+pub fn play_pair(players: [&Arc<EvalParams>; 2], depth: u8, random_plies: usize, adjudication: Option<&Adjudication>, seed: u64) -> f64 {
+    let [a, b] = players;
+    let points = |session: Session, a_moves_first: bool| match session.result().and_then(|result| result.winner) {
+        Some(winner) => if winner != a_moves_first { 1.0 } else { 0.0 },
+        None => 0.5,
+    };
+    let first = points(play_game([a, b], depth, random_plies, adjudication, &mut Rng::new(seed)), true);
+    let second = points(play_game([b, a], depth, random_plies, adjudication, &mut Rng::new(seed)), false);
+    first + second
+}
+
+// a position of a self-play game, with the move played from it and the outcome of the game
+#[derive(Clone, Copy, Debug)]
+pub struct Sample {
+    pub game: usize,
+    pub ply: usize,
+    pub pieces: Pieces,
+    pub turn: bool,
+    pub mov: Move,
+    pub outcome: f32, // 1 if the first player won, -1 if the first player lost, 0 for a draw
+}
+
+// Self-play games of the weights in use. The number of random opening moves varies
+// up to "max_random_plies" so that the games explore different positions.
+// The random moves are not part of the samples, the games are played to the end so that
+// every outcome is a real one.
+pub fn generate(games: usize, depth: u8, max_random_plies: usize, rng: &mut Rng) -> Vec<Sample> {
+    let params = shogi::eval_params();
+    let mut samples = vec![];
+    for game in 0..games {
+        let random_plies = rng.below(max_random_plies + 1);
+        let session = play_game([&params, &params], depth, random_plies, None, rng);
+        let outcome = match session.result().and_then(|result| result.winner) {
+            Some(winner) => if winner { -1.0 } else { 1.0 },
+            None => 0.0,
+        };
+        let positions = session.played();
+        for (ply, (pieces, mov)) in positions.into_iter().zip(session.moves()).enumerate().skip(random_plies) {
+            samples.push(Sample { game, ply, pieces, turn: ply % 2 == 1, mov, outcome });
+        }
+    }
+    samples
+}
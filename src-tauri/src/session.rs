@@ -0,0 +1,1053 @@
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+use crate::analysis::Analysis;
+use crate::archive::{self, GameInfo};
+use crate::assets;
+use crate::calibration::Calibration;
+use crate::clock::{Clock, ClockView, TimeControl};
+use crate::correspondence::{self, Correspondence};
+use crate::drill::{self, Drill};
+use crate::endgame::{self, EndgameStyle};
+use crate::engine::{Engine, Hold, Message};
+use crate::events::{self, SessionEvent};
+use crate::explorer::Explorer;
+use crate::human::HumanStyle;
+use crate::notation;
+use crate::review::Review;
+use crate::shogi::{self, HandSlot, Kind, Limits, Move, Pieces, Progress, Rules, SearchControl, Standard, HAND};
+use crate::store::Store;
+use crate::timeman;
+use crate::warning::Severity;
+
+const TIME_CHECK_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Termination { LionCaptured, LionReachedCamp, Repetition, Timeout, Resignation, Agreement, MoveLimit, Adjudication }
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct GameResult {
+    pub winner: Option<bool>, // None for a draw
+    pub reason: Termination,
+}
+
+#[derive(Clone, Copy, Serialize)]
+pub struct PieceView {
+    index: usize,
+    kind: Kind,
+    owner: bool,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct LastMove {
+    pub from: Option<u8>, // None for a drop
+    pub to: u8,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GameStateView {
+    board: [Option<PieceView>; Standard::SQUARES],
+    hands: [Vec<HandSlot>; 2],
+    turn: bool,
+    last_move: Option<LastMove>,
+    premove: Option<Move>,
+    move_list: Vec<String>,
+    thinking_ms: Vec<u64>, // time spent on each move of move_list
+    lions_in_danger: [bool; 2],
+    legal_move_count: usize,
+    clock: Option<ClockView>,
+    deadline_ms: Option<u64>, // correspondence games only
+    paused: bool,
+    draw_offer: Option<bool>, // the player who offered a draw, until it is answered
+    takeback_request: Option<TakebackRequest>,
+    moves_left: Option<u32>, // before the game is drawn by the move limit
+    result: Option<GameResult>,
+    info: GameInfo,
+    ai_level: Option<AiLevel>, // the last level the engine played at
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TakebackRequest {
+    pub player: bool,
+    pub plies: usize, // the moves to take back, the last ones
+}
+
+// Sent by the player accepting a takeback so that both sides of a network game roll back to the
+// same ply with the same clock times, whatever the lag between them.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Takeback {
+    pub ply: usize, // number of moves left
+    pub remaining_ms: Option<[u64; 2]>, // the clock times at that ply in a timed game
+}
+
+// strength and pace of the engine, the clock takes precedence in timed games
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AiLevel {
+    pub depth: u8,
+    pub min_think_ms: Option<u64>, // pacing, the reply is never delivered earlier
+    pub respond_within_ms: Option<u64>, // replaces "depth", from the measured speed of the machine
+    #[serde(default)]
+    pub endgame_style: EndgameStyle,
+    #[serde(default)]
+    pub conduct: Conduct,
+    #[serde(default)]
+    pub ponder: bool, // the engine goes on thinking during the time of the opponent
+    #[serde(default)]
+    pub human: Option<HumanStyle>, // plays like a person of this strength, at the depth of the level
+    #[serde(default)]
+    pub move_warnings: Option<Severity>, // the least serious mistake the player is warned of before playing it
+}
+
+// when the engine gives up or settles for a draw, scores are from the point of view of the engine
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Conduct {
+    pub never_resign: bool, // for beginners who want to play the game to the end
+    pub resign_score: i32,
+    pub resign_moves: usize, // consecutive searches at or below -resign_score, a proven loss is enough
+    pub draw_score: i32, // the largest score considered dead equal
+    pub draw_repetitions: usize, // times the position must have been seen before
+}
+
+impl Default for Conduct {
+    fn default() -> Self {
+        Conduct { never_resign: false, resign_score: 1500, resign_moves: 3, draw_score: 30, draw_repetitions: 1 }
+    }
+}
+
+impl Conduct {
+    // "scores" of the last searches, the latest last
+    fn resigns(&self, scores: &[i32]) -> bool {
+        let proven_loss = scores.last().is_some_and(|&score| score <= -100000);
+        let hopeless = self.resign_moves > 0 && scores.len() >= self.resign_moves
+            && scores[scores.len() - self.resign_moves..].iter().all(|&score| score <= -self.resign_score);
+        !self.never_resign && (proven_loss || hopeless)
+    }
+
+    fn draws(&self, score: i32, repetitions: usize) -> bool {
+        score.abs() <= self.draw_score && repetitions >= self.draw_repetitions
+    }
+}
+
+pub struct Session {
+    id: u64, // creation time, tells the recent games apart
+    pieces: Pieces,
+    turn: bool,
+    rules: Rules,
+    // position before each move and the move played from it
+    history: Vec<(Pieces, Move)>,
+    // time spent on each move of the history, pauses excluded
+    thinking_times: Vec<Duration>,
+    // time spent by the player to move so far, "thinking_since" is None while the game is paused or over
+    thought: Duration,
+    thinking_since: Option<Instant>,
+    result: Option<GameResult>,
+    // principal variation of the last engine search and the ply it starts from
+    engine_pv: Option<(usize, Vec<Move>)>,
+    // move registered by the player waiting for the opponent
+    premove: Option<Move>,
+    clock: Option<Clock>,
+    correspondence: Option<Correspondence>,
+    paused: bool,
+    drill: Option<Drill>,
+    draw_offer: Option<bool>, // the player who offered a draw
+    takeback_request: Option<TakebackRequest>,
+    // times of the clock before each move of the history, in timed games
+    clock_marks: Vec<[Duration; 2]>,
+    // scores of the engine searches that led to a move, with the ply of the move
+    engine_scores: Vec<(usize, i32)>,
+    info: GameInfo,
+    // the last level the engine played at
+    level: Option<AiLevel>,
+    // id of the game in the archive and its length when it was recorded
+    archived: Option<(u32, usize)>,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Session::new(None)
+    }
+}
+
+impl Session {
+    // the clock of the first player starts running immediately
+    pub fn new(time_controls: Option<[TimeControl; 2]>) -> Self {
+        let now = Instant::now();
+        let clock = time_controls.map(|controls| {
+            let mut clock = Clock::new(controls);
+            clock.start(now);
+            clock
+        });
+        Session {
+            id: correspondence::now_ms(),
+            pieces: shogi::initial_pieces(),
+            turn: false,
+            rules: Rules::default(),
+            history: vec![],
+            thinking_times: vec![],
+            thought: Duration::ZERO,
+            thinking_since: Some(now),
+            result: None,
+            engine_pv: None,
+            premove: None,
+            clock,
+            correspondence: None,
+            paused: false,
+            drill: None,
+            draw_offer: None,
+            takeback_request: None,
+            clock_marks: vec![],
+            engine_scores: vec![],
+            info: GameInfo::default(),
+            level: None,
+            archived: None,
+        }
+    }
+
+    // a game starting from an arbitrary position, without clock
+    pub fn from_position(pieces: Pieces, turn: bool) -> Self {
+        Session { pieces, turn, ..Session::new(None) }
+    }
+
+    pub fn rules(&self) -> Rules {
+        self.rules
+    }
+
+    // before the first move only, the rules hold for the whole game
+    pub fn set_rules(&mut self, rules: Rules) {
+        if self.history.is_empty() {
+            self.rules = rules;
+        }
+    }
+
+    // moves left before the game is drawn by the move limit, if any
+    pub fn moves_left(&self) -> Option<u32> {
+        self.rules.max_moves.map(|max| max.saturating_sub(self.history.len() as u32))
+    }
+
+    pub fn set_drill(&mut self, drill: Drill) {
+        self.drill = Some(drill);
+    }
+
+    // the drill and its result once the game is over, only once
+    pub fn take_finished_drill(&mut self) -> Option<(Drill, GameResult)> {
+        let result = self.result?;
+        self.drill.take().map(|drill| (drill, result))
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn set_id(&mut self, id: u64) {
+        self.id = id;
+    }
+
+    pub fn clock(&self) -> Option<&Clock> {
+        self.clock.as_ref()
+    }
+
+    // a clock going on from "controls", the game is paused until resumed
+    pub fn restore_clock(&mut self, controls: [TimeControl; 2]) {
+        let mut clock = Clock::new(controls);
+        clock.rewind(clock.times(), self.turn, Instant::now());
+        self.clock = Some(clock);
+        self.pause();
+    }
+
+    pub fn ai_level(&self) -> Option<AiLevel> {
+        self.level
+    }
+
+    pub fn set_ai_level(&mut self, level: AiLevel) {
+        self.level = Some(level);
+    }
+
+    pub fn info(&self) -> &GameInfo {
+        &self.info
+    }
+
+    pub fn set_info(&mut self, info: GameInfo) {
+        self.info = info;
+    }
+
+    pub fn archived(&self) -> Option<u32> {
+        self.archived.map(|(id, _)| id)
+    }
+
+    pub fn set_archived(&mut self, id: u32) {
+        self.archived = Some((id, self.history.len()));
+    }
+
+    // the game is over and its archived record, if any, is from before a takeback
+    pub fn archive_due(&self) -> bool {
+        self.result.is_some() && self.archived.is_none_or(|(_, plies)| plies != self.history.len())
+    }
+
+    pub fn correspondence(&self) -> Option<Correspondence> {
+        self.correspondence
+    }
+
+    pub fn set_correspondence(&mut self, correspondence: Correspondence) {
+        self.correspondence = Some(correspondence);
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    // the clocks are frozen until the game is resumed
+    pub fn pause(&mut self) {
+        let now = Instant::now();
+        if let Some(clock) = &mut self.clock {
+            clock.stop(now);
+        }
+        self.stop_thinking(now);
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        if self.paused && self.result.is_none() {
+            let now = Instant::now();
+            if let Some(clock) = &mut self.clock {
+                clock.start(now);
+            }
+            self.thinking_since = Some(now);
+        }
+        self.paused = false;
+    }
+
+    fn stop_thinking(&mut self, now: Instant) {
+        if let Some(since) = self.thinking_since.take() {
+            self.thought += now.saturating_duration_since(since);
+        }
+    }
+
+    // the time spent on each move played so far
+    pub fn thinking_times(&self) -> &[Duration] {
+        &self.thinking_times
+    }
+
+    // used when the times of a saved game are restored
+    pub fn set_thinking_times(&mut self, times: Vec<Duration>) {
+        if times.len() == self.history.len() {
+            self.thinking_times = times;
+        }
+    }
+
+    pub fn set_result(&mut self, result: GameResult) {
+        self.result = Some(result);
+    }
+
+    // ends the game on time if the player to move has run out of time on their clock
+    // or has missed their correspondence deadline
+    pub fn check_time(&mut self, now_ms: u64) -> bool {
+        if self.result.is_some() {
+            return false;
+        }
+        let now = Instant::now();
+        let flagged = self.clock.as_ref().is_some_and(|clock| clock.flagged(now).is_some());
+        let expired = self.correspondence.is_some_and(|c| c.expired(now_ms));
+        if !flagged && !expired {
+            return false;
+        }
+        self.end(GameResult { winner: Some(!self.turn), reason: Termination::Timeout });
+        true
+    }
+
+    // a result reached off the board, the clocks stop
+    fn end(&mut self, result: GameResult) {
+        let now = Instant::now();
+        if let Some(clock) = &mut self.clock {
+            clock.stop(now);
+        }
+        self.stop_thinking(now);
+        self.premove = None;
+        self.draw_offer = None;
+        self.result = Some(result);
+    }
+
+    pub fn resign(&mut self, player: bool) -> Result<(), String> {
+        if self.result.is_some() {
+            return Err("the game is over".into());
+        }
+        self.end(GameResult { winner: Some(!player), reason: Termination::Resignation });
+        Ok(())
+    }
+
+    // the offer stands until the opponent answers it or makes a move
+    pub fn offer_draw(&mut self, player: bool) -> Result<(), String> {
+        if self.result.is_some() {
+            return Err("the game is over".into());
+        }
+        self.draw_offer = Some(player);
+        Ok(())
+    }
+
+    // the answer of "player", who cannot answer their own offer
+    pub fn respond_draw(&mut self, player: bool, accept: bool) -> Result<(), String> {
+        if self.result.is_some() {
+            return Err("the game is over".into());
+        }
+        match self.draw_offer {
+            None => return Err("no draw has been offered".into()),
+            Some(offered) if offered == player => return Err("the draw was offered by the same player".into()),
+            Some(_) => self.draw_offer = None,
+        }
+        if accept {
+            self.end(GameResult { winner: None, reason: Termination::Agreement });
+        }
+        Ok(())
+    }
+
+    // the player who offered a draw, if the offer stands
+    pub fn draw_offer(&self) -> Option<bool> {
+        self.draw_offer
+    }
+
+    // the request stands until the opponent answers it or a move is played
+    pub fn request_takeback(&mut self, player: bool, plies: usize) -> Result<(), String> {
+        if plies == 0 || plies > self.history.len() {
+            return Err("there are not so many moves to take back".into());
+        }
+        self.takeback_request = Some(TakebackRequest { player, plies });
+        Ok(())
+    }
+
+    pub fn takeback_request(&self) -> Option<TakebackRequest> {
+        self.takeback_request
+    }
+
+    // the answer of "player", who cannot answer their own request, and the agreement to apply on
+    // both sides when "accept" is true
+    pub fn respond_takeback(&mut self, player: bool, accept: bool) -> Result<Option<Takeback>, String> {
+        let request = self.takeback_request.ok_or("no takeback has been requested")?;
+        if request.player == player {
+            return Err("the takeback was requested by the same player".into());
+        }
+        self.takeback_request = None;
+        if !accept {
+            return Ok(None);
+        }
+        let ply = self.history.len() - request.plies;
+        let remaining = self.clock_marks.get(ply).map(|times| times.map(|time| time.as_millis() as u64));
+        let takeback = Takeback { ply, remaining_ms: remaining };
+        self.take_back(takeback)?;
+        Ok(Some(takeback))
+    }
+
+    // rolls the game back as agreed, the request is answered by the agreement
+    pub fn take_back(&mut self, takeback: Takeback) -> Result<(), String> {
+        if takeback.ply > self.history.len() {
+            return Err("the takeback goes beyond the moves played".into());
+        }
+        while self.history.len() > takeback.ply {
+            self.undo();
+        }
+        self.takeback_request = None;
+        if let (Some(clock), Some(remaining)) = (&mut self.clock, takeback.remaining_ms) {
+            clock.rewind(remaining.map(Duration::from_millis), self.turn, Instant::now());
+        }
+        Ok(())
+    }
+
+    pub fn pieces(&self) -> &Pieces {
+        &self.pieces
+    }
+
+    pub fn turn(&self) -> bool {
+        self.turn
+    }
+
+    pub fn result(&self) -> Option<GameResult> {
+        self.result
+    }
+
+    // number of moves played so far
+    pub fn ply(&self) -> usize {
+        self.history.len()
+    }
+
+    // the part of the last engine principal variation that is still ahead of the current position
+    pub fn expected_line(&self) -> &[Move] {
+        match &self.engine_pv {
+            Some((ply, pv)) if *ply <= self.history.len() && self.history.len() - ply <= pv.len() => {
+                let played = &self.history[*ply..];
+                if played.iter().zip(pv).all(|((_, mov), mov2)| mov == mov2) {
+                    &pv[played.len()..]
+                } else {
+                    &[]
+                }
+            }
+            _ => &[],
+        }
+    }
+
+    // position before each move and the move played from it
+    pub fn history(&self) -> &[(Pieces, Move)] {
+        &self.history
+    }
+
+    // the position the game started from and the player to move in it
+    pub fn start(&self) -> (Pieces, bool) {
+        match self.history.first() {
+            Some(&(pieces, _)) => (pieces, self.turn ^ (self.history.len() % 2 == 1)),
+            None => (self.pieces, self.turn),
+        }
+    }
+
+    pub fn moves(&self) -> Vec<Move> {
+        self.history.iter().map(|(_, mov)| *mov).collect()
+    }
+
+    pub fn played(&self) -> Vec<Pieces> {
+        self.history.iter().map(|(pieces, _)| *pieces).collect()
+    }
+
+    pub fn play(&mut self, mov: Move) -> Result<(), String> {
+        self.apply(mov, None)
+    }
+
+    // move received over the network, "thinking_time" is the time measured by the opponent's clock
+    pub fn play_remote(&mut self, mov: Move, thinking_time: Duration) -> Result<(), String> {
+        self.apply(mov, Some(thinking_time))
+    }
+
+    fn apply(&mut self, mov: Move, thinking_time: Option<Duration>) -> Result<(), String> {
+        if self.result.is_some() {
+            return Err("the game is over".into());
+        }
+        // the lag compensation of a remote move may still save the player
+        if thinking_time.is_none() && self.check_time(correspondence::now_ms()) {
+            return Err("the time is up".into());
+        }
+        // under the standard rules a chick reaching the last rank promotes whatever the move says
+        let mov = shogi::legal_move(&self.pieces, &self.rules, mov.from, mov.to, Some(mov.promote))
+            .filter(|mov| self.pieces[mov.from].owner == self.turn)
+            .ok_or("illegal move")?;
+        let now = Instant::now();
+        self.stop_thinking(now);
+        // a move declines the draw offered by the opponent
+        if self.draw_offer == Some(!self.turn) {
+            self.draw_offer = None;
+        }
+        self.takeback_request = None;
+        if let Some(clock) = &self.clock {
+            self.clock_marks.push(clock.times());
+        }
+        self.history.push((self.pieces, mov));
+        self.thinking_times.push(thinking_time.unwrap_or(self.thought));
+        self.pieces = shogi::play_move(&self.pieces, mov);
+        self.result = self.compute_result(self.turn);
+        self.turn = !self.turn;
+        self.thought = Duration::ZERO;
+        if self.result.is_none() && !self.paused {
+            self.thinking_since = Some(now);
+        }
+        if let Some(correspondence) = &mut self.correspondence {
+            correspondence.restart(correspondence::now_ms());
+        }
+        if let Some(clock) = &mut self.clock {
+            clock.press(now, thinking_time);
+            if self.result.is_some() {
+                clock.stop(now);
+            }
+        }
+        if let Some(premove) = self.premove.take() {
+            self.play_premove(premove);
+        }
+        Ok(())
+    }
+
+    pub fn set_premove(&mut self, mov: Move) -> Result<(), String> {
+        if self.result.is_some() {
+            return Err("the game is over".into());
+        }
+        if mov.from >= 8 || mov.to >= 12 || self.pieces[mov.from].owner == self.turn {
+            return Err("a premove must be a move of the player waiting for the opponent".into());
+        }
+        self.premove = Some(mov);
+        Ok(())
+    }
+
+    pub fn cancel_premove(&mut self) {
+        self.premove = None;
+    }
+
+    // the premove is played if it is legal after the opponent's move, and discarded otherwise
+    fn play_premove(&mut self, mut mov: Move) {
+        let piece = self.pieces[mov.from];
+        if piece.owner != self.turn {  // the piece has been captured
+            return;
+        }
+        if piece.position == HAND {
+            // the twin piece may be the one to drop now
+            if let Some(slot) = shogi::hand(&self.pieces, self.turn).iter().find(|slot| slot.kind == piece.kind) {
+                mov.from = slot.drop_index;
+            }
+        }
+        let _ = self.play(mov);
+    }
+
+    pub fn undo(&mut self) -> bool {
+        match self.history.pop() {
+            Some((pieces, _)) => {
+                let now = Instant::now();
+                self.thinking_times.pop();
+                self.clock_marks.truncate(self.history.len());
+                self.thought = Duration::ZERO;
+                self.thinking_since = if self.paused { None } else { Some(now) };
+                if let Some(clock) = &mut self.clock {
+                    clock.take_back(now);
+                    if self.result.is_some() { // the game goes on again
+                        clock.start(now);
+                    }
+                }
+                if let Some(correspondence) = &mut self.correspondence {
+                    correspondence.restart(correspondence::now_ms());
+                }
+                self.pieces = pieces;
+                self.turn = !self.turn;
+                self.result = None;
+                self.premove = None;
+                self.draw_offer = None;
+                self.takeback_request = None;
+                let ply = self.history.len();
+                self.engine_scores.retain(|&(p, _)| p < ply);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // search limits for the player to move: from their clock if there is one, "depth" otherwise
+    pub fn limits(&self, depth: u8) -> Limits {
+        let limits = match &self.clock {
+            Some(clock) => {
+                let remaining = clock.remaining(self.turn, Instant::now());
+                Limits::time(timeman::allocate(remaining, clock.increment(self.turn), self.ply(), &self.pieces))
+            }
+            None => Limits::depth(depth),
+        };
+        limits.with_rules(self.rules, self.ply())
+    }
+
+    fn compute_result(&self, mover: bool) -> Option<GameResult> {
+        if shogi::lion(&self.pieces, !mover).position == HAND {
+            Some(GameResult { winner: Some(mover), reason: Termination::LionCaptured })
+        } else if shogi::lion_reached_camp(&self.pieces, mover) {
+            Some(GameResult { winner: Some(mover), reason: Termination::LionReachedCamp })
+        } else if self.history.iter().filter(|(pieces, _)| *pieces == self.pieces).count() >= 2 {
+            Some(GameResult { winner: None, reason: Termination::Repetition })
+        } else if self.moves_left() == Some(0) {
+            Some(GameResult { winner: None, reason: Termination::MoveLimit })
+        } else {
+            None
+        }
+    }
+
+    pub fn view(&self) -> GameStateView {
+        let mut board = [None; Standard::SQUARES];
+        for (index, piece) in self.pieces.iter().enumerate() {
+            if piece.position != HAND {
+                board[piece.position as usize] = Some(PieceView { index, kind: piece.kind, owner: piece.owner });
+            }
+        }
+        let lion_in_danger = |owner| {
+            let position = shogi::lion(&self.pieces, owner).position;
+            position != HAND && shogi::is_attacked(&self.pieces, position, !owner)
+        };
+        GameStateView {
+            board,
+            hands: [shogi::hand(&self.pieces, false), shogi::hand(&self.pieces, true)],
+            turn: self.turn,
+            last_move: self.history.last().map(|(pieces, mov)| {
+                let from = pieces[mov.from].position;
+                LastMove { from: if from == HAND { None } else { Some(from) }, to: mov.to as u8 }
+            }),
+            premove: self.premove,
+            move_list: self.history.iter().map(|(pieces, mov)| notation::move_name(pieces, *mov)).collect(),
+            thinking_ms: self.thinking_times.iter().map(|time| time.as_millis() as u64).collect(),
+            lions_in_danger: [lion_in_danger(false), lion_in_danger(true)],
+            legal_move_count: shogi::legal_moves(&self.pieces, self.turn, &self.rules).len(),
+            clock: self.clock.as_ref().map(|clock| clock.view(Instant::now())),
+            deadline_ms: self.correspondence.map(|c| c.deadline_ms),
+            paused: self.paused,
+            draw_offer: self.draw_offer,
+            takeback_request: self.takeback_request,
+            moves_left: self.moves_left(),
+            result: self.result,
+            info: self.info.clone(),
+            ai_level: self.level,
+        }
+    }
+}
+
+#[tauri::command]
+pub fn new_game(
+    session: State<Mutex<Session>>,
+    engine: State<Engine>,
+    analysis: State<Analysis>,
+    store: State<Store>,
+    time_controls: Option<[TimeControl; 2]>, // per player, they differ in a time-odds game
+    per_move_ms: Option<u64>, // correspondence game
+    rules: Option<Rules>,
+) -> GameStateView {
+    stop_engine(&engine, &analysis);
+    engine.send(Message::NewGame);
+    let mut session = session.lock().unwrap();
+    *session = Session::new(time_controls);
+    session.set_rules(rules.unwrap_or_default());
+    if let Some(per_move_ms) = per_move_ms {
+        session.set_correspondence(Correspondence::new(per_move_ms, correspondence::now_ms()));
+    }
+    correspondence::save(&store, &session);
+    session.view()
+}
+
+#[tauri::command]
+pub fn game_state(session: State<Mutex<Session>>) -> GameStateView {
+    session.lock().unwrap().view()
+}
+
+#[tauri::command]
+pub fn play(
+    session: State<Mutex<Session>>,
+    engine: State<Engine>,
+    analysis: State<Analysis>,
+    store: State<Store>,
+    from: usize,
+    to: usize,
+    promote: Option<bool>, // asked to the player under the optional promotion rule
+) -> Result<GameStateView, String> {
+    analysis.stop();
+    let mut session = session.lock().unwrap();
+    if session.paused {
+        return Err("the game is paused".into());
+    }
+    let (expected, ply) = (session.expected_line().first().copied(), session.history.len());
+    session.play(Move { from, to, promote: promote.unwrap_or(true) })?;
+    // the pondering searched the reply the engine expected, it is of no use after another one
+    if session.result.is_some() || session.history.get(ply).map(|&(_, mov)| mov) != expected {
+        engine.stop_pondering();
+    }
+    correspondence::save(&store, &session);
+    Ok(session.view())
+}
+
+// the move of the opponent in a network game, with the thinking time measured by their clock so
+// that the lag is not charged to them
+#[tauri::command]
+pub fn play_network_move(
+    session: State<Mutex<Session>>,
+    analysis: State<Analysis>,
+    store: State<Store>,
+    from: usize,
+    to: usize,
+    promote: bool,
+    thinking_ms: u64,
+) -> Result<GameStateView, String> {
+    analysis.stop();
+    let mut session = session.lock().unwrap();
+    session.play_remote(Move { from, to, promote }, Duration::from_millis(thinking_ms))?;
+    correspondence::save(&store, &session);
+    Ok(session.view())
+}
+
+#[tauri::command]
+pub fn undo(session: State<Mutex<Session>>, engine: State<Engine>, analysis: State<Analysis>, store: State<Store>) -> GameStateView {
+    stop_engine(&engine, &analysis);
+    let mut session = session.lock().unwrap();
+    session.undo();
+    correspondence::save(&store, &session);
+    session.view()
+}
+
+#[tauri::command]
+pub fn set_premove(session: State<Mutex<Session>>, from: usize, to: usize, promote: Option<bool>) -> Result<GameStateView, String> {
+    let mut session = session.lock().unwrap();
+    session.set_premove(Move { from, to, promote: promote.unwrap_or(true) })?;
+    Ok(session.view())
+}
+
+#[tauri::command]
+pub fn cancel_premove(session: State<Mutex<Session>>) -> GameStateView {
+    let mut session = session.lock().unwrap();
+    session.cancel_premove();
+    session.view()
+}
+
+#[tauri::command]
+pub fn pause_game(session: State<Mutex<Session>>, engine: State<Engine>, analysis: State<Analysis>) -> GameStateView {
+    analysis.stop();
+    let mut session = session.lock().unwrap();
+    session.pause();
+    engine.hold(Hold::Game);
+    session.view()
+}
+
+#[tauri::command]
+pub fn resume_game(session: State<Mutex<Session>>, engine: State<Engine>) -> GameStateView {
+    let mut session = session.lock().unwrap();
+    session.resume();
+    engine.release(Hold::Game);
+    session.view()
+}
+
+// the search in progress waits while the app is in the background, except against the clock
+// where it would lose its time
+#[tauri::command]
+pub fn suspend_engine(session: State<Mutex<Session>>, engine: State<Engine>) {
+    if session.lock().unwrap().clock.is_none() {
+        engine.hold(Hold::Background);
+    }
+}
+
+#[tauri::command]
+pub fn resume_engine(engine: State<Engine>) {
+    engine.release(Hold::Background);
+}
+
+fn stop_engine(engine: &Engine, analysis: &Analysis) {
+    analysis.stop();
+    engine.send(Message::Stop);
+}
+
+// "player" defaults to the player to move
+#[tauri::command]
+pub fn resign(
+    session: State<Mutex<Session>>,
+    engine: State<Engine>,
+    analysis: State<Analysis>,
+    store: State<Store>,
+    player: Option<bool>,
+) -> Result<GameStateView, String> {
+    let mut session = session.lock().unwrap();
+    let player = player.unwrap_or(session.turn);
+    session.resign(player)?;
+    stop_engine(&engine, &analysis);
+    correspondence::save(&store, &session);
+    Ok(session.view())
+}
+
+#[tauri::command]
+pub fn offer_draw(session: State<Mutex<Session>>, player: Option<bool>) -> Result<GameStateView, String> {
+    let mut session = session.lock().unwrap();
+    let player = player.unwrap_or(session.turn);
+    session.offer_draw(player)?;
+    Ok(session.view())
+}
+
+#[tauri::command]
+pub fn respond_draw(
+    session: State<Mutex<Session>>,
+    engine: State<Engine>,
+    analysis: State<Analysis>,
+    store: State<Store>,
+    player: Option<bool>,
+    accept: bool,
+) -> Result<GameStateView, String> {
+    let mut session = session.lock().unwrap();
+    let player = player.unwrap_or(session.turn);
+    session.respond_draw(player, accept)?;
+    if accept {
+        stop_engine(&engine, &analysis);
+    }
+    correspondence::save(&store, &session);
+    Ok(session.view())
+}
+
+// called on both sides of a network game, for the request of the local player and for the one received
+#[tauri::command]
+pub fn request_takeback(session: State<Mutex<Session>>, player: bool, plies: usize) -> Result<GameStateView, String> {
+    let mut session = session.lock().unwrap();
+    session.request_takeback(player, plies)?;
+    Ok(session.view())
+}
+
+// an accepted takeback is returned to be sent to the opponent, who applies it with apply_takeback
+#[tauri::command]
+pub fn respond_takeback(
+    session: State<Mutex<Session>>,
+    engine: State<Engine>,
+    analysis: State<Analysis>,
+    store: State<Store>,
+    player: Option<bool>,
+    accept: bool,
+) -> Result<Option<Takeback>, String> {
+    let mut session = session.lock().unwrap();
+    let player = player.unwrap_or(session.turn);
+    let takeback = session.respond_takeback(player, accept)?;
+    if takeback.is_some() {
+        stop_engine(&engine, &analysis);
+        correspondence::save(&store, &session);
+    }
+    Ok(takeback)
+}
+
+#[tauri::command]
+pub fn apply_takeback(
+    session: State<Mutex<Session>>,
+    engine: State<Engine>,
+    analysis: State<Analysis>,
+    store: State<Store>,
+    takeback: Takeback,
+) -> Result<GameStateView, String> {
+    let mut session = session.lock().unwrap();
+    if session.takeback_request.is_none() {
+        return Err("no takeback has been requested".into());
+    }
+    stop_engine(&engine, &analysis);
+    session.take_back(takeback)?;
+    correspondence::save(&store, &session);
+    Ok(session.view())
+}
+
+#[tauri::command(async)]
+pub fn ai_play(
+    app: AppHandle,
+    session: State<Mutex<Session>>,
+    engine: State<Engine>,
+    analysis: State<Analysis>,
+    store: State<Store>,
+    mut level: AiLevel,
+) -> Result<GameStateView, String> {
+    analysis.stop();
+    // a change of level during the search restarts it with the new one, on the same table
+    let (pieces, played, turn, result) = loop {
+        // the lock is not held during the search
+        let (id, pieces, played, turn, limits, human_limits, control, drill) = {
+            let mut session = session.lock().unwrap();
+            if session.result.is_some() {
+                return Err("the game is over".into());
+            }
+            session.level = Some(level);
+            let limits = match level.respond_within_ms {
+                Some(ms) if session.clock.is_none() => {
+                    app.state::<Calibration>().limits(Duration::from_millis(ms)).with_rules(session.rules, session.ply())
+                }
+                _ => session.limits(level.depth),
+            };
+            let limits = match (level.min_think_ms, limits.time) {
+                // the pacing never makes the engine overstep its time budget
+                (Some(ms), Some(budget)) => limits.with_min_time(Duration::from_millis(ms).min(budget.soft)),
+                (Some(ms), None) => limits.with_min_time(Duration::from_millis(ms)),
+                (None, _) => limits,
+            };
+            let limits = timeman::limits(limits.with_endgame_style(level.endgame_style));
+            let control = Arc::new(SearchControl::default());
+            if session.paused {
+                control.pause();
+            }
+            // a human-like player scores the moves at the depth of the level, even against the clock
+            let human_limits = Limits::depth(timeman::depth(level.depth)).with_rules(session.rules, session.ply());
+            (session.id, session.pieces, session.played(), session.turn, limits, human_limits, control, session.drill.is_some())
+        };
+        // in a drill the engine resists as long as possible, however many pieces are on the board
+        let defense = if drill { endgame::solve(&pieces, turn) } else { None };
+        // the book has the first moves, except for a drill
+        let booked = if drill { None } else { assets::book_move(&pieces, turn, &limits.rules) };
+        let result = match (booked.or_else(|| defense.and_then(|solution| solution.search_result(turn, EndgameStyle::Optimal))), level.human) {
+            (Some(result), _) => result,
+            (None, Some(style)) => {
+                let budget = limits.time.map(|budget| budget.soft);
+                engine.play_human(pieces, turn, human_limits, style, budget, control).ok_or("the engine has stopped")?
+            }
+            (None, None) => {
+                let app = app.clone();
+                let on_progress = Box::new(move |progress: &Progress| {
+                    let _ = app.emit("thinking", progress);
+                    events::emit(&app, id, SessionEvent::Search { progress: *progress });
+                });
+                engine.search(pieces, played.clone(), turn, limits, control, on_progress).ok_or("the engine has stopped")?
+            }
+        };
+        match session.lock().unwrap().level {
+            Some(changed) if changed != level => level = changed,
+            _ => break (pieces, played, turn, result),
+        }
+    };
+    let mut session = session.lock().unwrap();
+    if session.pieces != pieces || session.history.len() != played.len() {
+        return Err("the position changed during the search".into());
+    }
+    let score = if turn { -result.score } else { result.score };
+    session.engine_scores.push((played.len(), score));
+    let scores: Vec<i32> = session.engine_scores.iter().map(|&(_, score)| score).collect();
+    let repetitions = played.iter().filter(|&&position| position == pieces).count();
+    let draws = level.conduct.draws(score, repetitions);
+    // in a drill the engine defends to the end
+    if level.conduct.resigns(&scores) && session.drill.is_none() {
+        session.resign(turn)?;
+    } else if draws && session.draw_offer == Some(!turn) {
+        session.respond_draw(turn, true)?;
+    } else {
+        if draws && session.draw_offer.is_none() {
+            session.offer_draw(turn)?;
+        }
+        session.play(result.mov)?;
+        // pondering on the position after the reply the engine expects, unless a premove
+        // has already answered
+        let premoved = session.turn == turn;
+        if let Some(&reply) = result.pv.get(1).filter(|_| level.ponder && timeman::ponder_allowed() && !shogi::deterministic() && session.result.is_none() && !premoved) {
+            engine.send(Message::Ponder {
+                pieces: shogi::play_move(&session.pieces, reply),
+                turn,
+                rules: session.rules(),
+                moves_left: session.moves_left().map(|left| left.saturating_sub(1)),
+                control: Arc::new(SearchControl::default()),
+            });
+        }
+        session.engine_pv = Some((played.len(), result.pv));
+    }
+    correspondence::save(&store, &session);
+    Ok(session.view())
+}
+
+// the level of the engine from now on, a search in progress starts again at this level
+#[tauri::command]
+pub fn set_ai_level(session: State<Mutex<Session>>, engine: State<Engine>, level: AiLevel) {
+    let mut session = session.lock().unwrap();
+    if session.level != Some(level) {
+        session.level = Some(level);
+        engine.send(Message::Stop);
+    }
+}
+
+// Ends the game as soon as the player to move runs out of time, the search is stopped and the
+// outcome saved. This includes correspondence deadlines that passed while the app was closed.
+// A finished game, however it ended, is also archived, counted when it was a drill and handed to
+// the review.
+pub fn watch_time(app: AppHandle) {
+    // whether the game was over at the previous check, so that its end is handled once
+    let mut over = false;
+    thread::spawn(move || loop {
+        {
+            let session = app.state::<Mutex<Session>>();
+            let mut session = session.lock().unwrap();
+            if session.check_time(correspondence::now_ms()) {
+                app.state::<Engine>().send(Message::Stop);
+                correspondence::save(&app.state::<Store>(), &session);
+                let _ = app.emit("game-over", session.result());
+            }
+            if session.archive_due() {
+                if let Some(game) = archive::record(&app.state::<Store>(), &mut session) {
+                    app.state::<Explorer>().archived(&game);
+                }
+            }
+            if session.result().is_some() && !over {
+                drill::record(&app.state::<Store>(), &mut session);
+                app.state::<Engine>().stop_pondering();
+                app.state::<Review>().game_over(&app, &session);
+            }
+            over = session.result().is_some();
+        }
+        thread::sleep(TIME_CHECK_INTERVAL);
+    });
+}
@@ -1,44 +1,289 @@
-use std::collections::HashMap;
-use std::time::Instant;
-use lazy_static::lazy_static;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Condvar, LazyLock, Mutex, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::endgame::{self, EndgameStyle};
+use crate::policy;
+use crate::timeman::TimeBudget;
+use crate::trace::{Exit, Trace};
 
 #[repr(u8)]
-#[derive(PartialEq, Eq, Clone, Copy, serde::Deserialize)]
-enum Kind { Chick, Elephant, Giraffe, Lion, Hen }
+#[derive(PartialEq, Eq, Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
+pub enum Kind { Chick, Elephant, Giraffe, Lion, Hen }
 
-#[derive(PartialEq, Eq, Clone, Copy, serde::Deserialize)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
 pub struct Piece {
-    kind: Kind,
-    position: u8,
-    owner: bool,
+    pub kind: Kind,
+    pub position: u8,
+    pub owner: bool,
 }
 
 pub type Pieces = [Piece; 8];
 
-#[derive(Clone, Copy, serde::Serialize)]
+// Geometry of the board. The squares are numbered row by row from the camp of the first player,
+// whose last rank is the first row. Everything it computes is constant, so that the default game
+// pays nothing for it. The rest of the app takes the board size from Standard, but the piece set
+// and the evaluation are still those of the 3x4 game: a larger board needs its own pieces.
+pub struct Board<const WIDTH: usize, const HEIGHT: usize>;
+
+impl<const WIDTH: usize, const HEIGHT: usize> Board<WIDTH, HEIGHT> {
+    pub const WIDTH: usize = WIDTH;
+    pub const HEIGHT: usize = HEIGHT;
+    pub const SQUARES: usize = WIDTH * HEIGHT;
+
+    // where a chick promotes and a lion wins
+    pub const fn last_rank(square: usize, owner: bool) -> bool {
+        if owner { square >= WIDTH * (HEIGHT - 1) && square < WIDTH * HEIGHT } else { square < WIDTH }
+    }
+
+    // the square one step away, None off the board
+    const fn step(square: usize, dx: i8, dy: i8) -> Option<u8> {
+        let x = (square % WIDTH) as i8 + dx;
+        let y = (square / WIDTH) as i8 + dy;
+        if 0 <= x && x < WIDTH as i8 && 0 <= y && y < HEIGHT as i8 {
+            Some((WIDTH as i8 * y + x) as u8)
+        } else {
+            None
+        }
+    }
+}
+
+// the game of the app
+pub type Standard = Board<3, 4>;
+
+// position of a piece in a player's hand
+pub const HAND: u8 = Standard::SQUARES as u8;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
 pub struct Move {
-    from: usize,
-    to: usize,
+    pub from: usize,
+    pub to: usize,
+    #[serde(default)]
+    pub promote: bool, // the chick becomes a hen, always on the last rank under the standard rules
 }
-#[derive(Clone, Copy, Debug)]
-enum Flag { Exact, Alpha, Beta }
 
-// transposition table
-type Table = HashMap<u64, (u8, i32, Flag)>;
+// Optional rules agreed before the game, the defaults are the standard rules
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Rules {
+    pub max_moves: Option<u32>, // the game is drawn once both players together have made this many moves
+    pub optional_promotion: bool, // a chick reaching the last rank may stay a chick
+    // the drop restrictions of shogi: no chick dropped on the last rank, where it could never
+    // move, and no chick dropped to checkmate the lion
+    pub shogi_drops: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+enum Flag { #[default] Exact, Alpha, Beta }
+
+// a power of two, 12 MB for the whole table
+const TABLE_BUCKETS: usize = 1 << 18;
+const BUCKET_SIZE: usize = 4;
+
+// depth 0 marks an empty entry, the search never stores the leaves
+#[derive(Clone, Copy, Default)]
+struct Entry {
+    check: u32, // the bits of the key that do not pick the bucket
+    score: i32,
+    depth: u8,
+    flag: Flag,
+    generation: u8,
+}
+
+// how well the table serves the search, counted since the start of the current search
+#[derive(Clone, Copy, Debug, Default, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableStats {
+    pub probes: u64,
+    pub hits: u64, // an entry of the position was found, its check bits matching
+    pub used: u64, // hits of the depth searched, whose score or bound served
+    pub collisions: u64, // probes whose bucket held other positions only, told apart by the check bits
+    pub replacements: u64, // entries of other positions overwritten
+    pub fill: u32, // per mille of the entries occupied, on a sample of the table
+}
+
+// Transposition table of fixed size, whose buckets hold a few entries. A new entry replaces the
+// same position or, failing that, the entry of the oldest generation and of the lowest depth.
+struct Table {
+    buckets: Vec<[Entry; BUCKET_SIZE]>,
+    stats: TableStats,
+}
+
+impl Table {
+    fn new() -> Self {
+        Table { buckets: vec![[Entry::default(); BUCKET_SIZE]; TABLE_BUCKETS], stats: TableStats::default() }
+    }
+
+    fn clear(&mut self) {
+        self.buckets.fill([Entry::default(); BUCKET_SIZE]);
+    }
+
+    // the bucket of a key and its check bits, the key is mixed first since the encodings of
+    // positions differ only in a few bits
+    fn locate(key: u64) -> (usize, u32) {
+        let mut hash = key.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        hash ^= hash >> 29;
+        ((hash as usize) & (TABLE_BUCKETS - 1), (hash >> 32) as u32)
+    }
 
-const PIECE_VALUE: [i32; 5] = [10, 30, 50, 10000, 70];
+    fn get(&mut self, key: u64) -> Option<(u8, i32, Flag)> {
+        let (bucket, check) = Table::locate(key);
+        let bucket = &self.buckets[bucket];
+        self.stats.probes += 1;
+        let found = bucket.iter().find(|entry| entry.depth > 0 && entry.check == check);
+        if found.is_some() {
+            self.stats.hits += 1;
+        } else if bucket.iter().any(|entry| entry.depth > 0) {
+            self.stats.collisions += 1;
+        }
+        found.map(|entry| (entry.depth, entry.score, entry.flag))
+    }
 
-lazy_static! {
-    static ref MOVE_DICT: [Vec<[i8; 2]>; 5] = [
-        vec![[0, 1]],  // chick
-        vec![[1, 1], [-1, 1], [1, -1], [-1, -1]], // elephant
-        vec![[0, 1], [1, 0], [0, -1], [-1, 0]], // giraffe
-        vec![[0, 1], [1, 0], [0, -1], [-1, 0], [1, 1], [-1, 1], [1, -1], [-1, -1]], // lion
-        vec![[0, 1], [1, 0], [0, -1], [-1, 0], [1, 1], [-1, 1]] // hen
-    ];
+    // the fill is sampled on the first thousand buckets
+    fn stats(&self) -> TableStats {
+        let sample = &self.buckets[..1000.min(TABLE_BUCKETS)];
+        let occupied = sample.iter().flatten().filter(|entry| entry.depth > 0).count();
+        TableStats { fill: (occupied * 1000 / (sample.len() * BUCKET_SIZE)) as u32, ..self.stats }
+    }
+
+    fn insert(&mut self, key: u64, depth: u8, score: i32, flag: Flag, generation: u32) {
+        let (bucket, check) = Table::locate(key);
+        let generation = generation as u8;
+        let bucket = &mut self.buckets[bucket];
+        let slot = match bucket.iter().position(|entry| entry.depth > 0 && entry.check == check) {
+            Some(slot) => slot,
+            None => (0..BUCKET_SIZE).min_by_key(|&slot| {
+                let entry = bucket[slot];
+                let age = generation.wrapping_sub(entry.generation) as i32;
+                if entry.depth == 0 { i32::MIN } else { entry.depth as i32 - 8 * age }
+            }).unwrap(),
+        };
+        if bucket[slot].depth > 0 && bucket[slot].check != check {
+            self.stats.replacements += 1;
+        }
+        bucket[slot] = Entry { check, score, depth, flag, generation };
+    }
 }
 
-fn encode_pieces(pieces: &Pieces, turn: bool) -> u64 {
+pub const PIECE_VALUE: [i32; 5] = [10, 30, 50, 10000, 70];
+
+// Weights of the evaluation, the defaults are the built-in evaluation.
+// Square bonuses are indexed by kind then square, from the point of view of the first player.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct EvalParams {
+    pub piece_value: [i32; 5],
+    pub square_bonus: [[i32; Standard::SQUARES]; 5],
+    pub mobility: i32, // per square controlled and not occupied by an own piece
+    pub lion_safety: i32, // penalty per square next to the lion attacked by the opponent
+}
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        EvalParams { piece_value: PIECE_VALUE, square_bonus: [[0; Standard::SQUARES]; 5], mobility: 1, lion_safety: 0 }
+    }
+}
+
+// steps of each kind for the second player, the first player's are the opposite
+const MOVE_DICT: [&[[i8; 2]]; 5] = [
+    &[[0, 1]],  // chick
+    &[[1, 1], [-1, 1], [1, -1], [-1, -1]], // elephant
+    &[[0, 1], [1, 0], [0, -1], [-1, 0]], // giraffe
+    &[[0, 1], [1, 0], [0, -1], [-1, 0], [1, 1], [-1, 1], [1, -1], [-1, -1]], // lion
+    &[[0, 1], [1, 0], [0, -1], [-1, 0], [1, 1], [-1, 1]] // hen
+];
+
+// squares a piece reaches in one step, the first "count" of "squares"
+#[derive(Clone, Copy)]
+struct Destinations {
+    squares: [u8; 8],
+    count: u8,
+}
+
+// indexed by kind, owner and square, computed at compile time
+static DESTINATIONS: [[[Destinations; Standard::SQUARES]; 2]; 5] = destinations::<3, 4, { Standard::SQUARES }>();
+
+// "SQUARES" is the number of squares of the board, which a generic array size cannot compute yet
+const fn destinations<const WIDTH: usize, const HEIGHT: usize, const SQUARES: usize>() -> [[[Destinations; SQUARES]; 2]; 5] {
+    assert!(SQUARES == Board::<WIDTH, HEIGHT>::SQUARES);
+    let mut table = [[[Destinations { squares: [0; 8], count: 0 }; SQUARES]; 2]; 5];
+    let mut kind = 0;
+    while kind < 5 {
+        let mut owner = 0;
+        while owner < 2 {
+            let mut square = 0;
+            while square < SQUARES {
+                let entry = &mut table[kind][owner][square];
+                let mut i = 0;
+                while i < MOVE_DICT[kind].len() {
+                    let [dx, dy] = MOVE_DICT[kind][i];
+                    let (dx, dy) = if owner == 1 { (dx, dy) } else { (-dx, -dy) };
+                    if let Some(destination) = Board::<WIDTH, HEIGHT>::step(square, dx, dy) {
+                        entry.squares[entry.count as usize] = destination;
+                        entry.count += 1;
+                    }
+                    i += 1;
+                }
+                square += 1;
+            }
+            owner += 1;
+        }
+        kind += 1;
+    }
+    table
+}
+
+// squares the piece reaches in one step, nothing for a piece in hand
+fn destinations_of(piece: Piece) -> &'static [u8] {
+    if piece.position == HAND {
+        return &[];
+    }
+    let entry = &DESTINATIONS[piece.kind as usize][piece.owner as usize][piece.position as usize];
+    &entry.squares[..entry.count as usize]
+}
+
+// a search keeps the parameters it started with when they are replaced
+static EVAL_PARAMS: LazyLock<RwLock<Arc<EvalParams>>> = LazyLock::new(RwLock::default);
+
+pub fn eval_params() -> Arc<EvalParams> {
+    EVAL_PARAMS.read().unwrap().clone()
+}
+
+pub fn set_eval_params(params: EvalParams) {
+    *EVAL_PARAMS.write().unwrap() = Arc::new(params);
+}
+
+// Strict deterministic mode, for the test suites and for reproducing bug reports: the same
+// position gives the same move, score and variation whatever the timing and the machine.
+// A time budget gives way to a fixed depth, and every search starts afresh, without hint or table.
+static DETERMINISTIC: AtomicBool = AtomicBool::new(false);
+const DETERMINISTIC_DEPTH: u8 = 8;
+
+pub fn deterministic() -> bool {
+    DETERMINISTIC.load(Ordering::Relaxed)
+}
+
+#[tauri::command]
+pub fn set_deterministic(enabled: bool) {
+    DETERMINISTIC.store(enabled, Ordering::Relaxed);
+}
+
+pub fn initial_pieces() -> Pieces {
+    let piece = |kind, position, owner| Piece { kind, position, owner };
+    [
+        piece(Kind::Elephant, 9, false),
+        piece(Kind::Lion, 10, false),
+        piece(Kind::Giraffe, 11, false),
+        piece(Kind::Chick, 7, false),
+        piece(Kind::Elephant, 2, true),
+        piece(Kind::Lion, 1, true),
+        piece(Kind::Giraffe, 0, true),
+        piece(Kind::Chick, 4, true),
+    ]
+}
+
+pub fn encode_pieces(pieces: &Pieces, turn: bool) -> u64 {
     let mut encoding = 0;
     for piece in pieces {
         encoding |= piece.position as u64;
@@ -59,40 +304,170 @@ fn encode_pieces(pieces: &Pieces, turn: bool) -> u64 {
     encoding
 }
 
-fn possible_moves(pieces: &Pieces, turn: bool) -> Vec<Move> {
-    let mut result = vec![];
-    let mut board = [0u8; 12];
+// the inverse of encode_pieces, None if the encoding does not describe a position
+pub fn decode_pieces(encoding: u64) -> Option<(Pieces, bool)> {
+    let mut pieces = initial_pieces();
+    for (i, piece) in pieces.iter_mut().enumerate() {
+        let bits = (encoding >> (5 * (8 - i))) & 31;
+        piece.position = (bits & 15) as u8;
+        piece.owner = bits & 16 != 0;
+    }
+    if encoding & 2 != 0 {
+        pieces[3].kind = Kind::Hen;
+    }
+    if encoding & 4 != 0 {
+        pieces[7].kind = Kind::Hen;
+    }
+    let valid = encoding >> 45 == 0 && is_valid(&pieces);
+    valid.then_some((pieces, encoding & 1 != 0))
+}
+
+// no two pieces on the same square, no lion or hen in hand
+pub fn is_valid(pieces: &Pieces) -> bool {
+    pieces.iter().enumerate().all(|(i, piece)| {
+        piece.position <= HAND
+            && (piece.position == HAND || pieces[..i].iter().all(|other| other.position != piece.position))
+            && !(piece.position == HAND && matches!(piece.kind, Kind::Hen | Kind::Lion))
+    })
+}
+
+// for the positions received by the commands, the tables of moves are indexed by the squares
+pub fn validate(pieces: &Pieces) -> Result<(), String> {
+    if is_valid(pieces) { Ok(()) } else { Err("invalid position".into()) }
+}
+
+// Most moves a player can have: 36 on the board for a lion, two hens, two giraffes and two
+// elephants, 30 drops of three kinds on ten empty squares, and the two declined promotions.
+pub const MAX_MOVES: usize = 72;
+
+// the moves of a position, on the stack since they are generated at every node of the search
+#[derive(Clone, Copy)]
+pub struct MoveList {
+    moves: [Move; MAX_MOVES],
+    len: usize,
+}
+
+impl MoveList {
+    pub fn new() -> Self {
+        MoveList { moves: [Move { from: 0, to: 0, promote: false }; MAX_MOVES], len: 0 }
+    }
+
+    pub fn push(&mut self, mov: Move) {
+        debug_assert!(self.len < MAX_MOVES, "more than {MAX_MOVES} moves");
+        self.moves[self.len] = mov;
+        self.len += 1;
+    }
+
+    pub fn retain(&mut self, mut keep: impl FnMut(&Move) -> bool) {
+        let mut len = 0;
+        for i in 0..self.len {
+            if keep(&self.moves[i]) {
+                self.moves[len] = self.moves[i];
+                len += 1;
+            }
+        }
+        self.len = len;
+    }
+}
+
+impl Default for MoveList {
+    fn default() -> Self {
+        MoveList::new()
+    }
+}
+
+impl Deref for MoveList {
+    type Target = [Move];
+    fn deref(&self) -> &[Move] {
+        &self.moves[..self.len]
+    }
+}
+
+impl DerefMut for MoveList {
+    fn deref_mut(&mut self) -> &mut [Move] {
+        &mut self.moves[..self.len]
+    }
+}
+
+impl IntoIterator for MoveList {
+    type Item = Move;
+    type IntoIter = std::iter::Take<std::array::IntoIter<Move, MAX_MOVES>>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.moves.into_iter().take(self.len)
+    }
+}
+
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = &'a Move;
+    type IntoIter = std::slice::Iter<'a, Move>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl Extend<Move> for MoveList {
+    fn extend<T: IntoIterator<Item = Move>>(&mut self, moves: T) {
+        for mov in moves {
+            self.push(mov);
+        }
+    }
+}
+
+// owner of the piece on each square: 0 when empty, 1 for the first player, 2 for the second
+fn occupancy(pieces: &Pieces) -> [u8; Standard::SQUARES] {
+    let mut board = [0u8; Standard::SQUARES];
     for piece in pieces {
-        if piece.position < 12 {
+        if piece.position < HAND {
             board[piece.position as usize] = if piece.owner { 2 } else { 1 };
         }
     }
+    board
+}
 
+// moves of the pieces on the board, the captures or the quiet moves
+fn board_moves(pieces: &Pieces, turn: bool, capturing: bool) -> MoveList {
+    let mut result = MoveList::new();
+    let board = occupancy(pieces);
+    let (own, other) = if turn { (2, 1) } else { (1, 2) };
     for (i, &piece) in pieces.iter().enumerate() {
-        if piece.owner != turn {
+        if piece.owner != turn || piece.position == HAND {
             continue;
         }
-        if piece.position == 12 {
-            if i < 4 || pieces[i - 4].owner != piece.owner || pieces[i - 4].position < 12 {
-                for j in 0..12 {
-                    if board[j] == 0 {
-                        result.push(Move {from: i, to: j})
-                    }
-                }
+        for &index in destinations_of(piece) {
+            let index = index as usize;
+            if board[index] != own && (board[index] == other) == capturing {
+                let promote = piece.kind == Kind::Chick && Standard::last_rank(index, turn);
+                result.push(Move {from: i, to: index, promote})
             }
-        } else {
-            let x = piece.position as i8 % 3;
-            let y = piece.position as i8 / 3;
-            let owner = if piece.owner { 2 } else { 1 };
-            for &[dx, dy] in &MOVE_DICT[piece.kind as usize] {
-                let [dx, dy] = if turn { [dx, dy] } else { [-dx, -dy] };
-                let x2 = x + dx;
-                let y2 = y + dy;
-                if x2 >= 0 && x2 < 3 && y2 >= 0 && y2 < 4 {
-                    let index = (3 * y2 + x2) as usize;
-                    if board[index] != owner {
-                        result.push(Move {from: i, to: index})
-                    }
+        }
+    }
+    result
+}
+
+// The move generation comes in stages, captures, drops and quiet moves, for the callers that
+// only need some of them. possible_moves chains the three.
+
+// captures, the most valuable victim first and, for a same victim, the least valuable attacker first
+pub fn captures(pieces: &Pieces, turn: bool) -> MoveList {
+    let mut result = board_moves(pieces, turn, true);
+    let victim = |mov: &Move| pieces.iter().find(|piece| piece.position as usize == mov.to).map_or(0, |piece| PIECE_VALUE[piece.kind as usize]);
+    result.sort_by_key(|mov| (-victim(mov), PIECE_VALUE[pieces[mov.from].kind as usize]));
+    result
+}
+
+// drops of one piece of each kind in hand on the empty squares
+pub fn drops(pieces: &Pieces, turn: bool) -> MoveList {
+    let mut result = MoveList::new();
+    let board = occupancy(pieces);
+    for (i, &piece) in pieces.iter().enumerate() {
+        if piece.owner != turn || piece.position != HAND {
+            continue;
+        }
+        // a twin in hand already drops this kind
+        if i < 4 || pieces[i - 4].owner != piece.owner || pieces[i - 4].position < HAND {
+            for (j, &square) in board.iter().enumerate() {
+                if square == 0 {
+                    result.push(Move {from: i, to: j, promote: false})
                 }
             }
         }
@@ -100,51 +475,157 @@ fn possible_moves(pieces: &Pieces, turn: bool) -> Vec<Move> {
     result
 }
 
-fn play_move(pieces: &Pieces, mov: Move) -> Pieces {
+pub fn quiet_moves(pieces: &Pieces, turn: bool) -> MoveList {
+    board_moves(pieces, turn, false)
+}
+
+pub fn possible_moves(pieces: &Pieces, turn: bool) -> MoveList {
+    let mut result = captures(pieces, turn);
+    result.extend(drops(pieces, turn));
+    result.extend(quiet_moves(pieces, turn));
+    result
+}
+
+pub fn play_move(pieces: &Pieces, mov: Move) -> Pieces {
     let to = mov.to as u8;
-    let Piece {owner, kind, position} = pieces[mov.from as usize];
+    let Piece {owner, kind, position} = pieces[mov.from];
     let mut new_pieces = *pieces;
 
     if let Some(j) = pieces.iter().position(|p| p.position == to) {
-        new_pieces[j].position = 12;
+        new_pieces[j].position = HAND;
         new_pieces[j].owner = owner;
         if new_pieces[j].kind == Kind::Hen {
             new_pieces[j].kind = Kind::Chick;
         }  
     }
     new_pieces[mov.from].position = to;
-    if kind == Kind::Chick && position < 12 && (owner && to > 8 || !owner && to < 3) {
+    if kind == Kind::Chick && position < HAND && mov.promote {
         new_pieces[mov.from].kind = Kind::Hen;
     }
     new_pieces
 }
 
-fn evaluate_position(pieces: &Pieces) -> i32 {
+// the moves allowed by "rules", a promotion may be declined under the optional promotion rule
+pub fn legal_moves(pieces: &Pieces, turn: bool, rules: &Rules) -> MoveList {
+    let mut moves = possible_moves(pieces, turn);
+    if rules.shogi_drops {
+        moves.retain(|&mov| !forbidden_drop(pieces, mov));
+    }
+    if !rules.optional_promotion {
+        return moves;
+    }
+    let mut with_declined = MoveList::new();
+    for mov in moves {
+        with_declined.push(mov);
+        if mov.promote {
+            with_declined.push(Move { promote: false, ..mov });
+        }
+    }
+    with_declined
+}
+
+// a chick dropped on the last rank or giving a mate, the opponent's lion being captured
+// whatever the reply
+fn forbidden_drop(pieces: &Pieces, mov: Move) -> bool {
+    let piece = pieces[mov.from];
+    if piece.position != HAND || piece.kind != Kind::Chick {
+        return false;
+    }
+    let last_rank = Standard::last_rank(mov.to, piece.owner);
+    let after = play_move(pieces, mov);
+    let attacked = |pieces: &Pieces| is_attacked(pieces, lion(pieces, !piece.owner).position, piece.owner);
+    last_rank || attacked(&after) && possible_moves(&after, !piece.owner).into_iter().all(|reply| {
+        let after_reply = play_move(&after, reply);
+        !endgame::lost(&after_reply, piece.owner) && attacked(&after_reply)
+    })
+}
+
+// The legal move of the piece "from" to "to", "promote" only matters when the rules leave the
+// choice and the move promotes when it is not given.
+pub fn legal_move(pieces: &Pieces, rules: &Rules, from: usize, to: usize, promote: Option<bool>) -> Option<Move> {
+    let turn = pieces.get(from)?.owner;
+    let moves: Vec<Move> = legal_moves(pieces, turn, rules).into_iter().filter(|mov| mov.from == from && mov.to == to).collect();
+    moves.iter().find(|mov| promote.is_some_and(|promote| mov.promote == promote)).or(moves.first()).copied()
+}
+
+// squares controlled by a piece on the board, nothing for a piece in hand
+pub fn attacks(piece: Piece) -> impl Iterator<Item = u8> {
+    destinations_of(piece).iter().copied()
+}
+
+// is the square attacked by a piece of the player "by"
+pub fn is_attacked(pieces: &Pieces, square: u8, by: bool) -> bool {
+    pieces.iter().any(|&piece| piece.owner == by && attacks(piece).any(|s| s == square))
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HandSlot {
+    pub kind: Kind,
+    pub count: u8,
+    pub drop_index: usize, // index of the piece used when dropping this kind
+}
+
+// pieces in the hand of "owner" grouped by kind.
+// Two pieces of the same kind in a hand are always twins i and i+4, and possible_moves only
+// allows the drop of the lower index, so that is the one a drop consumes.
+pub fn hand(pieces: &Pieces, owner: bool) -> Vec<HandSlot> {
+    let mut slots: Vec<HandSlot> = vec![];
+    for (i, piece) in pieces.iter().enumerate() {
+        if piece.position != HAND || piece.owner != owner {
+            continue;
+        }
+        match slots.iter_mut().find(|slot| slot.kind == piece.kind) {
+            Some(slot) => {
+                slot.count += 1;
+                slot.drop_index = slot.drop_index.min(i);
+            }
+            None => slots.push(HandSlot { kind: piece.kind, count: 1, drop_index: i }),
+        }
+    }
+    slots.sort_by_key(|slot| slot.kind as u8);
+    slots
+}
+
+pub fn lion(pieces: &Pieces, owner: bool) -> &Piece {
+    &pieces[if owner { 5 } else { 1 }]
+}
+
+// the lion of "owner" stands on the last rank and cannot be captured
+pub fn lion_reached_camp(pieces: &Pieces, owner: bool) -> bool {
+    let position = lion(pieces, owner).position;
+    Standard::last_rank(position as usize, owner) && !is_attacked(pieces, position, !owner)
+}
+
+pub fn evaluate_position(pieces: &Pieces, params: &EvalParams) -> i32 {
     let mut result = 0;
   
-    let mut board = [0u8; 12];
+    let mut board = [0u8; Standard::SQUARES];
     for piece in pieces {
-        result += (if piece.owner {-1} else {1}) * PIECE_VALUE[piece.kind as usize];
-        if piece.position < 12 {
+        let sign = if piece.owner {-1} else {1};
+        result += sign * params.piece_value[piece.kind as usize];
+        if piece.position < HAND {
             board[piece.position as usize] = if piece.owner { 2 } else { 1 };
+            let square = if piece.owner { HAND - 1 - piece.position } else { piece.position };
+            result += sign * params.square_bonus[piece.kind as usize][square as usize];
+        }
+    }
+    if params.lion_safety != 0 {
+        for owner in [false, true] {
+            let sign = if owner {-1} else {1};
+            let around = attacks(Piece { kind: Kind::Lion, ..*lion(pieces, owner) });
+            let attackers = around.filter(|&square| is_attacked(pieces, square, !owner)).count() as i32;
+            result -= sign * params.lion_safety * attackers;
         }
     }
 
     for piece in pieces {
-        if piece.position < 12 {
+        if piece.position < HAND {
             let owner = if piece.owner {2} else {1};
             let dscore = if piece.owner {-1} else {1};
-            let x = piece.position as i8 % 3;
-            let y = piece.position as i8 / 3;
-            for &[dx, dy] in &MOVE_DICT[piece.kind as usize] {
-                let [dx, dy] = if piece.owner { [dx, dy] } else { [-dx, -dy] };
-                let x2 = x + dx;
-                let y2 = y + dy;
-                if x2 >= 0 && x2 < 3 && y2 >= 0 && y2 < 4 {
-                    let index = (3 * y2 + x2) as usize;
-                    if board[index] != owner {
-                        result += dscore;
-                    }
+            for &index in destinations_of(*piece) {
+                if board[index as usize] != owner {
+                    result += dscore * params.mobility;
                 }
             }
         }
@@ -152,132 +633,658 @@ fn evaluate_position(pieces: &Pieces) -> i32 {
     result
 }
 
-fn alphabeta(table: &mut Table, depth: u8, turn: bool, mut alpha: i32, mut beta: i32, pieces: Pieces) -> i32 {
-    let encoding = encode_pieces(&pieces, turn);
-    let alpha_orig = alpha;
-    let beta_orig = beta;
-    if let Some(&(depth2, score, flag)) = table.get(&encoding) {
-        if depth2 == depth {
-            match flag {
-                Flag::Exact => return score,
-                Flag::Alpha => alpha = alpha.max(score),
-                Flag::Beta => beta = beta.min(score),
+// shared with the thread running a search to stop or pause it
+#[derive(Default)]
+pub struct SearchControl {
+    stop: AtomicBool,
+    paused: Mutex<bool>,
+    resumed: Condvar,
+}
+
+impl SearchControl {
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.resume();
+    }
+
+    pub fn stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    pub fn pause(&self) {
+        *self.paused.lock().unwrap() = true;
+        self.resumed.notify_all();
+    }
+
+    pub fn resume(&self) {
+        *self.paused.lock().unwrap() = false;
+        self.resumed.notify_all();
+    }
+
+    // blocks as long as the search is paused, returns the time spent waiting
+    fn wait_while_paused(&self) -> Duration {
+        let start = Instant::now();
+        let mut paused = self.paused.lock().unwrap();
+        while *paused {
+            paused = self.resumed.wait(paused).unwrap();
+        }
+        start.elapsed()
+    }
+
+    // waits for "duration" of the time not paused, a stop ends the wait at once
+    pub fn sleep(&self, duration: Duration) {
+        let mut left = duration;
+        let mut paused = self.paused.lock().unwrap();
+        while !self.stopped() && (*paused || !left.is_zero()) {
+            if *paused {
+                paused = self.resumed.wait(paused).unwrap();
+            } else {
+                let start = Instant::now();
+                paused = self.resumed.wait_timeout(paused, left).unwrap().0;
+                left = left.saturating_sub(start.elapsed());
             }
         }
-        if alpha >= beta {
-            return score;
-        }
-    }
-    if depth == 0 {
-        return evaluate_position(&pieces)
-    } else if pieces[1].position == 12 { // white Lion has been captured
-        return -100000-(depth as i32)
-    } else if pieces[5].position == 12 { // black Lion has been captured
-        return 100000+(depth as i32)
-    } else if turn && pieces[5].position > 8 { // black Lion has reached the enemy camp
-        return -100000-(depth as i32)
-    } else if !turn && pieces[1].position < 3 { // white Lion has reached the enemy camp
-        return 100000+(depth as i32)
-    }
-    
-    if !turn {  // maximizing
-        let mut best_score = i32::MIN;
-        for mov in possible_moves(&pieces, turn) {
-            let new_pieces = play_move(&pieces, mov);
-            let score = alphabeta(table, depth - 1, true, alpha, beta, new_pieces);
-            best_score = best_score.max(score);
-            alpha = alpha.max(score);
-            if alpha >= beta {
-                break
+    }
+}
+
+// What the previous search expected from the position: the principal variation after the reply
+// it predicted, and its score. The expected move is searched first and the expected score gives
+// the first window.
+#[derive(Clone, Debug)]
+pub struct Hint {
+    pub pieces: Pieces,
+    pub turn: bool,
+    pub mov: Option<Move>,
+    pub score: i32,
+}
+
+impl Hint {
+    // after the move played and the reply expected, None when the variation stops before
+    pub fn after_reply(pieces: &Pieces, turn: bool, result: &SearchResult) -> Option<Hint> {
+        let (&mov, &reply) = (result.pv.first()?, result.pv.get(1)?);
+        Some(Hint {
+            pieces: play_move(&play_move(pieces, mov), reply),
+            turn,
+            mov: result.pv.get(2).copied(),
+            score: result.score,
+        })
+    }
+}
+
+pub struct Search {
+    table: Table,
+    generation: u32, // bumped on each move the search is reused for
+    control: Arc<SearchControl>,
+    nodes: u64,
+    deadline: Option<Instant>, // the search stops itself at this point
+    node_limit: Option<u64>, // or when its node count reaches this one
+    paused: Duration, // total time spent paused
+    params: Arc<EvalParams>,
+    rules: Rules,
+    moves_left: Option<u32>, // before the game is drawn by the move limit, from the root
+    root_depth: u8,
+    completing: bool, // the first iteration of best_move, which a stop does not interrupt
+    hint: Option<Hint>, // for the next call to best_move
+    trace: Option<Trace>, // opt-in, it slows the search down
+}
+
+// the time budget, if any, takes precedence over the depth
+#[derive(Clone, Copy, Debug)]
+pub struct Limits {
+    pub depth: u8,
+    pub time: Option<TimeBudget>,
+    pub min_time: Option<Duration>, // the move is never delivered earlier
+    pub endgame_style: EndgameStyle, // how a position solved by the endgame solver is played
+    pub rules: Rules,
+    pub moves_left: Option<u32>, // before the game is drawn by the move limit
+    pub nodes: Option<u64>, // the search stops after this many nodes, whatever the time it takes
+}
+
+// deepest iteration of a time-managed search
+const MAX_DEPTH: u8 = 40;
+// the endgame solver takes up to a few hundred milliseconds, it is left to the longer searches
+const SOLVER_MIN_DEPTH: u8 = 8;
+const SOLVER_MIN_TIME: Duration = Duration::from_millis(300);
+// half-width of the window searched first around an expected score
+const ASPIRATION_WINDOW: i32 = 30;
+
+impl Limits {
+    pub fn depth(depth: u8) -> Self {
+        Limits {
+            depth,
+            time: None,
+            min_time: None,
+            endgame_style: EndgameStyle::Optimal,
+            rules: Rules::default(),
+            moves_left: None,
+            nodes: None,
+        }
+    }
+
+    // a search whose course does not depend on the speed of the machine, for benchmarks
+    pub fn nodes(nodes: u64) -> Self {
+        Limits { nodes: Some(nodes), ..Limits::depth(MAX_DEPTH) }
+    }
+
+    pub fn time(budget: TimeBudget) -> Self {
+        Limits { depth: MAX_DEPTH, time: Some(budget), ..Limits::depth(MAX_DEPTH) }
+    }
+
+    pub fn with_min_time(self, min_time: Duration) -> Self {
+        Limits { min_time: Some(min_time), ..self }
+    }
+
+    pub fn with_endgame_style(self, endgame_style: EndgameStyle) -> Self {
+        Limits { endgame_style, ..self }
+    }
+
+    // the rules of a game where "ply" moves have been played
+    pub fn with_rules(self, rules: Rules, ply: usize) -> Self {
+        let moves_left = rules.max_moves.map(|max| max.saturating_sub(ply as u32));
+        Limits { rules, moves_left, ..self }
+    }
+}
+
+// reported after each completed iteration of the iterative deepening
+#[derive(Clone, Copy, Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Progress {
+    pub depth: u8,
+    pub target_depth: u8,
+    pub nodes: u64,
+    pub elapsed_ms: u64,
+    pub eta_ms: u64,
+    pub progress: f64, // between 0 and 1
+    pub table: TableStats,
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct SearchResult {
+    #[serde(rename = "move")]
+    pub mov: Move,
+    pub score: i32, // from the point of view of the first player
+    pub depth: u8,
+    pub pv: Vec<Move>, // principal variation, starting with "mov"
+}
+
+impl Default for Search {
+    fn default() -> Self {
+        Search::new(Arc::default())
+    }
+}
+
+impl Search {
+    pub fn new(control: Arc<SearchControl>) -> Self {
+        Search {
+            table: Table::new(),
+            generation: 0,
+            control,
+            nodes: 0,
+            deadline: None,
+            node_limit: None,
+            paused: Duration::ZERO,
+            params: eval_params(),
+            rules: Rules::default(),
+            moves_left: None,
+            root_depth: 0,
+            completing: false,
+            hint: None,
+            trace: None,
+        }
+    }
+
+    // a search with its own evaluation weights, used to compare weights against each other
+    pub fn with_params(control: Arc<SearchControl>, params: Arc<EvalParams>) -> Self {
+        Search { params, ..Search::new(control) }
+    }
+
+    pub fn stopped(&self) -> bool {
+        self.control.stopped()
+    }
+
+    pub fn control(&self) -> &SearchControl {
+        &self.control
+    }
+
+    // whether the search gives up on its current iteration
+    fn interrupted(&self) -> bool {
+        self.stopped() && !self.completing
+    }
+
+    // visited since the search was created
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    // The search goes on with the next move under a new control and keeps its table, whose
+    // entries stay valid from one position to the next. An update of the evaluation weights
+    // drops all of them.
+    pub fn resume(&mut self, control: Arc<SearchControl>) {
+        self.control = control;
+        self.next_generation();
+        let params = eval_params();
+        if !Arc::ptr_eq(&params, &self.params) {
+            self.table.clear();
+            self.params = params;
+        }
+    }
+
+    // Entries are aged by generation rather than cleared, for a new move or a new game, the
+    // older ones being replaced first.
+    pub fn next_generation(&mut self) {
+        self.generation += 1;
+    }
+
+    pub fn start_trace(&mut self) {
+        self.trace = Some(Trace::default());
+    }
+
+    pub fn take_trace(&mut self) -> Option<Trace> {
+        self.trace.take()
+    }
+
+    pub fn set_hint(&mut self, hint: Hint) {
+        self.hint = Some(hint);
+    }
+
+    // for the searches that do not go through best_move, the table does not carry over another rule set
+    pub fn set_rules(&mut self, rules: Rules, moves_left: Option<u32>) {
+        if rules != self.rules {
+            self.table.clear();
+        }
+        self.rules = rules;
+        self.moves_left = moves_left;
+    }
+
+    // score of the position from the point of view of the first player, None if the search has been stopped
+    pub fn evaluate(&mut self, pieces: &Pieces, turn: bool, depth: u8) -> Option<i32> {
+        self.root_depth = depth;
+        let score = self.alphabeta(depth, turn, i32::MIN, i32::MAX, *pieces, &mut vec![]);
+        if self.stopped() { None } else { Some(score) }
+    }
+
+    // The best move among "moves", None if no move beats the initial bounds. Once the search is
+    // interrupted only the moves searched to the end count.
+    fn search_root(&mut self, moves: &[(Move, Pieces)], depth: u8, turn: bool, (mut alpha, mut beta): (i32, i32)) -> Option<SearchResult> {
+        let mut best_move = None;
+        let mut pv = vec![];
+        self.root_depth = depth;
+        for &(mov, new_pieces) in moves {
+            pv.clear();
+            let score = self.alphabeta(depth - 1, !turn, alpha, beta, new_pieces, &mut pv);
+            if self.interrupted() {
+                break;
+            }
+            if !turn && score > alpha || turn && score < beta {
+                if turn { beta = score } else { alpha = score }
+                pv.insert(0, mov);
+                best_move = Some(SearchResult { mov, score, depth, pv: pv.clone() });
             }
         }
-        let flag = 
-            if best_score <= alpha_orig {
-                Flag::Beta
-            } else if best_score >= beta{
-                Flag::Alpha
-            } else {
-                Flag::Exact
-            };
-        table.insert(encoding, (depth, best_score, flag));
-        alpha
-    } else {   // minimizing
-        let mut best_score = i32::MAX;
-        for mov in possible_moves(&pieces, turn) {
-            let new_pieces = play_move(&pieces, mov);
-            let score = alphabeta(table, depth - 1, false, alpha, beta, new_pieces);
-            best_score = best_score.min(score);
-            beta = beta.min(score);
+        best_move
+    }
+
+    fn table_answer(&mut self, score: i32) -> i32 {
+        if let Some(trace) = &mut self.trace {
+            trace.table_hit = true;
+        }
+        score
+    }
+
+    // "pv" receives the principal variation when the score lies between alpha and beta
+    fn alphabeta(&mut self, depth: u8, turn: bool, alpha: i32, beta: i32, pieces: Pieces, pv: &mut Vec<Move>) -> i32 {
+        let Some(index) = self.trace.as_mut().and_then(|trace| trace.enter(encode_pieces(&pieces, turn), depth, alpha, beta)) else {
+            return self.search_node(depth, turn, alpha, beta, pieces, pv);
+        };
+        let score = self.search_node(depth, turn, alpha, beta, pieces, pv);
+        let stopped = self.stopped();
+        let trace = self.trace.as_mut().unwrap();
+        let (fails_high, fails_low) = (score >= beta, score <= alpha);
+        let exit = match () {
+            _ if stopped => Exit::Stopped,
+            _ if trace.table_hit => Exit::Table,
+            _ if depth == 0 => Exit::Leaf,
+            _ if endgame::lost(&pieces, turn) => Exit::Terminal,
+            _ if if turn { fails_low } else { fails_high } => Exit::Cutoff,
+            _ if fails_high || fails_low => Exit::FailLow,
+            _ => Exit::Exact,
+        };
+        trace.exit(index, score, exit);
+        score
+    }
+
+    fn search_node(&mut self, depth: u8, turn: bool, mut alpha: i32, mut beta: i32, pieces: Pieces, pv: &mut Vec<Move>) -> i32 {
+        if self.interrupted() {
+            return 0;
+        }
+        self.nodes += 1;
+        if self.node_limit.is_some_and(|limit| self.nodes >= limit) {
+            self.control.stop();
+        }
+        if self.nodes & 1023 == 0 {
+            // the time spent paused does not count against the deadline
+            let waited = self.control.wait_while_paused();
+            self.paused += waited;
+            if let Some(deadline) = &mut self.deadline {
+                *deadline += waited;
+                if Instant::now() >= *deadline {
+                    self.control.stop();
+                }
+            }
+        }
+        if self.interrupted() {
+            return 0;
+        }
+        // the moves left matter to the score when the limit falls within the depth searched
+        let moves_left = self.moves_left.map(|left| left.saturating_sub((self.root_depth - depth) as u32));
+        let mut encoding = encode_pieces(&pieces, turn);
+        if let Some(left) = moves_left.filter(|&left| left <= depth as u32) {
+            encoding |= (left as u64) << 48;
+        }
+        let alpha_orig = alpha;
+        let beta_orig = beta;
+        if let Some((depth2, score, flag)) = self.table.get(encoding) {
+            if depth2 == depth {
+                self.table.stats.used += 1;
+                match flag {
+                    Flag::Exact => return self.table_answer(score),
+                    Flag::Alpha => alpha = alpha.max(score),
+                    Flag::Beta => beta = beta.min(score),
+                }
+            }
             if alpha >= beta {
-                break
+                return self.table_answer(score);
             }
         }
-        let flag = 
-            if best_score >= beta_orig {
-                Flag::Alpha
-            } else if best_score <= alpha {
-                Flag::Beta
-            } else {
-                Flag::Exact
-            };
-        table.insert(encoding, (depth, best_score, flag));
-        beta
+        if moves_left == Some(0) && !endgame::lost(&pieces, turn) { // drawn by the move limit
+            return 0
+        }
+        if depth == 0 {
+            return evaluate_position(&pieces, &self.params)
+        } else if pieces[1].position == HAND { // white Lion has been captured
+            return -100000-(depth as i32)
+        } else if pieces[5].position == HAND { // black Lion has been captured
+            return 100000+(depth as i32)
+        } else if turn && Standard::last_rank(pieces[5].position as usize, true) { // black Lion has reached the enemy camp
+            return -100000-(depth as i32)
+        } else if !turn && Standard::last_rank(pieces[1].position as usize, false) { // white Lion has reached the enemy camp
+            return 100000+(depth as i32)
+        }
+
+        let mut child_pv = vec![];
+        let mut moves = legal_moves(&pieces, turn, &self.rules);
+        policy::WEIGHTS.order(&pieces, &mut moves);
+        if !turn {  // maximizing
+            let mut best_score = i32::MIN;
+            for mov in moves {
+                let new_pieces = play_move(&pieces, mov);
+                child_pv.clear();
+                let score = self.alphabeta(depth - 1, true, alpha, beta, new_pieces, &mut child_pv);
+                best_score = best_score.max(score);
+                if score > alpha {
+                    alpha = score;
+                    pv.clear();
+                    pv.push(mov);
+                    pv.extend_from_slice(&child_pv);
+                }
+                if alpha >= beta {
+                    break
+                }
+            }
+            let flag =
+                if best_score <= alpha_orig {
+                    Flag::Beta
+                } else if best_score >= beta{
+                    Flag::Alpha
+                } else {
+                    Flag::Exact
+                };
+            if !self.interrupted() {
+                self.table.insert(encoding, depth, best_score, flag, self.generation);
+            }
+            alpha
+        } else {   // minimizing
+            let mut best_score = i32::MAX;
+            for mov in moves {
+                let new_pieces = play_move(&pieces, mov);
+                child_pv.clear();
+                let score = self.alphabeta(depth - 1, false, alpha, beta, new_pieces, &mut child_pv);
+                best_score = best_score.min(score);
+                if score < beta {
+                    beta = score;
+                    pv.clear();
+                    pv.push(mov);
+                    pv.extend_from_slice(&child_pv);
+                }
+                if alpha >= beta {
+                    break
+                }
+            }
+            let flag =
+                if best_score >= beta_orig {
+                    Flag::Alpha
+                } else if best_score <= alpha {
+                    Flag::Beta
+                } else {
+                    Flag::Exact
+                };
+            if !self.interrupted() {
+                self.table.insert(encoding, depth, best_score, flag, self.generation);
+            }
+            beta
+        }
     }
 }
 
 
-#[tauri::command(async)]
-pub fn shogi_ai(pieces: Pieces, played: Vec<Pieces>, depth: u8, turn: bool) -> Move {
-    let mut alpha = i32::MIN;
-    let mut beta = i32::MAX;
-    let mut table: Table = HashMap::new();
+// estimated time to complete the depths following "depth", assuming each depth costs
+// "branching" times the previous one
+fn estimate_remaining(last_depth_ms: f64, branching: f64, depth: u8, target_depth: u8) -> f64 {
+    let mut eta = 0.0;
+    let mut time = last_depth_ms;
+    for _ in depth..target_depth {
+        time *= branching;
+        eta += time;
+    }
+    eta
+}
+
+// Iterative deepening within the limits, "on_progress" is called after each iteration.
+// When the limits are reached before "min_time", the search goes on deeper until then.
+// A fixed-depth search keeps its move so that its strength does not depend on the pacing,
+// the extra iterations only refine the principal variation and the score.
+// None when the player to move has no legal move.
+pub fn best_move(
+    search: &mut Search,
+    pieces: &Pieces,
+    played: &[Pieces],
+    limits: Limits,
+    turn: bool,
+    on_progress: &mut dyn FnMut(&Progress),
+) -> Option<SearchResult> {
+    let strict = deterministic();
+    let limits = match limits.time {
+        Some(_) if strict => Limits { time: None, depth: DETERMINISTIC_DEPTH, ..limits },
+        _ => limits,
+    };
+    if strict {
+        search.table.clear();
+        search.hint = None;
+    }
+    search.deadline = None;
+    search.node_limit = limits.nodes.map(|nodes| search.nodes + nodes);
+    search.table.stats = TableStats::default();
+    search.set_rules(limits.rules, limits.moves_left);
+    let paused_before = search.paused;
+    let target_depth = limits.depth.max(1);
+    let min_time = limits.min_time.unwrap_or(Duration::ZERO);
 
     let start = Instant::now();
 
+    // the hint only holds for the position it was made for
+    let hint = search.hint.take().filter(|hint| hint.pieces == *pieces && hint.turn == turn);
+    let mut moves = legal_moves(pieces, turn, &limits.rules);
+    if let Some(i) = hint.as_ref().and_then(|hint| moves.iter().position(|&mov| Some(mov) == hint.mov)) {
+        moves[..=i].rotate_right(1);
+    }
     let (played_twice, not_played_twice): (Vec<_>, Vec<_>) =
-        possible_moves(&pieces, turn)
+        moves
             .iter()
-            .map(|mov| (*mov, play_move(&pieces, *mov)))
+            .map(|mov| (*mov, play_move(pieces, *mov)))
             .partition(|(_, pieces)|
                 played.iter().filter(|&ps| ps == pieces).count() >= 1
             );
+    let mut guess = hint.map(|hint| hint.score);
 
+    // a proven result replaces the search when the solver is affordable
+    // a node-limited search leaves it out, it would not count in the nodes
+    let affordable = limits.nodes.is_none() && limits.time.map_or(limits.depth >= SOLVER_MIN_DEPTH, |budget| budget.soft >= SOLVER_MIN_TIME);
     let mut best_move = None;
-    for (mov, new_pieces) in not_played_twice {
-        if !turn {
-            let score = alphabeta(&mut table, depth - 1, true, alpha, beta, new_pieces);
-            if score > alpha {
-                alpha = score;
-                best_move = Some(mov);
+    // the solver only knows the standard rules
+    if affordable && limits.rules == Rules::default() && endgame::is_sparse(pieces) {
+        if let Some(solution) = endgame::solve(pieces, turn) {
+            best_move = solution.search_result(turn, limits.endgame_style);
+        }
+    }
+    let mut first_nodes = 0;
+    // nothing is left to search in a solved position
+    let last_depth = if best_move.is_some() { 0 } else { target_depth.max(MAX_DEPTH) };
+    for depth in 1..=last_depth {
+        // not even a stop interrupts the first iteration
+        search.completing = depth == 1;
+        let depth_start = Instant::now();
+        let nodes_before = search.nodes;
+        let traced = search.trace.as_mut().and_then(|trace| trace.enter(encode_pieces(pieces, turn), depth, i32::MIN, i32::MAX));
+        // a window around the expected score first, the full one when the score falls outside
+        let mut result = guess.and_then(|guess| {
+            let window = (guess - ASPIRATION_WINDOW, guess + ASPIRATION_WINDOW);
+            search.search_root(&not_played_twice, depth, turn, window).filter(|r| r.score > window.0 && r.score < window.1)
+        });
+        if result.is_none() && !search.interrupted() {
+            result = search.search_root(&not_played_twice, depth, turn, (i32::MIN, i32::MAX))
+                .or_else(|| search.search_root(&played_twice, depth, turn, (i32::MIN, i32::MAX)));
+        }
+        let interrupted = search.interrupted();
+        if let (Some(trace), Some(index)) = (&mut search.trace, traced) {
+            let exit = if interrupted { Exit::Stopped } else { Exit::Exact };
+            trace.exit(index, result.as_ref().map_or(0, |r| r.score), exit);
+        }
+        if interrupted { // the last iteration is incomplete
+            break;
+        }
+        search.completing = false;
+        let proven = result.as_ref().is_some_and(|r| r.score.abs() >= 100000);
+        if guess.is_some() {
+            guess = result.as_ref().map(|r| r.score).filter(|_| !proven);
+        }
+        if depth <= target_depth || limits.time.is_some() {
+            best_move = result;
+        } else if let (Some(best), Some(result)) = (&mut best_move, result) {
+            if result.mov == best.mov {
+                *best = result;
             }
+        }
+
+        // geometric mean of the growth between iterations, it smooths the odd/even effect of alpha-beta
+        let nodes = search.nodes - nodes_before;
+        let branching = if depth == 1 {
+            first_nodes = nodes.max(1);
+            first_nodes as f64
         } else {
-            let score = alphabeta(&mut table, depth - 1, false, alpha, beta, new_pieces);
-            if score < beta {
-                beta = score;
-                best_move = Some(mov);
-            }
+            (nodes as f64 / first_nodes as f64).powf(1.0 / (depth - 1) as f64)
+        };
+        let elapsed = start.elapsed().saturating_sub(search.paused - paused_before);
+        let elapsed_ms = elapsed.as_secs_f64() * 1000.0;
+        let last_iteration = depth_start.elapsed();
+        // deeper iterations see nothing more once every line ends at the move limit
+        let limited = limits.moves_left.is_some_and(|left| depth as u32 >= left);
+        let reached = proven || limited || depth >= target_depth || limits.time.is_some_and(|budget|
+            !budget.next_iteration(elapsed, last_iteration, branching)
+        );
+        // the extra iterations until "min_time" would make the variation depend on the pacing
+        let done = search.stopped() || reached && (proven || strict || elapsed >= min_time);
+        let eta_ms = match limits.time {
+            _ if done => 0.0,
+            _ if reached => (min_time - elapsed).as_secs_f64() * 1000.0,
+            Some(budget) => budget.soft.saturating_sub(elapsed).as_secs_f64() * 1000.0,
+            None => estimate_remaining(last_iteration.as_secs_f64() * 1000.0, branching, depth, target_depth),
+        };
+        on_progress(&Progress {
+            depth,
+            target_depth,
+            nodes: search.nodes,
+            elapsed_ms: elapsed_ms as u64,
+            eta_ms: eta_ms as u64,
+            progress: if done { 1.0 } else { elapsed_ms / (elapsed_ms + eta_ms).max(1.0) },
+            table: search.table.stats(),
+        });
+        if done {
+            break;
+        }
+        // the first iteration always completes so that there is a move to play
+        if reached {
+            search.deadline = Some(start + min_time + (search.paused - paused_before));
+        } else if let Some(budget) = limits.time {
+            search.deadline = Some(start + budget.hard + (search.paused - paused_before));
         }
     }
-    if let Some(mov) = best_move { //&& (if turn {beta <= 0} else {alpha >= 0})
-        println!("Computing time: {}ms", start.elapsed().as_millis());
-        return mov;
+    if let Some(rest) = min_time.checked_sub(start.elapsed()).filter(|_| !search.stopped()) {
+        thread::sleep(rest);
     }
-  
-    for (mov, new_pieces) in played_twice {
-        if !turn {
-            let score = alphabeta(&mut table, depth - 1, true, alpha, beta, new_pieces);
-            if score > alpha {
-                alpha = score;
-                best_move = Some(mov);
-            }
-        } else {
-            let score = alphabeta(&mut table, depth - 1, false, alpha, beta, new_pieces);
-            if score < beta {
-                beta = score;
-                best_move = Some(mov);
-            }
+    best_move
+}
+
+#[tauri::command]
+pub fn hands(pieces: Pieces) -> Result<[Vec<HandSlot>; 2], String> {
+    validate(&pieces)?;
+    Ok([hand(&pieces, false), hand(&pieces, true)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // keys of distinct positions that fall in the same bucket
+    fn same_bucket(count: usize) -> Vec<u64> {
+        let bucket = Table::locate(0).0;
+        (0..).filter(|&key| Table::locate(key).0 == bucket).take(count).collect()
+    }
+
+    #[test]
+    fn table_finds_what_was_inserted() {
+        let mut table = Table::new();
+        table.insert(42, 5, 17, Flag::Beta, 0);
+        assert!(matches!(table.get(42), Some((5, 17, Flag::Beta))));
+        assert!(table.get(43).is_none());
+        // a new entry of the same position replaces the old one
+        table.insert(42, 7, -3, Flag::Exact, 0);
+        assert!(matches!(table.get(42), Some((7, -3, Flag::Exact))));
+    }
+
+    #[test]
+    fn table_replaces_the_lowest_depth() {
+        let keys = same_bucket(BUCKET_SIZE + 1);
+        let mut table = Table::new();
+        for (i, &key) in keys[..BUCKET_SIZE].iter().enumerate() {
+            table.insert(key, if i == 1 { 2 } else { 6 }, 0, Flag::Exact, 0);
         }
+        table.insert(keys[BUCKET_SIZE], 4, 0, Flag::Exact, 0);
+        assert!(table.get(keys[1]).is_none());
+        assert!(keys.iter().enumerate().filter(|&(i, _)| i != 1).all(|(_, &key)| table.get(key).is_some()));
+        assert_eq!(table.stats.replacements, 1);
     }
-    println!("Computing time: {}ms", start.elapsed().as_millis());
-    best_move.unwrap()
-}
\ No newline at end of file
+
+    #[test]
+    fn table_replaces_the_oldest_generation_first() {
+        let keys = same_bucket(BUCKET_SIZE + 1);
+        let mut table = Table::new();
+        // a deep entry of an earlier search, shallow ones of the current search
+        table.insert(keys[0], 20, 0, Flag::Exact, 0);
+        for &key in &keys[1..BUCKET_SIZE] {
+            table.insert(key, 2, 0, Flag::Exact, 3);
+        }
+        table.insert(keys[BUCKET_SIZE], 2, 0, Flag::Exact, 3);
+        assert!(table.get(keys[0]).is_none());
+        assert!(keys[1..].iter().all(|&key| table.get(key).is_some()));
+    }
+}
@@ -0,0 +1,190 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+use crate::session::{GameResult, GameStateView, Session};
+use crate::shogi::{self, Limits, Move, Search, SearchControl};
+use crate::timeman::TimeBudget;
+
+// Simultaneous exhibition: one player against the engine on several boards. The engine replies
+// on one board at a time, in the order the player moved, and each reply has the same time
+// budget, so that a long think on one board never holds up the others for long.
+#[derive(Default)]
+pub struct Simul {
+    games: Mutex<Option<Games>>,
+    // search of the engine in progress, if any
+    search: Mutex<Option<Arc<SearchControl>>>,
+    // tells the engine thread of a replaced simul to stop
+    next_id: AtomicU64,
+}
+
+struct Games {
+    id: u64,
+    boards: Vec<Session>,
+    human: bool, // the side of the player on every board
+    limits: Limits,
+    // boards waiting for a reply of the engine
+    queue: Sender<usize>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulSummary {
+    pub wins: u32, // for the player
+    pub draws: u32,
+    pub losses: u32,
+    pub results: Vec<Option<GameResult>>, // per board, None while the game goes on
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulView {
+    pub boards: Vec<GameStateView>,
+    pub summary: SimulSummary,
+    pub finished: bool,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BoardUpdate {
+    board: usize,
+    state: GameStateView,
+}
+
+impl Games {
+    fn view(&self) -> SimulView {
+        let results: Vec<Option<GameResult>> = self.boards.iter().map(Session::result).collect();
+        let mut summary = SimulSummary { results: results.clone(), ..SimulSummary::default() };
+        for result in results.iter().flatten() {
+            match result.winner {
+                Some(winner) if winner == self.human => summary.wins += 1,
+                Some(_) => summary.losses += 1,
+                None => summary.draws += 1,
+            }
+        }
+        SimulView {
+            boards: self.boards.iter().map(Session::view).collect(),
+            finished: results.iter().all(Option::is_some),
+            summary,
+        }
+    }
+}
+
+// replies to the boards of the simul "id" one after the other until the simul is over or replaced
+fn run_engine(app: AppHandle, id: u64, queue: mpsc::Receiver<usize>) {
+    let simul = app.state::<Simul>();
+    // one table for all the boards of the simul
+    let mut search = Search::default();
+    for board in queue {
+        let (pieces, played, turn, limits) = {
+            let games = simul.games.lock().unwrap();
+            let Some(games) = games.as_ref().filter(|games| games.id == id) else { return };
+            let session = &games.boards[board];
+            if session.result().is_some() || session.turn() == games.human {
+                continue;
+            }
+            (*session.pieces(), session.played(), session.turn(), games.limits)
+        };
+        let control = Arc::new(SearchControl::default());
+        *simul.search.lock().unwrap() = Some(control.clone());
+        search.resume(control.clone());
+        let result = shogi::best_move(&mut search, &pieces, &played, limits, turn, &mut |_| ());
+        *simul.search.lock().unwrap() = None;
+        if control.stopped() {
+            return;
+        }
+        let Some(result) = result else { continue };
+        let mut games = simul.games.lock().unwrap();
+        let Some(games) = games.as_mut().filter(|games| games.id == id) else { return };
+        let session = &mut games.boards[board];
+        if session.play(result.mov).is_ok() {
+            let _ = app.emit("simul-move", BoardUpdate { board, state: session.view() });
+            let view = games.view();
+            if view.finished {
+                let _ = app.emit("simul-over", view.summary);
+            }
+        }
+    }
+}
+
+impl Simul {
+    fn stop(&self) {
+        *self.games.lock().unwrap() = None;
+        if let Some(control) = self.search.lock().unwrap().take() {
+            control.stop();
+        }
+    }
+}
+
+// "boards" new games, the engine replies within "think_ms" on each of them
+#[tauri::command]
+pub fn start_simul(
+    app: AppHandle,
+    simul: State<Simul>,
+    boards: usize,
+    human_first: bool,
+    depth: u8,
+    think_ms: u64,
+) -> Result<SimulView, String> {
+    if boards == 0 {
+        return Err("a simul needs at least one board".into());
+    }
+    simul.stop();
+    let think = Duration::from_millis(think_ms);
+    let limits = Limits { depth, ..Limits::time(TimeBudget::fixed(think)) };
+    let (queue, receiver) = mpsc::channel();
+    let mut games = simul.games.lock().unwrap();
+    let id = simul.next_id.fetch_add(1, Ordering::Relaxed);
+    let new_games = Games { id, boards: (0..boards).map(|_| Session::default()).collect(), human: !human_first, limits, queue };
+    if !human_first {
+        for board in 0..boards {
+            let _ = new_games.queue.send(board);
+        }
+    }
+    let view = new_games.view();
+    *games = Some(new_games);
+    thread::spawn(move || run_engine(app, id, receiver));
+    Ok(view)
+}
+
+#[tauri::command]
+pub fn simul_play(
+    app: AppHandle,
+    simul: State<Simul>,
+    board: usize,
+    from: usize,
+    to: usize,
+    promote: Option<bool>,
+) -> Result<GameStateView, String> {
+    let mut games = simul.games.lock().unwrap();
+    let games = games.as_mut().ok_or("there is no simul in progress")?;
+    let human = games.human;
+    let session = games.boards.get_mut(board).ok_or("there is no such board")?;
+    if session.turn() != human {
+        return Err("the engine is to move on this board".into());
+    }
+    session.play(Move { from, to, promote: promote.unwrap_or(true) })?;
+    let state = session.view();
+    if session.result().is_some() {
+        let view = games.view();
+        if view.finished {
+            let _ = app.emit("simul-over", view.summary);
+        }
+    } else {
+        let _ = games.queue.send(board);
+    }
+    Ok(state)
+}
+
+#[tauri::command]
+pub fn simul_state(simul: State<Simul>) -> Option<SimulView> {
+    simul.games.lock().unwrap().as_ref().map(Games::view)
+}
+
+#[tauri::command]
+pub fn stop_simul(simul: State<Simul>) {
+    simul.stop();
+}
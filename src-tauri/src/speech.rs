@@ -0,0 +1,34 @@
+use crate::commentary::{self, Language};
+use crate::session::GameResult;
+use crate::shogi::{self, Move, Pieces, Rules};
+
+// Sentences for text-to-speech and the accessibility mode, so that every part of the
+// frontend phrases moves and results the same way. The wording is that of the commentary.
+
+// "Giraffe moves to b2, capturing the chick.", "Elephant dropped on a3.", "Chick moves to b1 and promotes to hen. Check."
+pub fn move_sentence(pieces: &Pieces, mov: Move, language: &Language) -> String {
+    commentary::catalog().move_sentence(language, pieces, mov)
+}
+
+pub fn result_sentence(result: GameResult, language: &Language) -> String {
+    commentary::catalog().result_sentence(language, result)
+}
+
+#[tauri::command]
+pub fn speak_move(
+    pieces: Pieces,
+    from: usize,
+    to: usize,
+    promote: Option<bool>,
+    rules: Option<Rules>,
+    language: Option<Language>,
+) -> Result<String, String> {
+    shogi::validate(&pieces)?;
+    let mov = shogi::legal_move(&pieces, &rules.unwrap_or_default(), from, to, promote).ok_or("illegal move")?;
+    Ok(move_sentence(&pieces, mov, &language.unwrap_or_default()))
+}
+
+#[tauri::command]
+pub fn speak_result(result: GameResult, language: Option<Language>) -> String {
+    result_sentence(result, &language.unwrap_or_default())
+}
@@ -0,0 +1,41 @@
+use std::fs;
+use std::path::PathBuf;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+// JSON documents kept in the app data directory
+pub struct Store {
+    dir: PathBuf,
+}
+
+impl Store {
+    pub fn new(dir: PathBuf) -> Self {
+        Store { dir }
+    }
+
+    // for the files that are not JSON documents
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    pub fn load<T: DeserializeOwned>(&self, name: &str) -> Option<T> {
+        let text = fs::read_to_string(self.dir.join(name)).ok()?;
+        match serde_json::from_str(&text) {
+            Ok(value) => Some(value),
+            Err(err) => {
+                eprintln!("cannot read {name}: {err}");
+                None
+            }
+        }
+    }
+
+    pub fn save<T: Serialize>(&self, name: &str, value: &T) -> Result<(), String> {
+        fs::create_dir_all(&self.dir).map_err(|err| err.to_string())?;
+        let text = serde_json::to_string(value).map_err(|err| err.to_string())?;
+        fs::write(self.dir.join(name), text).map_err(|err| err.to_string())
+    }
+
+    pub fn remove(&self, name: &str) {
+        let _ = fs::remove_file(self.dir.join(name));
+    }
+}
@@ -0,0 +1,133 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State};
+use crate::analysis::{Analysis, EvalUpdate};
+use crate::board_image;
+use crate::raster::RenderOptions;
+use crate::session::{GameStateView, Session};
+
+const DEFAULT_PORT: u16 = 7878;
+// delay between two updates of the event stream
+const STREAM_INTERVAL: Duration = Duration::from_millis(500);
+
+// Local server for streaming software such as OBS, which can show it as a browser source:
+//   /             page showing the board, kept up to date
+//   /state.json   current game state, clocks and evaluation
+//   /board.svg    current board
+//   /events       server-sent events with the state, every STREAM_INTERVAL
+// Only connections from this machine are accepted, and without a CORS header the pages of other
+// sites open in a browser cannot read the game.
+#[derive(Default)]
+pub struct StreamServer {
+    running: Mutex<Option<(u16, Arc<AtomicBool>)>>,
+}
+
+#[derive(Serialize)]
+struct StreamState {
+    game: GameStateView,
+    eval: Option<EvalUpdate>,
+}
+
+fn state_json(app: &AppHandle) -> String {
+    let game = app.state::<Mutex<Session>>().lock().unwrap().view();
+    let eval = app.state::<Analysis>().latest();
+    serde_json::to_string(&StreamState { game, eval }).unwrap_or_default()
+}
+
+const PAGE: &str = "<!DOCTYPE html><html><body style=\"margin:0;background:transparent\">\
+<img id=\"board\" src=\"/board.svg\"><pre id=\"info\" style=\"font:20px sans-serif;color:white\"></pre>\
+<script>new EventSource('/events').onmessage = (e) => {\
+ const s = JSON.parse(e.data);\
+ document.getElementById('board').src = '/board.svg?' + Date.now();\
+ const c = s.game.clock ? s.game.clock.remainingMs.map((ms) => (ms / 1000).toFixed(1) + 's').join(' / ') : '';\
+ document.getElementById('info').textContent = c + (s.eval ? '  eval ' + s.eval.score : '');\
+};</script></body></html>";
+
+fn respond(stream: &mut TcpStream, content_type: &str, body: &str) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\n\
+         Cache-Control: no-store\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+fn handle(app: AppHandle, mut stream: TcpStream, stopped: Arc<AtomicBool>) -> std::io::Result<()> {
+    let mut request = String::new();
+    BufReader::new(&stream).read_line(&mut request)?;
+    let path = request.split_whitespace().nth(1).unwrap_or("/");
+    match path.split('?').next().unwrap_or("/") {
+        "/" => respond(&mut stream, "text/html", PAGE),
+        "/state.json" => respond(&mut stream, "application/json", &state_json(&app)),
+        "/board.svg" => {
+            let pieces = *app.state::<Mutex<Session>>().lock().unwrap().pieces();
+            respond(&mut stream, "image/svg+xml", &board_image::svg(&pieces, &RenderOptions { cell: 80, ..RenderOptions::default() }))
+        }
+        "/events" => {
+            write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\n\
+                            Cache-Control: no-store\r\n\r\n")?;
+            // until the client goes away
+            while !stopped.load(Ordering::Relaxed) {
+                write!(stream, "data: {}\n\n", state_json(&app))?;
+                stream.flush()?;
+                thread::sleep(STREAM_INTERVAL);
+            }
+            Ok(())
+        }
+        _ => write!(stream, "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"),
+    }
+}
+
+impl StreamServer {
+    pub fn start(&self, app: AppHandle, port: u16) -> Result<u16, String> {
+        let mut running = self.running.lock().unwrap();
+        if let Some((port, _)) = *running {
+            return Ok(port);
+        }
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port)).map_err(|err| err.to_string())?;
+        let port = listener.local_addr().map_err(|err| err.to_string())?.port();
+        let stopped = Arc::new(AtomicBool::new(false));
+        let server_stopped = stopped.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if server_stopped.load(Ordering::Relaxed) {
+                    break;
+                }
+                let Ok(stream) = stream else { continue };
+                let (app, stopped) = (app.clone(), server_stopped.clone());
+                thread::spawn(move || handle(app, stream, stopped));
+            }
+        });
+        *running = Some((port, stopped));
+        Ok(port)
+    }
+
+    // the port it listens on, None when it is stopped
+    pub fn port(&self) -> Option<u16> {
+        self.running.lock().unwrap().as_ref().map(|(port, _)| *port)
+    }
+
+    pub fn stop(&self) {
+        if let Some((port, stopped)) = self.running.lock().unwrap().take() {
+            stopped.store(true, Ordering::Relaxed);
+            // wakes the server up so that it sees the flag
+            let _ = TcpStream::connect((Ipv4Addr::LOCALHOST, port));
+        }
+    }
+}
+
+// returns the port the server listens on
+#[tauri::command]
+pub fn start_stream_server(app: AppHandle, server: State<StreamServer>, port: Option<u16>) -> Result<u16, String> {
+    server.start(app, port.unwrap_or(DEFAULT_PORT))
+}
+
+#[tauri::command]
+pub fn stop_stream_server(server: State<StreamServer>) {
+    server.stop();
+}
@@ -0,0 +1,204 @@
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use crate::analysis::{self, BatchPosition};
+use crate::archive::{self, ArchivedGame};
+use crate::ladder::{self, Ladder, LevelSetting};
+use crate::notation;
+use crate::review::REVIEW_DEPTH;
+use crate::selfplay::{self, Adjudication, Rng};
+use crate::shogi::{self, Move, Pieces};
+use crate::store::Store;
+
+// Estimate of the strength of a player from the archived games. Every move of the player is
+// compared with the move of a deeper search: how often it is the same and how many points it
+// loses. Both measures are placed on reference curves, made of the same measures for the engine
+// in self-play at levels of known strength, and the estimates of the games are averaged.
+
+pub const FILE: &str = "strength_reference.json";
+const REFERENCE_DEPTHS: [u8; 6] = [1, 2, 3, 4, 5, 6];
+const REFERENCE_RANDOM_PLIES: usize = 4;
+// a blunder into a mate is not a thousand times worse than a lost piece
+const LOSS_CAP: i32 = 1000;
+// the spread of the estimates of single games is never taken below this
+const MIN_SPREAD: f64 = 150.0;
+
+// rating of the engine searching at "depth" until the ladder has been measured
+pub fn nominal_rating(depth: u8) -> f64 {
+    match depth {
+        0 | 1 => 600.0,
+        2 => 900.0,
+        3 => 1150.0,
+        4 => 1350.0,
+        5 => 1500.0,
+        depth => 1650.0 + 100.0 * (depth - 6) as f64,
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Accuracy {
+    moves: usize,
+    matched: usize,
+    loss: i64,
+}
+
+impl Accuracy {
+    fn add(&mut self, other: Accuracy) {
+        self.moves += other.moves;
+        self.matched += other.matched;
+        self.loss += other.loss;
+    }
+
+    fn match_rate(&self) -> f64 {
+        self.matched as f64 / self.moves.max(1) as f64
+    }
+
+    fn average_loss(&self) -> f64 {
+        self.loss as f64 / self.moves.max(1) as f64
+    }
+}
+
+// the moves of "player" from the ply "from" on, None for both players
+fn accuracy(pieces: &Pieces, turn: bool, moves: &[Move], player: Option<bool>, from: usize) -> Accuracy {
+    let mut positions = vec![BatchPosition { pieces: *pieces, turn }];
+    for &mov in moves {
+        let last = positions[positions.len() - 1];
+        positions.push(BatchPosition { pieces: shogi::play_move(&last.pieces, mov), turn: !last.turn });
+    }
+    let evaluations = analysis::evaluate_all(&positions, REVIEW_DEPTH);
+    let mut accuracy = Accuracy::default();
+    for (ply, &mov) in moves.iter().enumerate().skip(from) {
+        let (position, before, after) = (positions[ply], &evaluations[ply], &evaluations[ply + 1]);
+        if player.is_some_and(|player| player != position.turn) || before.pv.is_empty() {
+            continue;
+        }
+        let sign = if position.turn { -1 } else { 1 };
+        accuracy.moves += 1;
+        accuracy.matched += (before.pv[0] == mov) as usize;
+        accuracy.loss += (sign * (before.score - after.score)).clamp(0, LOSS_CAP) as i64;
+    }
+    accuracy
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReferencePoint {
+    pub depth: u8,
+    pub rating: f64,
+    pub moves: usize,
+    pub match_rate: f64,
+    pub average_loss: f64,
+}
+
+// self-play games at every reference depth, both players at that depth, rated by the ladder if any
+pub fn build_reference(games: usize, ladder: Option<&Ladder>, rng: &mut Rng) -> Vec<ReferencePoint> {
+    let params = shogi::eval_params();
+    REFERENCE_DEPTHS.iter().map(|&depth| {
+        let mut total = Accuracy::default();
+        for _ in 0..games {
+            let session = selfplay::play_game([&params, &params], depth, REFERENCE_RANDOM_PLIES, Some(&Adjudication::default()), rng);
+            let start = session.history().first().map_or(*session.pieces(), |&(pieces, _)| pieces);
+            total.add(accuracy(&start, false, &session.moves(), None, REFERENCE_RANDOM_PLIES));
+        }
+        ReferencePoint {
+            depth,
+            rating: ladder.and_then(|ladder| ladder.rating(LevelSetting { depth, human: None })).unwrap_or_else(|| nominal_rating(depth)),
+            moves: total.moves,
+            match_rate: total.match_rate(),
+            average_loss: total.average_loss(),
+        }
+    }).collect()
+}
+
+// the rating at "value" on the curve, the measure grows with the rating when "rising"; the
+// rating is kept within the reference levels
+fn interpolate(points: &[(f64, f64)], value: f64, rising: bool) -> f64 {
+    let mut points = points.to_vec();
+    points.sort_by(|a, b| a.1.total_cmp(&b.1));
+    let at = |(measure, _): (f64, f64)| if rising { measure } else { -measure };
+    let value = if rising { value } else { -value };
+    // a level measured worse than the one below, by the luck of a few games, is taken as equal
+    for i in 1..points.len() {
+        if at(points[i]) < at(points[i - 1]) {
+            points[i].0 = points[i - 1].0;
+        }
+    }
+    let first = points[0];
+    if value <= at(first) {
+        return first.1;
+    }
+    for pair in points.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        if value <= at(b) {
+            let span = at(b) - at(a);
+            let t = if span > 0.0 { (value - at(a)) / span } else { 0.5 };
+            return a.1 + t * (b.1 - a.1);
+        }
+    }
+    points[points.len() - 1].1
+}
+
+fn rating(reference: &[ReferencePoint], accuracy: &Accuracy) -> f64 {
+    let matches: Vec<(f64, f64)> = reference.iter().map(|point| (point.match_rate, point.rating)).collect();
+    let losses: Vec<(f64, f64)> = reference.iter().map(|point| (point.average_loss, point.rating)).collect();
+    (interpolate(&matches, accuracy.match_rate(), true) + interpolate(&losses, accuracy.average_loss(), false)) / 2.0
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StrengthEstimate {
+    pub rating: f64,
+    // 95% confidence bounds
+    pub low: f64,
+    pub high: f64,
+    pub games: usize,
+    pub moves: usize,
+    pub match_rate: f64,
+    pub average_loss: f64,
+}
+
+// the games of "player", estimated one by one
+pub fn estimate(reference: &[ReferencePoint], games: &[ArchivedGame], player: &str) -> Option<StrengthEstimate> {
+    let player = player.to_lowercase();
+    let mut ratings = vec![];
+    let mut total = Accuracy::default();
+    for game in games {
+        let Some(side) = game.info.players.iter().position(|name| name.to_lowercase() == player) else { continue };
+        let Some((pieces, turn)) = notation::parse_position(&game.start) else { continue };
+        let accuracy = accuracy(&pieces, turn, &game.moves, Some(side == 1), 0);
+        if accuracy.moves > 0 {
+            ratings.push(rating(reference, &accuracy));
+            total.add(accuracy);
+        }
+    }
+    if reference.is_empty() || ratings.is_empty() {
+        return None;
+    }
+    let n = ratings.len() as f64;
+    let mean = ratings.iter().sum::<f64>() / n;
+    let variance = ratings.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.0).max(1.0);
+    let half_width = 1.96 * variance.sqrt().max(MIN_SPREAD) / n.sqrt();
+    Some(StrengthEstimate {
+        rating: mean,
+        low: mean - half_width,
+        high: mean + half_width,
+        games: ratings.len(),
+        moves: total.moves,
+        match_rate: total.match_rate(),
+        average_loss: total.average_loss(),
+    })
+}
+
+// the reference curves are saved, the estimates use the last ones built
+#[tauri::command(async)]
+pub fn build_strength_reference(store: State<Store>, games: usize) -> Result<Vec<ReferencePoint>, String> {
+    let reference = build_reference(games.max(1), ladder::load(&store).as_ref(), &mut Rng::from_clock());
+    store.save(FILE, &reference)?;
+    Ok(reference)
+}
+
+// "player" is the name given in the metadata of the games
+#[tauri::command(async)]
+pub fn estimate_strength(store: State<Store>, player: String) -> Result<StrengthEstimate, String> {
+    let reference: Vec<ReferencePoint> = store.load(FILE).ok_or("the reference curves have not been built")?;
+    estimate(&reference, &archive::load(&store), &player).ok_or_else(|| format!("no game of {player}"))
+}
@@ -0,0 +1,157 @@
+use std::fs;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use crate::archive::{self, ArchivedGame, GameInfo};
+use crate::bookmarks;
+use crate::correspondence;
+use crate::explorer::Explorer;
+use crate::notation;
+use crate::session::GameResult;
+use crate::shogi::{self, Limits, Move, Rules, Search};
+use crate::store::Store;
+
+// A lesson in one file: annotated games, key positions with a comment and puzzles, for a teacher
+// to hand to students using the app. The file is JSON, positions are position strings and moves
+// are names, so that it does not depend on the order of the pieces.
+
+const VERSION: u32 = 1;
+// depth of the search giving the solution of a puzzle that comes without one
+const SOLUTION_DEPTH: u8 = 10;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StudyGame {
+    pub info: GameInfo, // the annotations are in the notes
+    pub start: String,
+    pub moves: Vec<String>,
+    #[serde(default)]
+    pub rules: Rules,
+    pub result: Option<GameResult>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StudyPosition {
+    pub position: String,
+    pub comment: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Puzzle {
+    pub position: String,
+    #[serde(default)]
+    pub comment: String,
+    #[serde(default)]
+    pub solution: Vec<String>, // the moves of both sides, the first one to find
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Study {
+    pub version: u32,
+    pub title: String,
+    pub games: Vec<StudyGame>,
+    pub positions: Vec<StudyPosition>,
+    pub puzzles: Vec<Puzzle>,
+}
+
+fn study_game(game: &ArchivedGame) -> StudyGame {
+    StudyGame {
+        info: game.info.clone(),
+        start: game.start.clone(),
+        moves: game.move_list.clone(),
+        rules: game.rules,
+        result: game.result,
+    }
+}
+
+// the moves of the game, None if one of them cannot be played
+fn replay(game: &StudyGame) -> Option<Vec<Move>> {
+    let (mut pieces, mut turn) = notation::parse_position(&game.start)?;
+    game.moves.iter().map(|name| {
+        let mov = notation::parse_move(&pieces, turn, &game.rules, name)?;
+        pieces = shogi::play_move(&pieces, mov);
+        turn = !turn;
+        Some(mov)
+    }).collect()
+}
+
+fn solve(puzzle: &mut Puzzle) -> Result<(), String> {
+    let (pieces, turn) = notation::parse_position(&puzzle.position).ok_or_else(|| format!("invalid puzzle position {}", puzzle.position))?;
+    if puzzle.solution.is_empty() {
+        let result = shogi::best_move(&mut Search::default(), &pieces, &[], Limits::depth(SOLUTION_DEPTH), turn, &mut |_| ());
+        let result = result.ok_or_else(|| format!("no legal move in the puzzle position {}", puzzle.position))?;
+        puzzle.solution = notation::move_list(&pieces, &result.pv);
+    }
+    Ok(())
+}
+
+// the archived games and the bookmarks given by id, the puzzles without a solution are solved
+pub fn build(store: &Store, title: String, games: &[u32], bookmarks: &[u32], mut puzzles: Vec<Puzzle>) -> Result<Study, String> {
+    let archived = archive::load(store);
+    let games = games.iter()
+        .map(|&id| archived.iter().find(|game| game.id == id).map(study_game).ok_or_else(|| format!("no game {id}")))
+        .collect::<Result<_, _>>()?;
+    let marks = bookmarks::load(store);
+    let positions = bookmarks.iter()
+        .map(|&id| marks.iter().find(|mark| mark.id == id)
+            .map(|mark| StudyPosition { position: mark.position.clone(), comment: mark.note.clone() })
+            .ok_or_else(|| format!("no bookmark {id}")))
+        .collect::<Result<_, _>>()?;
+    for puzzle in &mut puzzles {
+        solve(puzzle)?;
+    }
+    Ok(Study { version: VERSION, title, games, positions, puzzles })
+}
+
+#[tauri::command(async)]
+pub fn export_study(
+    store: State<Store>,
+    path: String,
+    title: String,
+    games: Vec<u32>,
+    bookmarks: Vec<u32>,
+    puzzles: Vec<Puzzle>,
+) -> Result<Study, String> {
+    let study = build(&store, title, &games, &bookmarks, puzzles)?;
+    let text = serde_json::to_string_pretty(&study).map_err(|err| err.to_string())?;
+    fs::write(&path, text).map_err(|err| format!("cannot write {path}: {err}"))?;
+    Ok(study)
+}
+
+// The games join the archive and the positions the bookmarks, the study is returned for its puzzles.
+// Nothing is added unless the whole file is valid.
+#[tauri::command]
+pub fn import_study(store: State<Store>, explorer: State<Explorer>, path: String) -> Result<Study, String> {
+    let text = fs::read_to_string(&path).map_err(|err| format!("cannot read {path}: {err}"))?;
+    let study: Study = serde_json::from_str(&text).map_err(|err| format!("not a study: {err}"))?;
+    if study.version > VERSION {
+        return Err("the study was made by a newer version of the app".into());
+    }
+    let games = study.games.iter()
+        .map(|game| replay(game).ok_or_else(|| format!("invalid game {}", game.info.event)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let invalid = study.positions.iter().map(|p| &p.position).chain(study.puzzles.iter().map(|p| &p.position))
+        .find(|position| notation::parse_position(position).is_none());
+    if let Some(position) = invalid {
+        return Err(format!("invalid position {position}"));
+    }
+    for (game, moves) in study.games.iter().zip(games) {
+        let game = archive::add(&store, ArchivedGame {
+            id: 0,
+            info: game.info.clone(),
+            start: game.start.clone(),
+            moves,
+            move_list: game.moves.clone(),
+            rules: game.rules,
+            result: game.result,
+            ended_ms: correspondence::now_ms(),
+        })?;
+        explorer.archived(&game);
+    }
+    for position in &study.positions {
+        bookmarks::add(&store, position.position.clone(), position.comment.clone())?;
+    }
+    Ok(study)
+}
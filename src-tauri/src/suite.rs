@@ -0,0 +1,163 @@
+use std::fs;
+use std::time::{Duration, Instant};
+use serde::Serialize;
+use crate::notation;
+use crate::shogi::{self, Limits, Move, Pieces, Rules, Search};
+use crate::timeman::TimeBudget;
+
+// Test suites in the manner of EPD files, to track the strength of the engine on known themes.
+// One test per line: a position string, then operations separated by semicolons.
+//   bm <moves>            the engine plays one of these moves
+//   am <moves>            the engine plays none of these moves
+//   res win|draw|loss     the result with best play, for the player to move
+//   id "<name>"
+// Empty lines and lines starting with '#' are skipped.
+
+const DEFAULT_DEPTH: u8 = 8;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Outcome { Win, Draw, Loss }
+
+struct Test {
+    line: usize,
+    id: Option<String>,
+    pieces: Pieces,
+    turn: bool,
+    best: Vec<Move>,
+    avoid: Vec<Move>,
+    result: Option<Outcome>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestReport {
+    pub line: usize,
+    pub id: Option<String>,
+    pub passed: bool,
+    pub played: String,
+    pub score: i32, // from the point of view of the player to move
+    pub elapsed_ms: u64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SuiteReport {
+    pub tests: Vec<TestReport>,
+    pub passed: usize,
+    pub elapsed_ms: u64,
+}
+
+fn parse_test(line: usize, text: &str) -> Result<Test, String> {
+    let error = |message: &str| format!("line {line}: {message}");
+    let fields: Vec<&str> = text.split_whitespace().collect();
+    if fields.len() < 3 {
+        return Err(error("no position"));
+    }
+    let (pieces, turn) = notation::parse_position(&fields[..3].join(" ")).ok_or_else(|| error("invalid position"))?;
+    let mut test = Test { line, id: None, pieces, turn, best: vec![], avoid: vec![], result: None };
+    let operations = text.trim().splitn(4, char::is_whitespace).nth(3).unwrap_or("");
+    for operation in operations.split(';').map(str::trim).filter(|operation| !operation.is_empty()) {
+        let (opcode, operand) = operation.split_once(char::is_whitespace).unwrap_or((operation, ""));
+        let moves = || operand.split_whitespace()
+            .map(|name| notation::parse_move(&pieces, turn, &Rules::default(), name).ok_or_else(|| error(&format!("invalid move {name}"))))
+            .collect::<Result<Vec<_>, _>>();
+        match opcode {
+            "bm" => test.best = moves()?,
+            "am" => test.avoid = moves()?,
+            "res" => test.result = Some(match operand.trim() {
+                "win" => Outcome::Win,
+                "draw" => Outcome::Draw,
+                "loss" => Outcome::Loss,
+                _ => return Err(error("the result is win, draw or loss")),
+            }),
+            "id" => test.id = Some(operand.trim().trim_matches('"').to_string()),
+            _ => return Err(error(&format!("unknown operation {opcode}"))),
+        }
+    }
+    Ok(test)
+}
+
+fn parse_suite(text: &str) -> Result<Vec<Test>, String> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(i, line)| parse_test(i + 1, line))
+        .collect()
+}
+
+fn run_test(test: &Test, limits: Limits) -> TestReport {
+    let start = Instant::now();
+    let result = shogi::best_move(&mut Search::default(), &test.pieces, &[], limits, test.turn, &mut |_| ());
+    // a player without a move has lost
+    let score = result.as_ref().map_or(-100000, |result| if test.turn { -result.score } else { result.score });
+    let mov = result.map(|result| result.mov);
+    let outcome = match score {
+        _ if score >= 100000 => Outcome::Win,
+        _ if score <= -100000 => Outcome::Loss,
+        _ => Outcome::Draw,
+    };
+    let passed = (test.best.is_empty() || mov.is_some_and(|mov| test.best.contains(&mov)))
+        && !mov.is_some_and(|mov| test.avoid.contains(&mov))
+        && test.result.is_none_or(|expected| expected == outcome);
+    TestReport {
+        line: test.line,
+        id: test.id.clone(),
+        passed,
+        played: mov.map_or_else(|| "-".into(), |mov| notation::move_name(&test.pieces, mov)),
+        score,
+        elapsed_ms: start.elapsed().as_millis() as u64,
+    }
+}
+
+// a time per position takes precedence over the depth
+fn limits(depth: Option<u8>, time_ms: Option<u64>) -> Limits {
+    match time_ms {
+        Some(ms) => Limits::time(TimeBudget::fixed(Duration::from_millis(ms))),
+        None => Limits::depth(depth.unwrap_or(DEFAULT_DEPTH)),
+    }
+}
+
+pub fn run_suite(text: &str, depth: Option<u8>, time_ms: Option<u64>) -> Result<SuiteReport, String> {
+    let tests = parse_suite(text)?;
+    let start = Instant::now();
+    let tests: Vec<TestReport> = tests.iter().map(|test| run_test(test, limits(depth, time_ms))).collect();
+    Ok(SuiteReport {
+        passed: tests.iter().filter(|test| test.passed).count(),
+        tests,
+        elapsed_ms: start.elapsed().as_millis() as u64,
+    })
+}
+
+#[tauri::command(async)]
+pub fn run_test_suite(path: String, depth: Option<u8>, time_ms: Option<u64>) -> Result<SuiteReport, String> {
+    let text = fs::read_to_string(&path).map_err(|err| format!("cannot read {path}: {err}"))?;
+    run_suite(&text, depth, time_ms)
+}
+
+// "suite <file> [<depth> | <ms>ms]"
+pub fn suite_cli(args: &[String]) -> Result<(), String> {
+    let usage = || "usage: suite <file> [<depth> | <ms>ms]".to_string();
+    let (path, limit) = match args {
+        [path] => (path, None),
+        [path, limit] => (path, Some(limit.as_str())),
+        _ => return Err(usage()),
+    };
+    let (depth, time_ms) = match limit {
+        Some(limit) => match limit.strip_suffix("ms") {
+            Some(ms) => (None, Some(ms.parse().map_err(|_| usage())?)),
+            None => (Some(limit.parse().map_err(|_| usage())?), None),
+        },
+        None => (None, None),
+    };
+    let report = run_test_suite(path.clone(), depth, time_ms)?;
+    // the timings would make the deterministic output differ from one run to the next
+    let timing = |ms: u64| if shogi::deterministic() { String::new() } else { format!(" in {ms}ms") };
+    for test in &report.tests {
+        let name = test.id.clone().unwrap_or_else(|| format!("line {}", test.line));
+        let verdict = if test.passed { "ok" } else { "FAILED" };
+        println!("{name}: {verdict}, played {} ({}){}", test.played, test.score, timing(test.elapsed_ms));
+    }
+    println!("{}/{} passed{}", report.passed, report.tests.len(), timing(report.elapsed_ms));
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use serde::Serialize;
+use crate::shogi::{self, HandSlot, Kind, Pieces, HAND, PIECE_VALUE};
+
+// Facts about a position that the info panel and the move explanations both show,
+// computed in one place.
+
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttackedPiece {
+    pub index: usize,
+    pub kind: Kind,
+    pub square: u8,
+    pub defended: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SideSummary {
+    pub legal_moves: usize, // as if the player were to move
+    pub material: i32, // pieces on the board and in hand, the lion excluded
+    pub hand: Vec<HandSlot>,
+    pub attacked: Vec<AttackedPiece>, // pieces on the board the opponent could capture
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PositionSummary {
+    pub sides: [SideSummary; 2], // the first player, then the second
+    pub material_balance: i32, // from the point of view of the first player
+    pub check: bool, // the lion of the player to move is attacked
+}
+
+fn side(pieces: &Pieces, owner: bool) -> SideSummary {
+    let own = || pieces.iter().enumerate().filter(move |(_, piece)| piece.owner == owner);
+    let material = own().filter(|(_, piece)| piece.kind != Kind::Lion).map(|(_, piece)| PIECE_VALUE[piece.kind as usize]).sum();
+    let attacked = own()
+        .filter(|(_, piece)| piece.position != HAND && shogi::is_attacked(pieces, piece.position, !owner))
+        .map(|(index, piece)| AttackedPiece {
+            index,
+            kind: piece.kind,
+            square: piece.position,
+            defended: shogi::is_attacked(pieces, piece.position, owner),
+        })
+        .collect();
+    SideSummary {
+        legal_moves: shogi::possible_moves(pieces, owner).len(),
+        material,
+        hand: shogi::hand(pieces, owner),
+        attacked,
+    }
+}
+
+pub fn summary(pieces: &Pieces, turn: bool) -> PositionSummary {
+    let sides = [side(pieces, false), side(pieces, true)];
+    let lion = shogi::lion(pieces, turn).position;
+    PositionSummary {
+        material_balance: sides[0].material - sides[1].material,
+        check: lion != HAND && shogi::is_attacked(pieces, lion, !turn),
+        sides,
+    }
+}
+
+#[tauri::command]
+pub fn position_summary(pieces: Pieces, turn: bool) -> Result<PositionSummary, String> {
+    shogi::validate(&pieces)?;
+    Ok(summary(&pieces, turn))
+}
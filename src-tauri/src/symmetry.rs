@@ -0,0 +1,108 @@
+use serde::Serialize;
+use crate::census;
+use crate::endgame;
+use crate::notation;
+use crate::selfplay::{self, Rng};
+use crate::session::Session;
+use crate::shogi::{self, Pieces, HAND};
+
+// Checks of the evaluation on random positions, to catch asymmetries in new terms or weights:
+// the score is negated when the colors are swapped, unchanged under the left-right mirror, and
+// the same after a move is played and taken back.
+
+const DEFAULT_POSITIONS: usize = 10_000;
+// the random positions are reached by random moves from the initial one
+const MAX_PLIES: usize = 40;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Check { ColorFlip, Mirror, Undo }
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SymmetryFailure {
+    pub position: String,
+    pub check: Check,
+    pub scores: [i32; 2], // of the position and of its transform
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SymmetryReport {
+    pub positions: usize,
+    pub failures: Vec<SymmetryFailure>,
+}
+
+// the same position seen by the other player, the board turned around
+fn flip_colors(pieces: &Pieces) -> Pieces {
+    let mut flipped = *pieces;
+    for i in 0..4 {
+        flipped.swap(i, i + 4);
+    }
+    for piece in &mut flipped {
+        piece.owner = !piece.owner;
+        if piece.position != HAND {
+            piece.position = HAND - 1 - piece.position;
+        }
+    }
+    flipped
+}
+
+fn random_position(rng: &mut Rng) -> (Pieces, bool) {
+    let (mut pieces, mut turn) = (shogi::initial_pieces(), false);
+    for _ in 0..rng.below(MAX_PLIES + 1) {
+        if endgame::lost(&pieces, turn) {
+            break;
+        }
+        let moves = shogi::possible_moves(&pieces, turn);
+        pieces = shogi::play_move(&pieces, moves[rng.below(moves.len())]);
+        turn = !turn;
+    }
+    (pieces, turn)
+}
+
+pub fn validate(positions: usize, seed: u64) -> SymmetryReport {
+    let params = shogi::eval_params();
+    let evaluate = |pieces: &Pieces| shogi::evaluate_position(pieces, &params);
+    let mut rng = Rng::new(seed);
+    let mut failures = vec![];
+    for _ in 0..positions {
+        let (pieces, turn) = random_position(&mut rng);
+        let score = evaluate(&pieces);
+        let mut check = |check, other: i32, expected: i32| {
+            if other != expected {
+                failures.push(SymmetryFailure { position: notation::position_string(&pieces, turn), check, scores: [score, other] });
+            }
+        };
+        check(Check::ColorFlip, evaluate(&flip_colors(&pieces)), -score);
+        check(Check::Mirror, evaluate(&census::mirror(&pieces)), score);
+        let moves = shogi::possible_moves(&pieces, turn);
+        if !endgame::lost(&pieces, turn) && !moves.is_empty() {
+            let mut session = Session::from_position(pieces, turn);
+            if session.play(moves[rng.below(moves.len())]).is_ok() && session.undo() {
+                check(Check::Undo, evaluate(session.pieces()), score);
+            }
+        }
+    }
+    SymmetryReport { positions, failures }
+}
+
+#[tauri::command(async)]
+pub fn validate_evaluation(positions: Option<usize>, seed: Option<u64>) -> SymmetryReport {
+    validate(positions.unwrap_or(DEFAULT_POSITIONS), seed.unwrap_or_else(selfplay::clock_seed))
+}
+
+// "check-eval [<positions>]"
+pub fn check_eval_cli(args: &[String]) -> Result<(), String> {
+    let positions = match args {
+        [] => DEFAULT_POSITIONS,
+        [positions] => positions.parse().map_err(|_| "usage: check-eval [<positions>]")?,
+        _ => return Err("usage: check-eval [<positions>]".into()),
+    };
+    let report = validate(positions, selfplay::clock_seed());
+    for failure in &report.failures {
+        println!("{:?} {}: {} then {}", failure.check, failure.position, failure.scores[0], failure.scores[1]);
+    }
+    println!("{} positions, {} failures", report.positions, report.failures.len());
+    if report.failures.is_empty() { Ok(()) } else { Err("the evaluation is not symmetric".into()) }
+}
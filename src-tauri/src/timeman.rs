@@ -0,0 +1,145 @@
+use std::fs;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use crate::shogi::{Limits, Pieces, HAND};
+
+// kept in reserve so that the move is delivered before the flag falls
+const SAFETY_MARGIN: Duration = Duration::from_millis(50);
+// a single move never uses more than this fraction of the remaining time
+const MAX_SHARE: u32 = 3;
+
+#[derive(Clone, Copy, Debug)]
+pub struct TimeBudget {
+    pub soft: Duration, // no new iteration is started past this point
+    pub hard: Duration, // the search is aborted at this point
+}
+
+// fewer moves are expected to remain as the game goes on
+fn moves_to_go(ply: usize) -> u32 {
+    (25 - (ply as u32 / 2).min(15)).max(10)
+}
+
+// pieces in hand mean drops everywhere and sharper positions, they deserve more time.
+// The first moves are well known and are played faster.
+fn phase_factor(pieces: &Pieces, ply: usize) -> f64 {
+    let in_hand = pieces.iter().filter(|p| p.position == HAND).count();
+    let factor = 1.0 + 0.1 * in_hand as f64;
+    if ply < 4 { factor / 2.0 } else { factor }
+}
+
+// thinking time for the next move of a player with "remaining" time on their clock
+pub fn allocate(remaining: Duration, increment: Duration, ply: usize, pieces: &Pieces) -> TimeBudget {
+    let usable = remaining.saturating_sub(SAFETY_MARGIN);
+    let hard = (usable / MAX_SHARE + increment).min(usable);
+    let soft = ((usable / moves_to_go(ply) + increment * 3 / 4).mul_f64(phase_factor(pieces, ply))).min(hard);
+    TimeBudget { soft, hard }
+}
+
+impl TimeBudget {
+    // a set thinking time, no new iteration is started past half of it
+    pub fn fixed(time: Duration) -> Self {
+        TimeBudget { soft: time / 2, hard: time }
+    }
+
+    // a new iteration, expected to take "branching" times as long as the last one, is started only
+    // if it has a chance to complete
+    pub fn next_iteration(&self, elapsed: Duration, last_iteration: Duration, branching: f64) -> bool {
+        elapsed < self.soft && elapsed + last_iteration.mul_f64(branching) < self.hard
+    }
+}
+
+// Low-power mode, on battery or when the frontend asks for it: the engine searches with one
+// thread, less deep and for half of its soft budget in timed games, and it no longer ponders.
+// The depths that are cheap anyway are left alone, as is the hard budget, so that the clock
+// is handled the same way.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PowerMode {
+    #[default]
+    Auto, // low power while on battery, when the battery can be read
+    Normal,
+    LowPower,
+}
+
+const LOW_POWER_DEPTH_CUT: u8 = 2;
+const LOW_POWER_MIN_DEPTH: u8 = 4;
+// the battery is read again after this long
+const BATTERY_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+static POWER_MODE: Mutex<PowerMode> = Mutex::new(PowerMode::Auto);
+static BATTERY: Mutex<Option<(Instant, Option<bool>)>> = Mutex::new(None);
+
+// a battery that discharges, from the power supplies of the kernel; None where they cannot be read
+fn read_battery() -> Option<bool> {
+    let supplies = fs::read_dir("/sys/class/power_supply").ok()?;
+    let statuses: Vec<String> = supplies.flatten()
+        .filter(|supply| fs::read_to_string(supply.path().join("type")).is_ok_and(|kind| kind.trim() == "Battery"))
+        .filter_map(|supply| fs::read_to_string(supply.path().join("status")).ok())
+        .collect();
+    (!statuses.is_empty()).then(|| statuses.iter().any(|status| status.trim() == "Discharging"))
+}
+
+pub fn on_battery() -> Option<bool> {
+    let mut battery = BATTERY.lock().unwrap();
+    match *battery {
+        Some((read, state)) if read.elapsed() < BATTERY_CHECK_INTERVAL => state,
+        _ => {
+            let state = read_battery();
+            *battery = Some((Instant::now(), state));
+            state
+        }
+    }
+}
+
+pub fn power_mode() -> PowerMode {
+    *POWER_MODE.lock().unwrap()
+}
+
+// from the frontend, which may know the battery where the backend cannot read it
+#[tauri::command]
+pub fn set_power_mode(mode: PowerMode) {
+    *POWER_MODE.lock().unwrap() = mode;
+}
+
+pub fn low_power() -> bool {
+    match power_mode() {
+        PowerMode::Auto => on_battery() == Some(true),
+        mode => mode == PowerMode::LowPower,
+    }
+}
+
+// the threads of a search that could use "available"
+pub fn threads(available: usize) -> usize {
+    if low_power() { 1 } else { available }
+}
+
+pub fn depth(depth: u8) -> u8 {
+    if low_power() { depth.saturating_sub(LOW_POWER_DEPTH_CUT).max(LOW_POWER_MIN_DEPTH.min(depth)) } else { depth }
+}
+
+// the limits of a move of the engine in a game
+pub fn limits(limits: Limits) -> Limits {
+    if !low_power() {
+        return limits;
+    }
+    let time = limits.time.map(|budget| TimeBudget { soft: budget.soft / 2, ..budget });
+    Limits { depth: depth(limits.depth), time, ..limits }
+}
+
+pub fn ponder_allowed() -> bool {
+    !low_power()
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PowerState {
+    pub mode: PowerMode,
+    pub on_battery: Option<bool>,
+    pub low_power: bool,
+}
+
+#[tauri::command]
+pub fn power_state() -> PowerState {
+    PowerState { mode: power_mode(), on_battery: on_battery(), low_power: low_power() }
+}
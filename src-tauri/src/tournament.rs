@@ -0,0 +1,244 @@
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use crate::session::Session;
+use crate::store::Store;
+
+pub const FILE: &str = "tournaments.json";
+
+// Tournaments of a club or a classroom, the games are played in the app on a shared screen or
+// over the network and their results reported here.
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TournamentFormat { RoundRobin, Knockout }
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PairingResult { FirstWins, SecondWins, Draw }
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Pairing {
+    pub first: usize, // index of the player moving first
+    pub second: Option<usize>, // None for a bye, which counts as a win
+    pub result: Option<PairingResult>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Tournament {
+    pub id: u32,
+    pub name: String,
+    pub format: TournamentFormat,
+    pub players: Vec<String>,
+    // all the rounds of a round robin, the rounds of a knockout as they are reached
+    pub rounds: Vec<Vec<Pairing>>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Standing {
+    pub player: String,
+    pub points: f64,
+    pub sonneborn_berger: f64, // points of the beaten opponents plus half the points of the drawn ones
+    pub wins: u32,
+    pub games: u32,
+}
+
+fn pairing(first: usize, second: Option<usize>) -> Pairing {
+    let result = if second.is_none() { Some(PairingResult::FirstWins) } else { None };
+    Pairing { first, second, result }
+}
+
+// circle method: the first player stays, the others rotate, an odd field gets a bye each round
+fn round_robin(players: usize) -> Vec<Vec<Pairing>> {
+    let mut circle: Vec<Option<usize>> = (0..players).map(Some).collect();
+    if players % 2 == 1 {
+        circle.push(None);
+    }
+    let n = circle.len();
+    (0..n - 1).map(|round| {
+        let pairings = (0..n / 2).filter_map(|i| {
+            let (a, b) = (circle[i], circle[n - 1 - i]);
+            // the colors alternate from one round to the next
+            let (a, b) = if (round + i) % 2 == 0 { (a, b) } else { (b, a) };
+            match (a, b) {
+                (Some(a), b) => Some(pairing(a, b)),
+                (None, Some(b)) => Some(pairing(b, None)),
+                (None, None) => None,
+            }
+        }).collect();
+        circle[1..].rotate_right(1);
+        pairings
+    }).collect()
+}
+
+// the first seed meets the last, byes go to the best seeds when the field is not a power of two
+fn knockout_round(seeds: &[usize]) -> Vec<Pairing> {
+    let size = seeds.len().next_power_of_two();
+    (0..size / 2).filter_map(|i| {
+        let first = *seeds.get(i)?;
+        Some(pairing(first, seeds.get(size - 1 - i).copied()))
+    }).collect()
+}
+
+fn winner(pairing: &Pairing) -> Option<usize> {
+    match pairing.result? {
+        PairingResult::FirstWins => Some(pairing.first),
+        PairingResult::SecondWins => pairing.second,
+        PairingResult::Draw => None,
+    }
+}
+
+impl Tournament {
+    fn new(id: u32, name: String, format: TournamentFormat, players: Vec<String>) -> Self {
+        let seeds: Vec<usize> = (0..players.len()).collect();
+        let rounds = match format {
+            TournamentFormat::RoundRobin => round_robin(players.len()),
+            TournamentFormat::Knockout => vec![knockout_round(&seeds)],
+        };
+        Tournament { id, name, format, players, rounds }
+    }
+
+    pub fn report(&mut self, round: usize, board: usize, result: PairingResult) -> Result<(), String> {
+        if self.format == TournamentFormat::Knockout && result == PairingResult::Draw {
+            return Err("a knockout game needs a winner".into());
+        }
+        let last_round = self.rounds.len();
+        let pairing = self.rounds.get_mut(round).and_then(|pairings| pairings.get_mut(board))
+            .ok_or("there is no such game")?;
+        if pairing.second.is_none() {
+            return Err("this is a bye".into());
+        }
+        if self.format == TournamentFormat::Knockout && round + 1 < last_round {
+            return Err("the next round has already started".into());
+        }
+        pairing.result = Some(result);
+        // the winners of a complete knockout round meet in the next one
+        if self.format == TournamentFormat::Knockout {
+            let current = &self.rounds[round];
+            if current.len() > 1 && current.iter().all(|p| p.result.is_some()) {
+                let winners: Vec<usize> = current.iter().filter_map(winner).collect();
+                self.rounds.push(knockout_round(&winners));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn standings(&self) -> Vec<Standing> {
+        let mut points = vec![0.0; self.players.len()];
+        let mut wins = vec![0; self.players.len()];
+        let mut games = vec![0; self.players.len()];
+        let pairings = || self.rounds.iter().flatten().filter(|p| p.result.is_some());
+        for pairing in pairings() {
+            games[pairing.first] += 1;
+            if let Some(second) = pairing.second {
+                games[second] += 1;
+            }
+            match winner(pairing) {
+                Some(player) => {
+                    points[player] += 1.0;
+                    wins[player] += 1;
+                }
+                None => {
+                    points[pairing.first] += 0.5;
+                    points[pairing.second.unwrap_or(pairing.first)] += 0.5;
+                }
+            }
+        }
+        let mut sonneborn_berger = vec![0.0; self.players.len()];
+        for pairing in pairings() {
+            let Some(second) = pairing.second else { continue };
+            match winner(pairing) {
+                Some(player) => {
+                    let loser = if player == pairing.first { second } else { pairing.first };
+                    sonneborn_berger[player] += points[loser];
+                }
+                None => {
+                    sonneborn_berger[pairing.first] += points[second] / 2.0;
+                    sonneborn_berger[second] += points[pairing.first] / 2.0;
+                }
+            }
+        }
+        let mut standings: Vec<Standing> = self.players.iter().enumerate().map(|(i, player)| Standing {
+            player: player.clone(),
+            points: points[i],
+            sonneborn_berger: sonneborn_berger[i],
+            wins: wins[i],
+            games: games[i],
+        }).collect();
+        standings.sort_by(|a, b| {
+            b.points.total_cmp(&a.points)
+                .then(b.sonneborn_berger.total_cmp(&a.sonneborn_berger))
+                .then(b.wins.cmp(&a.wins))
+        });
+        standings
+    }
+}
+
+fn load(store: &Store) -> Vec<Tournament> {
+    store.load(FILE).unwrap_or_default()
+}
+
+#[tauri::command]
+pub fn create_tournament(
+    store: State<Store>,
+    name: String,
+    players: Vec<String>,
+    format: TournamentFormat,
+) -> Result<Tournament, String> {
+    if players.len() < 2 {
+        return Err("a tournament needs at least two players".into());
+    }
+    let mut tournaments = load(&store);
+    let id = tournaments.iter().map(|t| t.id + 1).max().unwrap_or(1);
+    let tournament = Tournament::new(id, name, format, players);
+    tournaments.push(tournament.clone());
+    store.save(FILE, &tournaments)?;
+    Ok(tournament)
+}
+
+#[tauri::command]
+pub fn tournaments(store: State<Store>) -> Vec<Tournament> {
+    load(&store)
+}
+
+#[tauri::command]
+pub fn delete_tournament(store: State<Store>, id: u32) -> Result<(), String> {
+    let mut tournaments = load(&store);
+    tournaments.retain(|t| t.id != id);
+    store.save(FILE, &tournaments)
+}
+
+// without "result", the result of the game just finished in the app, whose first player
+// is the first player of the pairing
+#[tauri::command]
+pub fn report_tournament_result(
+    store: State<Store>,
+    session: State<Mutex<Session>>,
+    id: u32,
+    round: usize,
+    board: usize,
+    result: Option<PairingResult>,
+) -> Result<Tournament, String> {
+    let result = match result {
+        Some(result) => result,
+        None => match session.lock().unwrap().result().ok_or("the game is not over")?.winner {
+            Some(false) => PairingResult::FirstWins,
+            Some(true) => PairingResult::SecondWins,
+            None => PairingResult::Draw,
+        },
+    };
+    let mut tournaments = load(&store);
+    let tournament = tournaments.iter_mut().find(|t| t.id == id).ok_or("there is no such tournament")?;
+    tournament.report(round, board, result)?;
+    let tournament = tournament.clone();
+    store.save(FILE, &tournaments)?;
+    Ok(tournament)
+}
+
+#[tauri::command]
+pub fn tournament_standings(store: State<Store>, id: u32) -> Result<Vec<Standing>, String> {
+    load(&store).iter().find(|t| t.id == id).map(Tournament::standings).ok_or("there is no such tournament".into())
+}
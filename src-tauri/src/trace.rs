@@ -0,0 +1,185 @@
+use std::fs;
+use std::sync::Arc;
+use serde::Serialize;
+use crate::notation;
+use crate::shogi::{self, Limits, Pieces, Search, SearchControl};
+
+// Trace of the search for a single move, to answer "why did it play that?". Every node visited is
+// recorded with its bounds, its score and how the search left it, then the tree is written to a
+// compact file that query_trace browses node by node.
+
+// beyond this, the rest of the search goes unrecorded
+const MAX_NODES: usize = 4_000_000;
+const MAGIC: &[u8; 8] = b"CTLTRACE";
+const RECORD_SIZE: usize = 26;
+// parent of the root of each iteration
+const NO_PARENT: u32 = u32::MAX;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Exit {
+    Exact, // the score lies between the bounds
+    Cutoff, // a move reached beta (alpha for the minimizing side), the other moves were skipped
+    FailLow, // no move reached the bounds
+    Table, // answered by the transposition table
+    Leaf, // evaluated at depth 0
+    Terminal, // the game is over
+    Stopped, // the search was stopped
+}
+
+impl Exit {
+    fn from_byte(byte: u8) -> Option<Exit> {
+        [Exit::Exact, Exit::Cutoff, Exit::FailLow, Exit::Table, Exit::Leaf, Exit::Terminal, Exit::Stopped].get(byte as usize).copied()
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Node {
+    parent: u32,
+    key: u64, // encode_pieces of the position
+    depth: u8,
+    alpha: i32,
+    beta: i32,
+    score: i32,
+    exit: Exit,
+}
+
+#[derive(Default)]
+pub struct Trace {
+    nodes: Vec<Node>,
+    stack: Vec<u32>, // the nodes being searched, from the root
+    truncated: bool,
+    pub table_hit: bool, // set by the search when the table answers the node it is in
+}
+
+impl Trace {
+    // the index of the node, None once the trace is full
+    pub fn enter(&mut self, key: u64, depth: u8, alpha: i32, beta: i32) -> Option<u32> {
+        if self.nodes.len() >= MAX_NODES {
+            self.truncated = true;
+            return None;
+        }
+        let index = self.nodes.len() as u32;
+        let parent = self.stack.last().copied().unwrap_or(NO_PARENT);
+        self.nodes.push(Node { parent, key, depth, alpha, beta, score: 0, exit: Exit::Exact });
+        self.stack.push(index);
+        self.table_hit = false;
+        Some(index)
+    }
+
+    pub fn exit(&mut self, index: u32, score: i32, exit: Exit) {
+        let node = &mut self.nodes[index as usize];
+        node.score = score;
+        node.exit = exit;
+        self.stack.pop();
+        self.table_hit = false;
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(MAGIC.len() + 1 + self.nodes.len() * RECORD_SIZE);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(self.truncated as u8);
+        for node in &self.nodes {
+            bytes.extend_from_slice(&node.parent.to_le_bytes());
+            bytes.extend_from_slice(&node.key.to_le_bytes());
+            bytes.push(node.depth);
+            bytes.extend_from_slice(&node.alpha.to_le_bytes());
+            bytes.extend_from_slice(&node.beta.to_le_bytes());
+            bytes.extend_from_slice(&node.score.to_le_bytes());
+            bytes.push(node.exit as u8);
+        }
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Trace> {
+        let records = bytes.strip_prefix(MAGIC)?;
+        let (&truncated, records) = records.split_first()?;
+        if records.len() % RECORD_SIZE != 0 {
+            return None;
+        }
+        let nodes = records.chunks(RECORD_SIZE).map(|record| {
+            let int = |at: usize| i32::from_le_bytes(record[at..at + 4].try_into().unwrap());
+            Some(Node {
+                parent: u32::from_le_bytes(record[0..4].try_into().unwrap()),
+                key: u64::from_le_bytes(record[4..12].try_into().unwrap()),
+                depth: record[12],
+                alpha: int(13),
+                beta: int(17),
+                score: int(21),
+                exit: Exit::from_byte(record[25])?,
+            })
+        }).collect::<Option<_>>()?;
+        Some(Trace { nodes, truncated: truncated != 0, ..Trace::default() })
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TraceSummary {
+    pub nodes: usize,
+    pub truncated: bool,
+    pub best_move: String,
+    pub score: i32,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TraceNodeView {
+    pub index: u32,
+    pub position: String,
+    pub depth: u8,
+    pub alpha: i32,
+    pub beta: i32,
+    pub score: i32,
+    pub exit: Exit,
+    pub children: Vec<u32>,
+}
+
+// the best move at "depth" with the search traced into "path"
+pub fn trace_move(pieces: &Pieces, played: &[Pieces], turn: bool, depth: u8, path: &str) -> Result<TraceSummary, String> {
+    let mut search = Search::new(Arc::new(SearchControl::default()));
+    search.start_trace();
+    let result = shogi::best_move(&mut search, pieces, played, Limits::depth(depth), turn, &mut |_| ()).ok_or("no legal move")?;
+    let trace = search.take_trace().unwrap_or_default();
+    fs::write(path, trace.bytes()).map_err(|err| format!("cannot write {path}: {err}"))?;
+    Ok(TraceSummary {
+        nodes: trace.nodes.len(),
+        truncated: trace.truncated,
+        best_move: notation::move_name(pieces, result.mov),
+        score: result.score,
+    })
+}
+
+#[tauri::command(async)]
+pub fn trace_search(pieces: Pieces, played: Vec<Pieces>, turn: bool, depth: u8, path: String) -> Result<TraceSummary, String> {
+    shogi::validate(&pieces)?;
+    trace_move(&pieces, &played, turn, depth, &path)
+}
+
+// the node "index" and its children, or the roots of the iterations when no index is given
+#[tauri::command]
+pub fn query_trace(path: String, index: Option<u32>) -> Result<Vec<TraceNodeView>, String> {
+    let bytes = fs::read(&path).map_err(|err| format!("cannot read {path}: {err}"))?;
+    let trace = Trace::from_bytes(&bytes).ok_or("not a search trace")?;
+    let children = |parent: u32| -> Vec<u32> {
+        (0..trace.nodes.len() as u32).filter(|&i| trace.nodes[i as usize].parent == parent).collect()
+    };
+    let view = |index: u32| -> Result<TraceNodeView, String> {
+        let node = trace.nodes.get(index as usize).ok_or("no such node")?;
+        let (pieces, turn) = shogi::decode_pieces(node.key).ok_or("invalid position in the trace")?;
+        Ok(TraceNodeView {
+            index,
+            position: notation::position_string(&pieces, turn),
+            depth: node.depth,
+            alpha: node.alpha,
+            beta: node.beta,
+            score: node.score,
+            exit: node.exit,
+            children: children(index),
+        })
+    };
+    match index {
+        Some(index) => Ok(vec![view(index)?]),
+        None => children(NO_PARENT).into_iter().map(view).collect(),
+    }
+}
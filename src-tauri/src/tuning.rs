@@ -0,0 +1,188 @@
+use std::fs;
+use std::sync::Arc;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State};
+use crate::endgame;
+use crate::notation;
+use crate::selfplay::{self, Adjudication};
+use crate::shogi::{self, EvalParams, Kind, Limits, Search};
+use crate::store::Store;
+
+// edited by hand, the missing weights keep their default value
+const FILE: &str = "eval.toml";
+
+// Installs the evaluation weights of the parameter file, the defaults if there is none.
+// Searches that are already running keep the weights they started with.
+pub fn load(store: &Store) -> Result<EvalParams, String> {
+    let path = store.path(FILE);
+    let params = if path.exists() {
+        let text = fs::read_to_string(&path).map_err(|err| err.to_string())?;
+        toml::from_str(&text).map_err(|err| format!("{FILE}: {err}"))?
+    } else {
+        EvalParams::default()
+    };
+    shogi::set_eval_params(params.clone());
+    Ok(params)
+}
+
+pub fn save(store: &Store, params: &EvalParams) -> Result<(), String> {
+    let text = toml::to_string(params).map_err(|err| err.to_string())?;
+    fs::create_dir_all(store.path("")).map_err(|err| err.to_string())?;
+    fs::write(store.path(FILE), text).map_err(|err| err.to_string())
+}
+
+// the weights adjusted by the tuner, the lion value only decides who wins and is left alone
+const TUNED_KINDS: [Kind; 4] = [Kind::Chick, Kind::Elephant, Kind::Giraffe, Kind::Hen];
+
+fn tuned_values(params: &EvalParams) -> Vec<f64> {
+    let mut values: Vec<f64> = TUNED_KINDS.iter().map(|&kind| params.piece_value[kind as usize] as f64).collect();
+    values.push(params.mobility as f64);
+    values.push(params.lion_safety as f64);
+    values
+}
+
+fn with_tuned_values(params: &EvalParams, values: &[f64]) -> EvalParams {
+    let mut params = params.clone();
+    for (&kind, value) in TUNED_KINDS.iter().zip(values) {
+        params.piece_value[kind as usize] = value.round() as i32;
+    }
+    params.mobility = values[4].round() as i32;
+    params.lion_safety = values[5].round() as i32;
+    params
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TuneProgress {
+    iteration: u32,
+    iterations: u32,
+    params: EvalParams,
+}
+
+// SPSA gains, the perturbation and the step shrink as the tuning converges
+const PERTURBATION: f64 = 4.0;
+const STEP: f64 = 8.0;
+// opening moves played at random so that the games of an iteration differ from the previous ones
+const RANDOM_PLIES: usize = 4;
+
+// Simultaneous perturbation stochastic approximation: at each iteration, every weight is moved
+// up or down at random, the two resulting sets of weights play a pair of games against each other
+// and the weights move in the direction of the winner.
+pub fn tune(start: &EvalParams, iterations: u32, depth: u8, seed: u64, on_progress: &mut dyn FnMut(u32, &EvalParams)) -> EvalParams {
+    let mut rng = selfplay::Rng::new(seed);
+    let mut values = tuned_values(start);
+    for k in 0..iterations {
+        let c = PERTURBATION / ((k + 1) as f64).powf(0.101);
+        let a = STEP / ((k + 1) as f64).powf(0.602);
+        let delta: Vec<f64> = values.iter().map(|_| rng.sign()).collect();
+        let shifted = |sign: f64| {
+            let values: Vec<f64> = values.iter().zip(&delta).map(|(v, d)| v + sign * c * d).collect();
+            Arc::new(with_tuned_values(start, &values))
+        };
+        let (plus, minus) = (shifted(1.0), shifted(-1.0));
+        // between -1 and 1, positive when the weights moved up did better
+        let outcome = selfplay::play_pair([&plus, &minus], depth, RANDOM_PLIES, Some(&Adjudication::default()), rng.next_u64()) - 1.0;
+        for (value, d) in values.iter_mut().zip(&delta) {
+            *value = (*value + a / c * outcome * d).max(0.0);
+        }
+        on_progress(k + 1, &with_tuned_values(start, &values));
+    }
+    with_tuned_values(start, &values)
+}
+
+// tunes the weights in use and writes the result to the parameter file, where it is loaded
+#[tauri::command(async)]
+pub fn tune_eval_params(app: AppHandle, store: State<Store>, iterations: u32, depth: u8) -> Result<EvalParams, String> {
+    let params = tune(&shogi::eval_params(), iterations, depth, selfplay::clock_seed(), &mut |iteration, params| {
+        let _ = app.emit("tune-progress", TuneProgress { iteration, iterations, params: params.clone() });
+    });
+    save(&store, &params)?;
+    load(&store)
+}
+
+#[tauri::command]
+pub fn reload_eval_params(store: State<Store>) -> Result<EvalParams, String> {
+    load(&store)
+}
+
+// the weights in use, a starting point for a parameter file
+#[tauri::command]
+pub fn eval_params() -> Result<String, String> {
+    toml::to_string(&*shogi::eval_params()).map_err(|err| err.to_string())
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Disagreement {
+    pub position: String,
+    pub scores: [i32; 2], // from the point of view of the first player
+    pub moves: [String; 2],
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvalComparison {
+    pub positions: usize,
+    pub mean_difference: f64, // of the scores, proven wins and losses left out
+    pub different_moves: usize,
+    pub disagreements: Vec<Disagreement>, // the largest score differences first
+}
+
+// Two sets of weights side by side on the same positions, searched at "depth". A quicker answer
+// than a match when one term of the evaluation changes.
+pub fn compare(positions: &[String], params: [EvalParams; 2], depth: u8, min_difference: i32) -> Result<EvalComparison, String> {
+    let params = params.map(Arc::new);
+    let mut disagreements = vec![];
+    let (mut total, mut scored, mut different_moves) = (0, 0, 0);
+    for text in positions {
+        let (pieces, turn) = notation::parse_position(text).ok_or_else(|| format!("invalid position {text}"))?;
+        if endgame::lost(&pieces, turn) {
+            continue;
+        }
+        let results = params.clone().map(|params| {
+            let mut search = Search::with_params(Arc::default(), params);
+            shogi::best_move(&mut search, &pieces, &[], Limits::depth(depth), turn, &mut |_| ())
+        });
+        let [Some(first), Some(second)] = results else { continue };
+        let results = [first, second];
+        let scores = [results[0].score, results[1].score];
+        let difference = (scores[0] - scores[1]).abs();
+        if scores.iter().all(|score| score.abs() < 100000) {
+            total += difference;
+            scored += 1;
+        }
+        let different_move = results[0].mov != results[1].mov;
+        different_moves += different_move as usize;
+        if different_move || difference >= min_difference {
+            disagreements.push((difference, Disagreement {
+                position: text.clone(),
+                scores,
+                moves: results.map(|result| notation::move_name(&pieces, result.mov)),
+            }));
+        }
+    }
+    disagreements.sort_by_key(|&(difference, _)| std::cmp::Reverse(difference));
+    Ok(EvalComparison {
+        positions: positions.len(),
+        mean_difference: total as f64 / scored.max(1) as f64,
+        different_moves,
+        disagreements: disagreements.into_iter().map(|(_, disagreement)| disagreement).collect(),
+    })
+}
+
+// the weights are given in the format of the parameter file, the installed ones when "a" is missing
+#[tauri::command(async)]
+pub fn compare_eval_params(
+    positions: Vec<String>,
+    a: Option<String>,
+    b: String,
+    depth: u8,
+    min_difference: Option<i32>,
+) -> Result<EvalComparison, String> {
+    let parse = |text: &str| toml::from_str::<EvalParams>(text).map_err(|err| err.to_string());
+    let a = match a {
+        Some(text) => parse(&text)?,
+        None => (*shogi::eval_params()).clone(),
+    };
+    compare(&positions, [a, parse(&b)?], depth, min_difference.unwrap_or(20))
+}
@@ -0,0 +1,100 @@
+use std::sync::{Arc, Mutex};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use crate::endgame;
+use crate::notation;
+use crate::session::Session;
+use crate::shogi::{self, Limits, Move, Search};
+
+// "Are you sure?" before a move is played: a shallow search of the position and of the position
+// after the move, with a table kept from one check to the next so that checking the moves of the
+// same position again costs almost nothing.
+
+const CHECK_DEPTH: u8 = 5;
+// what the move costs the player, in points of the evaluation
+const INACCURACY_LOSS: i32 = 15;
+const MATERIAL_LOSS: i32 = 35;
+const MATE: i32 = 100000;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Severity {
+    Inaccuracy,
+    Material, // about a chick or more, counting the one the opponent gets in hand
+    Decisive, // the opponent can force a win, which the best move would not allow
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MoveWarning {
+    pub severity: Severity,
+    pub loss: i32,
+    pub best: String,
+    pub reply: Option<String>, // the refutation
+}
+
+#[derive(Default)]
+pub struct MoveChecker(Mutex<Search>);
+
+fn severity(best: i32, after: i32) -> Option<Severity> {
+    if after <= -MATE && best > -MATE {
+        Some(Severity::Decisive)
+    } else if best - after >= MATERIAL_LOSS {
+        Some(Severity::Material)
+    } else if best - after >= INACCURACY_LOSS {
+        Some(Severity::Inaccuracy)
+    } else {
+        None
+    }
+}
+
+impl MoveChecker {
+    // the warning for "mov" in the position of the session, None for a good enough move
+    pub fn check(&self, session: &Session, mov: Move) -> Option<MoveWarning> {
+        let (pieces, turn) = (session.pieces(), session.turn());
+        let mut search = self.0.lock().unwrap();
+        search.resume(Arc::default());
+        let limits = Limits::depth(CHECK_DEPTH).with_rules(session.rules(), session.ply());
+        let best = shogi::best_move(&mut search, pieces, &session.played(), limits, turn, &mut |_| ())?;
+        if best.mov == mov {
+            return None;
+        }
+        let child = shogi::play_move(pieces, mov);
+        let sign = if turn { -1 } else { 1 };
+        let after = if endgame::lost(&child, !turn) {
+            MATE
+        } else {
+            search.set_rules(session.rules(), session.moves_left().map(|left| left.saturating_sub(1)));
+            sign * search.evaluate(&child, !turn, CHECK_DEPTH - 1)?
+        };
+        let best_score = sign * best.score;
+        let severity = severity(best_score, after)?;
+        let reply = (!endgame::lost(&child, !turn)).then(|| {
+            let limits = Limits::depth(CHECK_DEPTH - 1).with_rules(session.rules(), session.ply() + 1);
+            shogi::best_move(&mut search, &child, &[], limits, !turn, &mut |_| ()).map(|reply| reply.mov)
+        }).flatten();
+        Some(MoveWarning {
+            severity,
+            loss: (best_score - after).clamp(0, MATE),
+            best: notation::move_name(pieces, best.mov),
+            reply: reply.map(|reply| notation::move_name(&child, reply)),
+        })
+    }
+}
+
+// before the move is played, None when the move is fine or the level does not ask for warnings
+#[tauri::command]
+pub fn check_move(
+    session: State<Mutex<Session>>,
+    checker: State<MoveChecker>,
+    from: usize,
+    to: usize,
+    promote: Option<bool>,
+) -> Result<Option<MoveWarning>, String> {
+    let session = session.lock().unwrap();
+    let Some(threshold) = session.ai_level().and_then(|level| level.move_warnings) else { return Ok(None) };
+    let mov = shogi::legal_move(session.pieces(), &session.rules(), from, to, promote)
+        .filter(|mov| session.pieces()[mov.from].owner == session.turn())
+        .ok_or("illegal move")?;
+    Ok(checker.check(&session, mov).filter(|warning| warning.severity >= threshold))
+}